@@ -0,0 +1,353 @@
+use crate::config::Config;
+use crate::scanner::select_files;
+use crate::source::{FileSource, FileStat, MemorySource};
+use anyhow::{bail, Context, Result};
+use chrono::NaiveDate;
+use flate2::read::GzDecoder;
+use log::{debug, info, warn};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Read, Seek};
+use std::path::{Component, Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// Unix file type bits of a symbolic link
+const S_IFLNK: u32 = 0o120000;
+const S_IFMT: u32 = 0o170000;
+
+/// Archive formats that can be packed without extracting them
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    Tar,
+    TarGz,
+    TarZst,
+    Zip,
+}
+
+impl ArchiveFormat {
+    /// The format of an archive, from its file name
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_str()?.to_lowercase();
+        if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Some(Self::TarGz)
+        } else if name.ends_with(".tar.zst") || name.ends_with(".tzst") {
+            Some(Self::TarZst)
+        } else if name.ends_with(".tar") {
+            Some(Self::Tar)
+        } else if name.ends_with(".zip") {
+            Some(Self::Zip)
+        } else {
+            None
+        }
+    }
+}
+
+/// A regular file of an archive, before its content is read
+struct Listed {
+    /// Position of the entry in the archive
+    index: usize,
+    path: PathBuf,
+    size: u64,
+    modified: Option<SystemTime>,
+    /// The content of `.gitignore` and `.remixignore` files, read up front
+    /// since they decide which other files are read
+    rules: Option<String>,
+}
+
+/// A regular file read from an archive
+struct Entry {
+    path: PathBuf,
+    data: Vec<u8>,
+    modified: Option<SystemTime>,
+}
+
+/// The files of a `.tar`, `.tar.gz`, `.tar.zst` or `.zip` archive.
+///
+/// The archive is read in two passes, and nothing is extracted to disk. The
+/// first only lists the files, their sizes and the ignore files; the second
+/// reads the files that the ignore and include config would pack into
+/// memory. A single top-level directory, as in release archives, is left out
+/// of the paths.
+pub struct ArchiveSource {
+    path: PathBuf,
+    files: MemorySource,
+}
+
+impl ArchiveSource {
+    /// Read the files of an archive that the config would pack, leaving out
+    /// files larger than `max_file_size`
+    pub fn open(path: &Path, format: ArchiveFormat, config: &Config) -> Result<Self> {
+        info!("Reading archive {}", path.display());
+
+        let open = || {
+            File::open(path).with_context(|| format!("Failed to open archive: {}", path.display()))
+        };
+        let entries = match format {
+            ArchiveFormat::Zip => read_zip(open()?, config),
+            _ => {
+                let listed = list_tar(tar_stream(open()?, format)?, config)?;
+                let selected = select_entries(listed, config);
+                read_tar(tar_stream(open()?, format)?, selected)
+            }
+        }
+        .with_context(|| format!("Failed to read archive: {}", path.display()))?;
+
+        let mut files = MemorySource::new();
+        for entry in entries {
+            files.insert(entry.path, entry.data, entry.modified);
+        }
+        Ok(Self {
+            path: path.to_path_buf(),
+            files,
+        })
+    }
+}
+
+impl FileSource for ArchiveSource {
+    fn list(&self) -> Result<Vec<PathBuf>> {
+        self.files.list()
+    }
+
+    fn read(&self, path: &Path) -> Result<Vec<u8>> {
+        self.files.read(path)
+    }
+
+    fn stat(&self, path: &Path) -> Result<FileStat> {
+        self.files.stat(path)
+    }
+
+    fn describe(&self) -> String {
+        format!("archive {}", self.path.display())
+    }
+
+    fn path(&self, relative_path: &Path) -> PathBuf {
+        self.path.join(relative_path)
+    }
+}
+
+/// The decompressed tar stream of an archive
+fn tar_stream(file: File, format: ArchiveFormat) -> Result<Box<dyn Read>> {
+    Ok(match format {
+        ArchiveFormat::TarGz => Box::new(GzDecoder::new(file)),
+        ArchiveFormat::TarZst => Box::new(zstd::Decoder::new(file)?),
+        _ => Box::new(file),
+    })
+}
+
+/// Whether a file holds ignore rules that apply to the other files
+fn is_ignore_file(path: &Path) -> bool {
+    path.file_name()
+        .is_some_and(|name| name == ".gitignore" || name == ".remixignore")
+}
+
+/// List the regular files of a tar stream without reading their content
+fn list_tar(reader: impl Read, config: &Config) -> Result<Vec<Listed>> {
+    let mut archive = tar::Archive::new(reader);
+    let mut listed = Vec::new();
+    for (index, entry) in archive.entries()?.enumerate() {
+        let entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let path = match entry_path(&entry.path()?) {
+            Some(path) => path,
+            None => continue,
+        };
+        let size = entry.header().size()?;
+        let modified = entry
+            .header()
+            .mtime()
+            .ok()
+            .map(|seconds| SystemTime::UNIX_EPOCH + Duration::from_secs(seconds));
+        let rules = if is_ignore_file(&path) && size <= config.max_file_size {
+            let mut data = Vec::new();
+            entry.take(size).read_to_end(&mut data)?;
+            Some(String::from_utf8_lossy(&data).into_owned())
+        } else {
+            None
+        };
+        listed.push(Listed {
+            index,
+            path,
+            size,
+            modified,
+            rules,
+        });
+    }
+    Ok(listed)
+}
+
+/// Read the content of the selected files of a tar stream, which is listed
+/// again in the same order
+fn read_tar(reader: impl Read, selected: Vec<Listed>) -> Result<Vec<Entry>> {
+    let mut archive = tar::Archive::new(reader);
+    let mut selected = selected.into_iter().peekable();
+    let mut entries = Vec::new();
+    for (index, entry) in archive.entries()?.enumerate() {
+        let entry = entry?;
+        let listed = match selected.next_if(|listed| listed.index == index) {
+            Some(listed) => listed,
+            None => continue,
+        };
+        let data = read_body(entry, &listed)?;
+        entries.push(Entry {
+            path: listed.path,
+            data,
+            modified: listed.modified,
+        });
+    }
+    if let Some(listed) = selected.next() {
+        bail!("{} is missing from the archive", listed.path.display());
+    }
+    Ok(entries)
+}
+
+/// Read the regular files of a zip archive that the config would pack
+fn read_zip(reader: impl Read + Seek, config: &Config) -> Result<Vec<Entry>> {
+    let mut archive = zip::ZipArchive::new(reader)?;
+    let mut listed = Vec::new();
+    for index in 0..archive.len() {
+        let mut file = archive.by_index(index)?;
+        let is_link = file
+            .unix_mode()
+            .is_some_and(|mode| mode & S_IFMT == S_IFLNK);
+        if !file.is_file() || is_link {
+            continue;
+        }
+        let path = match file.enclosed_name().as_deref().and_then(entry_path) {
+            Some(path) => path,
+            None => continue,
+        };
+        let size = file.size();
+        let rules = if is_ignore_file(&path) && size <= config.max_file_size {
+            let mut data = Vec::new();
+            (&mut file).take(size).read_to_end(&mut data)?;
+            Some(String::from_utf8_lossy(&data).into_owned())
+        } else {
+            None
+        };
+        listed.push(Listed {
+            index,
+            path,
+            size,
+            modified: zip_time(&file),
+            rules,
+        });
+    }
+
+    let selected = select_entries(listed, config);
+    let mut entries = Vec::new();
+    for listed in selected {
+        let data = read_body(archive.by_index(listed.index)?, &listed)?;
+        entries.push(Entry {
+            path: listed.path,
+            data,
+            modified: listed.modified,
+        });
+    }
+    Ok(entries)
+}
+
+/// The modification time of a zip entry. Zip times have no time zone; they
+/// are taken as UTC.
+fn zip_time(file: &zip::read::ZipFile<impl Read>) -> Option<SystemTime> {
+    let time = file.last_modified()?;
+    let date = NaiveDate::from_ymd_opt(time.year().into(), time.month().into(), time.day().into())?;
+    let time = date.and_hms_opt(
+        time.hour().into(),
+        time.minute().into(),
+        time.second().into(),
+    )?;
+    Some(SystemTime::from(time.and_utc()))
+}
+
+/// Keep the listed files that the ignore and include config would pack,
+/// with the top-level directory left out of their paths if they share one
+fn select_entries(listed: Vec<Listed>, config: &Config) -> Vec<Listed> {
+    let files: Vec<(PathBuf, Listed)> = strip_common_root(listed)
+        .into_iter()
+        .filter(|listed| {
+            let small = listed.size <= config.max_file_size;
+            if !small {
+                debug!(
+                    "Skipping large file: {} ({} bytes)",
+                    listed.path.display(),
+                    listed.size
+                );
+            }
+            small
+        })
+        .map(|listed| (listed.path.clone(), listed))
+        .collect();
+    let rules: HashMap<PathBuf, String> = files
+        .iter()
+        .filter_map(|(path, listed)| Some((path.clone(), listed.rules.clone()?)))
+        .collect();
+    let read = |path: &Path| rules.get(path).cloned();
+    select_files(files, read, config)
+        .into_iter()
+        .map(|(_, listed)| listed)
+        .collect()
+}
+
+/// Read the content of an entry, no further than the size its header
+/// declares. Entries larger than `max_file_size` were left out, so this also
+/// bounds what the whole archive takes in memory.
+fn read_body(reader: impl Read, listed: &Listed) -> Result<Vec<u8>> {
+    let mut data = Vec::with_capacity(listed.size as usize);
+    reader
+        .take(listed.size + 1)
+        .read_to_end(&mut data)
+        .with_context(|| format!("Failed to read {}", listed.path.display()))?;
+    if data.len() as u64 > listed.size {
+        bail!(
+            "{} is larger than the archive says ({} bytes)",
+            listed.path.display(),
+            listed.size
+        );
+    }
+    Ok(data)
+}
+
+/// The relative path of an archive entry, without `./` segments, or `None`
+/// for absolute paths and paths that leave the archive
+fn entry_path(path: &Path) -> Option<PathBuf> {
+    let mut relative = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(name) => relative.push(name),
+            Component::CurDir => {}
+            _ => {
+                warn!(
+                    "Skipping archive entry outside the archive: {}",
+                    path.display()
+                );
+                return None;
+            }
+        }
+    }
+    (!relative.as_os_str().is_empty()).then_some(relative)
+}
+
+/// Leave out the top-level directory if every file is in the same one
+fn strip_common_root(mut entries: Vec<Listed>) -> Vec<Listed> {
+    let root = match entries
+        .first()
+        .and_then(|entry| entry.path.components().next())
+    {
+        Some(root) => PathBuf::from(root.as_os_str()),
+        None => return entries,
+    };
+    let shared = entries
+        .iter()
+        .all(|entry| entry.path.starts_with(&root) && entry.path.components().count() > 1);
+    if shared {
+        debug!("Leaving out the top-level directory {}", root.display());
+        for entry in &mut entries {
+            if let Ok(path) = entry.path.strip_prefix(&root) {
+                entry.path = path.to_path_buf();
+            }
+        }
+    }
+    entries
+}
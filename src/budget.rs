@@ -0,0 +1,307 @@
+use crate::compress::compress_content;
+use crate::config::BudgetConfig;
+use crate::packer::FileContent;
+use crate::tokenizer::Tokenizer;
+use anyhow::{anyhow, Result};
+use log::{debug, info};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::SystemTime;
+
+/// Estimated tokens used by the per-file header in the output (path, size, fences)
+const FILE_OVERHEAD_TOKENS: usize = 20;
+
+/// Tokens reserved for the marker appended to truncated files
+const TRUNCATION_MARKER_TOKENS: usize = 16;
+
+/// Don't bother truncating a file if fewer than this many tokens would be kept
+const MIN_TRUNCATED_TOKENS: usize = 64;
+
+/// File names that describe a project and should be packed first
+const MANIFEST_FILES: &[&str] = &[
+    "cargo.toml",
+    "package.json",
+    "pyproject.toml",
+    "setup.py",
+    "setup.cfg",
+    "requirements.txt",
+    "go.mod",
+    "pom.xml",
+    "build.gradle",
+    "build.gradle.kts",
+    "gemfile",
+    "composer.json",
+    "cmakelists.txt",
+    "makefile",
+    "dockerfile",
+];
+
+/// File stems that are usually program entry points
+const ENTRY_POINT_STEMS: &[&str] = &["main", "lib", "index", "app", "__main__", "__init__", "mod"];
+
+/// Order in which files of the same priority tier are packed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BudgetStrategy {
+    /// Smallest files first, to pack as many files as possible
+    Size,
+    /// Most recently modified files first
+    Recency,
+    /// Alphabetical by path
+    Path,
+}
+
+impl BudgetStrategy {
+    pub fn from_name(name: &str) -> Result<Self> {
+        match name.to_lowercase().as_str() {
+            "size" => Ok(Self::Size),
+            "recency" | "recent" => Ok(Self::Recency),
+            "path" => Ok(Self::Path),
+            _ => Err(anyhow!("Unknown budget strategy: {}", name)),
+        }
+    }
+}
+
+/// What happened to a file that did not fit the token budget as-is
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum OmissionKind {
+    /// Only the leading part of the file was kept
+    Truncated,
+    /// Only the signatures of the file were kept
+    Summarized,
+    /// The file was left out entirely
+    Dropped,
+}
+
+impl OmissionKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Truncated => "truncated",
+            Self::Summarized => "summarized",
+            Self::Dropped => "dropped",
+        }
+    }
+}
+
+/// A file that was cut down or left out to fit the token budget
+#[derive(Debug, Serialize, Clone)]
+pub struct OmittedFile {
+    pub relative_path: String,
+    pub kind: OmissionKind,
+    pub original_tokens: usize,
+    pub kept_tokens: usize,
+}
+
+/// Priority tier of a file, lower tiers are packed first
+fn priority_tier(relative_path: &str, priority_patterns: &[String]) -> u8 {
+    let path = relative_path.replace('\\', "/");
+
+    if priority_patterns
+        .iter()
+        .any(|pattern| glob_match::glob_match(&pattern.replace('\\', "/"), &path))
+    {
+        return 0;
+    }
+
+    let file_name = Path::new(&path)
+        .file_name()
+        .map(|f| f.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let stem = Path::new(&file_name)
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let depth = path.matches('/').count();
+
+    if file_name.starts_with("readme") || MANIFEST_FILES.contains(&file_name.as_str()) {
+        return 1;
+    }
+
+    let lower = path.to_lowercase();
+    let is_auxiliary = lower.starts_with("test")
+        || lower.contains("/test")
+        || lower.starts_with("example")
+        || lower.contains("/example")
+        || lower.starts_with("bench")
+        || lower.contains("/bench")
+        || lower.starts_with("docs/")
+        || lower.contains("/docs/")
+        || file_name.contains("_test.")
+        || file_name.contains(".test.")
+        || file_name.contains(".spec.")
+        || file_name.starts_with("test_");
+
+    if is_auxiliary {
+        return 4;
+    }
+
+    if ENTRY_POINT_STEMS.contains(&stem.as_str()) && depth <= 1 {
+        return 2;
+    }
+
+    3
+}
+
+/// Cost of a file in the budget, including its header and diff in the output
+fn file_cost(file: &FileContent, content_tokens: usize, tokenizer: &Tokenizer) -> usize {
+    let diff_tokens = file.diff.as_deref().map_or(0, |diff| tokenizer.count(diff));
+    content_tokens + diff_tokens + tokenizer.count(&file.relative_path) + FILE_OVERHEAD_TOKENS
+}
+
+/// Estimated cost of a file's header and diff, which the budget charges on top
+/// of its content
+pub fn header_cost(file: &FileContent, tokenizer: &Tokenizer) -> usize {
+    file_cost(file, 0, tokenizer)
+}
+
+/// Keep whole leading lines of `content` until `max_tokens` would be exceeded
+fn truncate_to_tokens(content: &str, max_tokens: usize, tokenizer: &Tokenizer) -> (String, usize) {
+    let line_budget = max_tokens.saturating_sub(TRUNCATION_MARKER_TOKENS);
+    let mut kept = String::new();
+    let mut kept_tokens = 0;
+    let mut remaining_lines = 0;
+
+    for line in content.lines() {
+        if remaining_lines > 0 {
+            remaining_lines += 1;
+            continue;
+        }
+
+        let line_tokens = tokenizer.count(line) + 1;
+        if kept_tokens + line_tokens > line_budget {
+            remaining_lines = 1;
+            continue;
+        }
+
+        kept.push_str(line);
+        kept.push('\n');
+        kept_tokens += line_tokens;
+    }
+
+    if remaining_lines > 0 {
+        kept.push_str(&format!(
+            "... [truncated by remix: {} more lines]\n",
+            remaining_lines
+        ));
+    }
+
+    let kept_tokens = tokenizer.count(&kept);
+    (kept, kept_tokens)
+}
+
+/// Select, truncate or summarize files so that the pack stays within the token budget.
+///
+/// Files are considered in priority order: user priority patterns, then READMEs and
+/// manifests, then entry points, then other sources, and finally tests, examples and
+/// docs. Within a tier the configured strategy decides the order. Files keep their
+/// original order in the returned list.
+pub fn apply_token_budget(
+    files: Vec<FileContent>,
+    modified_times: &HashMap<String, SystemTime>,
+    config: &BudgetConfig,
+    compress_enabled: bool,
+    tokenizer: &Tokenizer,
+) -> Result<(Vec<FileContent>, Vec<OmittedFile>)> {
+    let max_tokens = match config.max_tokens {
+        Some(max_tokens) => max_tokens,
+        None => return Ok((files, Vec::new())),
+    };
+    let strategy = BudgetStrategy::from_name(&config.strategy)?;
+
+    info!(
+        "Applying token budget of {} tokens ({:?} strategy)",
+        max_tokens, strategy
+    );
+
+    let tiers: Vec<u8> = files
+        .iter()
+        .map(|file| priority_tier(&file.relative_path, &config.priority_patterns))
+        .collect();
+    let mut order: Vec<usize> = (0..files.len()).collect();
+    order.sort_by(|&a, &b| {
+        let (fa, fb) = (&files[a], &files[b]);
+
+        tiers[a]
+            .cmp(&tiers[b])
+            .then_with(|| match strategy {
+                BudgetStrategy::Size => fa.token_count.cmp(&fb.token_count),
+                BudgetStrategy::Recency => modified_times
+                    .get(&fb.relative_path)
+                    .cmp(&modified_times.get(&fa.relative_path)),
+                BudgetStrategy::Path => std::cmp::Ordering::Equal,
+            })
+            .then_with(|| fa.relative_path.cmp(&fb.relative_path))
+    });
+
+    let mut remaining = max_tokens;
+    let mut kept: Vec<Option<FileContent>> = files.into_iter().map(Some).collect();
+    let mut omitted = Vec::new();
+
+    for index in order {
+        let file = kept[index].as_mut().expect("each file is visited once");
+        let original_tokens = file.token_count;
+
+        let cost = file_cost(file, original_tokens, tokenizer);
+        if cost <= remaining {
+            remaining -= cost;
+            continue;
+        }
+
+        // Try the signatures-only view of the file before cutting it off
+        if !compress_enabled {
+            let summary = compress_content(&file.content, &file.extension);
+            let summary_tokens = tokenizer.count(&summary);
+            let summary_cost = file_cost(file, summary_tokens, tokenizer);
+
+            if summary_tokens < original_tokens && summary_cost <= remaining {
+                remaining -= summary_cost;
+                file.content = summary;
+                file.token_count = summary_tokens;
+                omitted.push(OmittedFile {
+                    relative_path: file.relative_path.clone(),
+                    kind: OmissionKind::Summarized,
+                    original_tokens,
+                    kept_tokens: summary_tokens,
+                });
+                continue;
+            }
+        }
+
+        let available = remaining.saturating_sub(file_cost(file, 0, tokenizer));
+        if available >= MIN_TRUNCATED_TOKENS {
+            let (content, kept_tokens) = truncate_to_tokens(&file.content, available, tokenizer);
+            remaining = remaining.saturating_sub(file_cost(file, kept_tokens, tokenizer));
+            file.content = content;
+            file.token_count = kept_tokens;
+            omitted.push(OmittedFile {
+                relative_path: file.relative_path.clone(),
+                kind: OmissionKind::Truncated,
+                original_tokens,
+                kept_tokens,
+            });
+            continue;
+        }
+
+        debug!(
+            "Dropping {} ({} tokens) to fit the token budget",
+            file.relative_path, original_tokens
+        );
+        omitted.push(OmittedFile {
+            relative_path: file.relative_path.clone(),
+            kind: OmissionKind::Dropped,
+            original_tokens,
+            kept_tokens: 0,
+        });
+        kept[index] = None;
+    }
+
+    omitted.sort_by(|a, b| a.relative_path.cmp(&b.relative_path));
+
+    info!(
+        "{} files were truncated, summarized or dropped to fit the token budget",
+        omitted.len()
+    );
+
+    Ok((kept.into_iter().flatten().collect(), omitted))
+}
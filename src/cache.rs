@@ -0,0 +1,354 @@
+use crate::config::RemoteConfig;
+use crate::utils::format_size;
+use anyhow::{Context, Result};
+use chrono::DateTime;
+use git2::Repository;
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::{self, File, TryLockError};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use walkdir::WalkDir;
+
+/// Metadata file kept in every cached clone
+const METADATA_FILE: &str = "remix-cache.json";
+
+/// Suffix of the lock file held while fetching into a cached clone
+const FETCH_LOCK_SUFFIX: &str = ".lock";
+
+/// Suffix of the lock file held by every checkout that reads a cached clone
+const USE_LOCK_SUFFIX: &str = ".use.lock";
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// A cached clone of a remote repository
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CacheEntry {
+    /// URL the clone was last fetched from
+    pub url: String,
+    /// Last time the clone was used, in seconds since the Unix epoch
+    pub last_used: u64,
+    /// Directory of the bare repository
+    #[serde(skip)]
+    pub path: PathBuf,
+    /// Size on disk in bytes
+    #[serde(skip)]
+    pub size: u64,
+}
+
+/// A lock on a cached clone, shared with other processes or not, released
+/// when it is dropped
+#[derive(Debug)]
+pub struct EntryLock {
+    _file: File,
+}
+
+/// Bare clones of remote repositories, one per normalized URL
+pub struct RepoCache {
+    root: PathBuf,
+}
+
+impl RepoCache {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// The configured cache directory, or the user's cache directory
+    pub fn from_config(config: &RemoteConfig) -> Self {
+        match &config.cache_dir {
+            Some(dir) => Self::new(dir),
+            None => Self::new(default_cache_dir()),
+        }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Directory of the cached clone of a URL
+    pub fn entry_path(&self, url: &str) -> PathBuf {
+        self.root.join(cache_key(url))
+    }
+
+    /// Lock the cached clone of a URL for fetching into it, waiting for other
+    /// processes that are fetching the same URL. Hold it until the fetch is done.
+    pub fn lock_fetch(&self, url: &str) -> Result<EntryLock> {
+        let file = self.lock_file(&self.entry_path(url), FETCH_LOCK_SUFFIX)?;
+        file.lock()
+            .with_context(|| format!("Failed to lock the cached clone of {}", url))?;
+        Ok(EntryLock { _file: file })
+    }
+
+    /// Mark the cached clone of a URL as in use, so that no process evicts it
+    /// while a checkout reads its objects. Several processes can hold it.
+    pub fn lock_use(&self, url: &str) -> Result<EntryLock> {
+        let file = self.lock_file(&self.entry_path(url), USE_LOCK_SUFFIX)?;
+        file.lock_shared()
+            .with_context(|| format!("Failed to lock the cached clone of {}", url))?;
+        Ok(EntryLock { _file: file })
+    }
+
+    /// Open the cached clone of a URL, creating an empty one if there is none,
+    /// and mark it as used
+    pub fn open(&self, url: &str) -> Result<Repository> {
+        let path = self.entry_path(url);
+        let repo = if path.join(METADATA_FILE).exists() {
+            debug!("Using cached clone at {}", path.display());
+            Repository::open_bare(&path)
+                .with_context(|| format!("Failed to open cached clone: {}", path.display()))?
+        } else {
+            debug!("Creating cached clone at {}", path.display());
+            fs::create_dir_all(&path)
+                .with_context(|| format!("Failed to create cache directory: {}", path.display()))?;
+            Repository::init_bare(&path)
+                .with_context(|| format!("Failed to create cached clone: {}", path.display()))?
+        };
+
+        let entry = CacheEntry {
+            url: strip_credentials(url),
+            last_used: now(),
+            path: path.clone(),
+            size: 0,
+        };
+        let json = serde_json::to_string_pretty(&entry)?;
+        fs::write(path.join(METADATA_FILE), json + "\n")
+            .with_context(|| format!("Failed to write cache metadata in {}", path.display()))?;
+
+        Ok(repo)
+    }
+
+    /// Cached clones, most recently used first
+    pub fn entries(&self) -> Result<Vec<CacheEntry>> {
+        if !self.root.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut entries = Vec::new();
+        let dirs = fs::read_dir(&self.root)
+            .with_context(|| format!("Failed to read cache directory: {}", self.root.display()))?;
+        for dir in dirs {
+            let path = dir?.path();
+            // Only directories with metadata are ours
+            let metadata = match fs::read_to_string(path.join(METADATA_FILE)) {
+                Ok(metadata) => metadata,
+                Err(_) => continue,
+            };
+            let mut entry: CacheEntry = match serde_json::from_str(&metadata) {
+                Ok(entry) => entry,
+                Err(e) => {
+                    warn!("Invalid cache metadata in {}: {}", path.display(), e);
+                    continue;
+                }
+            };
+            // Clones cached by older versions may have a token in their URL
+            entry.url = strip_credentials(&entry.url);
+            entry.size = dir_size(&path);
+            entry.path = path;
+            entries.push(entry);
+        }
+
+        entries.sort_by(|a, b| b.last_used.cmp(&a.last_used).then(a.url.cmp(&b.url)));
+        Ok(entries)
+    }
+
+    /// Evict clones not used for `cache_max_age_days`, then the least recently
+    /// used ones until the cache fits in `cache_max_size`. The clone at `keep`,
+    /// if any, is never evicted. Returns the evicted clones.
+    pub fn prune(&self, config: &RemoteConfig, keep: Option<&Path>) -> Result<Vec<CacheEntry>> {
+        let max_age = config.cache_max_age_days * SECONDS_PER_DAY;
+        let now = now();
+
+        let mut total = 0;
+        let mut evicted = Vec::new();
+        for entry in self.entries()? {
+            if keep == Some(entry.path.as_path()) {
+                total += entry.size;
+                continue;
+            }
+
+            let too_old = max_age > 0 && now.saturating_sub(entry.last_used) > max_age;
+            let too_big = config.cache_max_size > 0 && total + entry.size > config.cache_max_size;
+            if too_old || too_big {
+                let Some(_locks) = self.try_lock_entry(&entry)? else {
+                    total += entry.size;
+                    continue;
+                };
+                remove_entry(&entry)?;
+                evicted.push(entry);
+            } else {
+                total += entry.size;
+            }
+        }
+
+        Ok(evicted)
+    }
+
+    /// Remove every cached clone that no other process is using. Returns the
+    /// removed clones.
+    pub fn clear(&self) -> Result<Vec<CacheEntry>> {
+        let mut removed = Vec::new();
+        for entry in self.entries()? {
+            if let Some(_locks) = self.try_lock_entry(&entry)? {
+                remove_entry(&entry)?;
+                removed.push(entry);
+            }
+        }
+        Ok(removed)
+    }
+
+    /// Lock a cached clone for removal, or None if a process is fetching
+    /// into it or checking it out
+    fn try_lock_entry(&self, entry: &CacheEntry) -> Result<Option<(EntryLock, EntryLock)>> {
+        let locks = match self.try_lock(entry, FETCH_LOCK_SUFFIX)? {
+            Some(fetch_lock) => self
+                .try_lock(entry, USE_LOCK_SUFFIX)?
+                .map(|use_lock| (fetch_lock, use_lock)),
+            None => None,
+        };
+        if locks.is_none() {
+            debug!("Keeping cached clone of {}, which is in use", entry.url);
+        }
+        Ok(locks)
+    }
+
+    fn try_lock(&self, entry: &CacheEntry, suffix: &str) -> Result<Option<EntryLock>> {
+        let file = self.lock_file(&entry.path, suffix)?;
+        match file.try_lock() {
+            Ok(()) => Ok(Some(EntryLock { _file: file })),
+            Err(TryLockError::WouldBlock) => Ok(None),
+            Err(TryLockError::Error(e)) => Err(e)
+                .with_context(|| format!("Failed to lock cached clone: {}", entry.path.display())),
+        }
+    }
+
+    /// Open or create a lock file next to the directory of a cached clone.
+    /// Lock files stay behind when the clone is removed, so that a process
+    /// waiting on one never holds a lock on a deleted file.
+    fn lock_file(&self, entry_path: &Path, suffix: &str) -> Result<File> {
+        fs::create_dir_all(&self.root).with_context(|| {
+            format!("Failed to create cache directory: {}", self.root.display())
+        })?;
+        let mut name = entry_path.file_name().unwrap_or_default().to_os_string();
+        name.push(suffix);
+        let path = self.root.join(name);
+        File::options()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&path)
+            .with_context(|| format!("Failed to open lock file: {}", path.display()))
+    }
+}
+
+/// Print one line per cached clone: size, last use and URL
+pub fn print_entries(entries: &[CacheEntry]) {
+    for entry in entries {
+        let last_used = DateTime::from_timestamp(entry.last_used as i64, 0)
+            .map(|date| date.format("%Y-%m-%d %H:%M").to_string())
+            .unwrap_or_default();
+        println!(
+            "{:>10}  {}  {}",
+            format_size(entry.size),
+            last_used,
+            entry.url
+        );
+    }
+}
+
+fn remove_entry(entry: &CacheEntry) -> Result<()> {
+    info!("Removing cached clone of {}", entry.url);
+    fs::remove_dir_all(&entry.path)
+        .with_context(|| format!("Failed to remove cached clone: {}", entry.path.display()))
+}
+
+/// The user's cache directory for remix, e.g. `$XDG_CACHE_HOME/remix`
+pub fn default_cache_dir() -> PathBuf {
+    dirs::cache_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join("remix")
+}
+
+/// Normalize a git URL so that the same repository gets the same cache entry:
+/// no scheme, user or `.git` suffix, and a lowercase host.
+///
+/// `https://github.com/user/repo.git` and `git@github.com:user/repo` both
+/// become `github.com/user/repo`.
+pub fn normalize_url(url: &str) -> String {
+    let url = url.trim().trim_end_matches('/');
+    let url = url.strip_suffix(".git").unwrap_or(url);
+
+    let rest = match url.split_once("://") {
+        Some((scheme, rest)) if scheme.eq_ignore_ascii_case("file") => {
+            return format!("file/{}", rest.trim_start_matches('/'));
+        }
+        Some((_, rest)) => rest.to_string(),
+        // scp-like syntax: user@host:path
+        None => match url.split_once(':') {
+            Some((host, path)) if !host.contains('/') => format!("{}/{}", host, path),
+            _ => return url.to_string(),
+        },
+    };
+
+    let (authority, path) = rest.split_once('/').unwrap_or((&rest, ""));
+    let host = authority.rsplit('@').next().unwrap_or(authority);
+    let host = host.to_lowercase();
+    if path.is_empty() {
+        host
+    } else {
+        format!("{}/{}", host, path.trim_start_matches('/'))
+    }
+}
+
+/// A URL without the user name and password, which may hold a token
+pub fn strip_credentials(url: &str) -> String {
+    match url.split_once("://") {
+        Some((scheme, rest)) => {
+            let (authority, path) = rest.split_once('/').unwrap_or((rest, ""));
+            let host = authority.rsplit('@').next().unwrap_or(authority);
+            if path.is_empty() {
+                format!("{}://{}", scheme, host)
+            } else {
+                format!("{}://{}/{}", scheme, host, path)
+            }
+        }
+        None => url.to_string(),
+    }
+}
+
+/// Directory name of a cached clone: a readable prefix of the normalized URL
+/// and a hash of it, so different URLs never share a directory
+fn cache_key(url: &str) -> String {
+    let normalized = normalize_url(url);
+    let readable: String = normalized
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect();
+    let readable = readable.trim_matches('-');
+    let readable: String = readable.chars().rev().take(48).collect();
+    let readable: String = readable.chars().rev().collect();
+
+    let hash = Sha256::digest(normalized.as_bytes());
+    let hash: String = hash[..6]
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    format!("{}-{}", readable.trim_start_matches('-'), hash)
+}
+
+fn dir_size(path: &Path) -> u64 {
+    WalkDir::new(path)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| entry.metadata().ok())
+        .filter(|metadata| metadata.is_file())
+        .map(|metadata| metadata.len())
+        .sum()
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default()
+}
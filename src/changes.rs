@@ -0,0 +1,136 @@
+use crate::config::GitConfig;
+use anyhow::{anyhow, bail, Context, Result};
+use git2::{Delta, Diff, DiffOptions, Patch, Repository};
+use log::{debug, info, warn};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Changed files by canonical path, with their unified diff if one was requested
+pub type ChangedFiles = HashMap<PathBuf, Option<String>>;
+
+/// Whether any change filter is configured
+fn is_enabled(config: &GitConfig) -> bool {
+    config.since.is_some() || config.staged || config.working_tree
+}
+
+/// Find the files that changed in the git repository containing `base_path`.
+///
+/// With `since` alone, files are compared between that ref and HEAD, or the
+/// packed `ref` if there is one; with `staged` or `working_tree` between `since`
+/// (or HEAD) and the index or the working tree. Returns `None` if no change
+/// filter is configured. Deleted files are left out, since there is no content
+/// to pack.
+pub fn changed_files(base_path: &Path, config: &GitConfig) -> Result<Option<ChangedFiles>> {
+    if !is_enabled(config) {
+        if config.with_diff {
+            warn!("Diffs are only added with --since, --staged or --working-tree");
+        }
+        return Ok(None);
+    }
+    if config.reference.is_some() && (config.staged || config.working_tree) {
+        bail!("A git ref can only be combined with --since, not --staged or --working-tree");
+    }
+
+    let repo = Repository::discover(base_path)
+        .with_context(|| format!("Not a git repository: {}", base_path.display()))?;
+    let workdir = repo
+        .workdir()
+        .ok_or_else(|| anyhow!("Cannot find changed files in a bare repository"))?
+        .canonicalize()
+        .context("Failed to resolve the git working directory")?;
+
+    let diff = diff_changes(&repo, config)?;
+
+    let mut changes = ChangedFiles::new();
+    let mut deleted = 0;
+    for (index, delta) in diff.deltas().enumerate() {
+        match delta.status() {
+            Delta::Added
+            | Delta::Modified
+            | Delta::Renamed
+            | Delta::Copied
+            | Delta::Typechange
+            | Delta::Untracked => {}
+            Delta::Deleted => {
+                deleted += 1;
+                continue;
+            }
+            _ => continue,
+        }
+
+        let path = match delta.new_file().path() {
+            Some(path) => path,
+            None => continue,
+        };
+        let patch = if config.with_diff {
+            patch_text(&diff, index)?
+        } else {
+            None
+        };
+
+        debug!("Changed file: {}", path.display());
+        changes.insert(workdir.join(path), patch);
+    }
+
+    info!("Found {} changed files", changes.len());
+    if deleted > 0 {
+        info!("{} deleted files have no content to pack", deleted);
+    }
+
+    Ok(Some(changes))
+}
+
+/// Diff the configured base (a ref, or HEAD) against HEAD or the packed ref, the
+/// index or the working tree
+fn diff_changes<'repo>(repo: &'repo Repository, config: &GitConfig) -> Result<Diff<'repo>> {
+    let base_tree = match &config.since {
+        Some(since) => Some(
+            repo.revparse_single(since)
+                .and_then(|object| object.peel_to_tree())
+                .with_context(|| format!("Unknown git ref: {}", since))?,
+        ),
+        // Before the first commit everything is a change
+        None => repo.head().ok().and_then(|head| head.peel_to_tree().ok()),
+    };
+
+    let mut options = DiffOptions::new();
+    options
+        .include_untracked(true)
+        .recurse_untracked_dirs(true)
+        .show_untracked_content(true);
+
+    let diff = if config.staged {
+        let index = repo.index().context("Failed to read the git index")?;
+        repo.diff_tree_to_index(base_tree.as_ref(), Some(&index), Some(&mut options))
+    } else if config.working_tree {
+        repo.diff_tree_to_workdir_with_index(base_tree.as_ref(), Some(&mut options))
+    } else {
+        let target = match &config.reference {
+            Some(reference) => repo
+                .revparse_single(reference)
+                .and_then(|object| object.peel_to_tree())
+                .with_context(|| format!("Unknown git ref: {}", reference))?,
+            None => repo
+                .head()
+                .and_then(|head| head.peel_to_tree())
+                .context("Failed to read HEAD")?,
+        };
+        repo.diff_tree_to_tree(base_tree.as_ref(), Some(&target), Some(&mut options))
+    };
+
+    diff.context("Failed to compute the changed files")
+}
+
+/// Unified diff of one file, or `None` if it is binary
+fn patch_text(diff: &Diff, index: usize) -> Result<Option<String>> {
+    let mut patch = match Patch::from_diff(diff, index).context("Failed to build a file diff")? {
+        Some(patch) => patch,
+        None => return Ok(None),
+    };
+    if patch.delta().flags().is_binary() {
+        return Ok(None);
+    }
+
+    let buf = patch.to_buf().context("Failed to format a file diff")?;
+    Ok(Some(String::from_utf8_lossy(&buf).into_owned()))
+}
@@ -0,0 +1,215 @@
+use crate::config::Config;
+use crate::scanner::{scan_repository, select_files};
+use crate::security::{self, SecretBaseline, SecretFinding, SecretScanner, Severity};
+use crate::source;
+use anyhow::{anyhow, Context, Result};
+use console::style;
+use git2::{Delta, Repository};
+use log::debug;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Exit code when no finding reaches the severity threshold
+pub const EXIT_CLEAN: i32 = 0;
+
+/// Exit code when at least one finding reaches the severity threshold
+pub const EXIT_FINDINGS: i32 = 1;
+
+/// Exit code when the check itself could not run
+pub const EXIT_ERROR: i32 = 2;
+
+/// A secret finding with the severity of the rule that found it
+#[derive(Debug, Clone)]
+pub struct CheckFinding {
+    pub finding: SecretFinding,
+    pub severity: Severity,
+}
+
+/// Result of `remix check`
+#[derive(Debug, Clone)]
+pub struct CheckResult {
+    /// New findings, i.e. those not in the secrets baseline
+    pub findings: Vec<CheckFinding>,
+    /// Findings hidden because they are in the baseline
+    pub baselined: usize,
+    /// Lowest severity that fails the check
+    pub threshold: Severity,
+}
+
+impl CheckResult {
+    /// Findings at or above the threshold
+    pub fn failing(&self) -> impl Iterator<Item = &CheckFinding> {
+        self.findings
+            .iter()
+            .filter(|finding| finding.severity >= self.threshold)
+    }
+
+    pub fn exit_code(&self) -> i32 {
+        if self.failing().next().is_some() {
+            EXIT_FINDINGS
+        } else {
+            EXIT_CLEAN
+        }
+    }
+}
+
+/// Scan a path for secrets without packing it.
+///
+/// The files that would be packed are scanned as they are on disk. With `staged`,
+/// the staged version of every file added or modified in the git index is
+/// scanned instead, as a pre-commit hook would see them. Either way, files are
+/// reported relative to the root of the working copy, whose baseline applies.
+pub fn run_check(path: &Path, staged: bool, config: &Config) -> Result<CheckResult> {
+    let threshold = Severity::from_name(&config.security.fail_on)?;
+    let scanner = SecretScanner::from_config(&config.security)?;
+
+    let findings = if staged {
+        let repo = Repository::discover(path)
+            .with_context(|| format!("Not a git repository: {}", path.display()))?;
+        scan_staged(&repo, path, &scanner, config)?
+    } else {
+        let source = source::open_path(path, config)?;
+        let files = scan_repository(source.as_ref(), config)?;
+        security::perform_security_check(source.as_ref(), &files, &scanner)
+    };
+
+    let baseline = SecretBaseline::load_for(&SecretBaseline::root_for(path), &config.security)?;
+    let total = findings.len();
+    let findings: Vec<CheckFinding> = findings
+        .into_iter()
+        .filter(|finding| !baseline.contains(finding))
+        .map(|finding| CheckFinding {
+            severity: scanner
+                .rule(&finding.rule_id)
+                .map_or(Severity::High, |rule| rule.severity),
+            finding,
+        })
+        .collect();
+
+    Ok(CheckResult {
+        baselined: total - findings.len(),
+        findings,
+        threshold,
+    })
+}
+
+/// Scan the staged content of files that are added or modified in the index,
+/// limited to those under `path` that the ignore and include config would
+/// pack
+fn scan_staged(
+    repo: &Repository,
+    path: &Path,
+    scanner: &SecretScanner,
+    config: &Config,
+) -> Result<Vec<SecretFinding>> {
+    let workdir = repo
+        .workdir()
+        .ok_or_else(|| anyhow!("Cannot check staged files in a bare repository"))?;
+    let prefix = path
+        .canonicalize()
+        .ok()
+        .and_then(|path| {
+            let workdir = workdir.canonicalize().ok()?;
+            path.strip_prefix(workdir).ok().map(Path::to_path_buf)
+        })
+        .unwrap_or_default();
+
+    // Before the first commit everything in the index is staged
+    let head_tree = repo.head().ok().and_then(|head| head.peel_to_tree().ok());
+    let index = repo.index().context("Failed to read the git index")?;
+
+    // The same files as when checking the working tree, with the ignore files
+    // there applied
+    let files = index
+        .iter()
+        .filter_map(|entry| {
+            let file_path = PathBuf::from(String::from_utf8_lossy(&entry.path).into_owned());
+            let relative_path = file_path.strip_prefix(&prefix).ok()?.to_path_buf();
+            Some((relative_path, ()))
+        })
+        .collect();
+    let read = |relative_path: &Path| fs::read_to_string(path.join(relative_path)).ok();
+    let selected: HashSet<PathBuf> = select_files(files, read, config)
+        .into_iter()
+        .map(|(relative_path, ())| relative_path)
+        .collect();
+    let diff = repo
+        .diff_tree_to_index(head_tree.as_ref(), Some(&index), None)
+        .context("Failed to diff the git index against HEAD")?;
+
+    let mut findings = Vec::new();
+    for delta in diff.deltas() {
+        if !matches!(
+            delta.status(),
+            Delta::Added | Delta::Modified | Delta::Renamed | Delta::Copied | Delta::Typechange
+        ) {
+            continue;
+        }
+
+        let file = delta.new_file();
+        let file_path = match file.path() {
+            Some(file_path) => file_path,
+            None => continue,
+        };
+        let is_selected = file_path
+            .strip_prefix(&prefix)
+            .is_ok_and(|relative_path| selected.contains(relative_path));
+        if !is_selected {
+            continue;
+        }
+
+        let blob = repo
+            .find_blob(file.id())
+            .with_context(|| format!("Failed to read staged {}", file_path.display()))?;
+        let content = match std::str::from_utf8(blob.content()) {
+            Ok(content) if !blob.is_binary() => content,
+            _ => {
+                debug!("Skipping binary staged file: {}", file_path.display());
+                continue;
+            }
+        };
+
+        let relative_path = file_path.to_string_lossy().replace('\\', "/");
+        findings.extend(scanner.findings(&relative_path, content));
+    }
+
+    findings.sort_by(|a, b| (&a.file, a.line, a.column).cmp(&(&b.file, b.line, b.column)));
+    Ok(findings)
+}
+
+/// Print the findings and a one-line verdict
+pub fn print_check_result(result: &CheckResult) {
+    for check in &result.findings {
+        let finding = &check.finding;
+        let severity = match check.severity {
+            Severity::High => style("high").bold().red(),
+            Severity::Medium => style("medium").bold().yellow(),
+            Severity::Low => style("low").bold().dim(),
+        };
+        println!(
+            "{}:{}:{} {} [{}] {}",
+            finding.file, finding.line, finding.column, severity, finding.rule_id, finding.snippet
+        );
+    }
+
+    if result.baselined > 0 {
+        println!("{} known secrets hidden by the baseline", result.baselined);
+    }
+
+    let failing = result.failing().count();
+    if failing > 0 {
+        println!(
+            "{} {} secret(s) at or above {} severity",
+            style("✗").bold().red(),
+            failing,
+            result.threshold.name()
+        );
+    } else {
+        println!(
+            "{} No secrets at or above {} severity",
+            style("✓").bold().green(),
+            result.threshold.name()
+        );
+    }
+}
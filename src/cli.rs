@@ -1,4 +1,4 @@
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use std::path::PathBuf;
 
 #[derive(Parser, Debug)]
@@ -12,17 +12,27 @@ EXAMPLES:\n\
   remix --remote https://github.com/user/repo  # Pack remote repository\n\
   remix --include \"*.rs,*.toml\"   # Only include Rust and TOML files\n\
   remix --output my-repo.txt     # Custom output filename\n\
-  remix --format toon --compress # Use TOON format with compression",
+  remix --format toon --compress # Use TOON format with compression\n\
+  remix --max-tokens 100000      # Keep the pack under 100k tokens\n\
+  remix --since main --with-diff # Pack files changed since main, with their diffs\n\
+  remix --ref v1.2.0             # Pack a tag without checking it out\n\
+  remix release-1.0.tar.gz       # Pack a release archive without extracting it\n\
+  remix check --staged           # Fail a pre-commit hook on staged secrets\n\
+  remix security baseline        # Record current secrets so only new ones are reported\n\
+  remix cache list               # Show the cached remote clones",
     version,
     author
 )]
 pub struct Cli {
-    /// Path to the directory or file to process (defaults to current directory)
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Path to the directory, file or archive (.tar, .tar.gz, .tar.zst, .zip) to process (defaults to current directory)
     #[arg(index = 1)]
     pub path: Option<String>,
 
     /// Path to a configuration file
-    #[arg(short, long)]
+    #[arg(short, long, global = true)]
     pub config: Option<PathBuf>,
 
     /// Initialize a new configuration file
@@ -45,11 +55,41 @@ pub struct Cli {
     #[arg(short, long)]
     pub output: Option<PathBuf>,
 
-    /// Output format: md/markdown (.md), json (.json), txt/text (.txt), toon (.toon)
-    #[arg(long, value_parser = ["md", "markdown", "json", "txt", "text", "toon"])]
+    /// Output format: md/markdown (.md), json (.json), txt/text (.txt), toon (.toon), xml (.xml)
+    #[arg(long, value_parser = ["md", "markdown", "json", "txt", "text", "toon", "xml"])]
     pub format: Option<String>,
 
-    /// Compress the code output (removes unnecessary whitespace)
+    /// Token encoding used for token counts: o200k_base, cl100k_base, heuristic
+    #[arg(long, value_parser = ["o200k_base", "cl100k_base", "heuristic"])]
+    pub token_encoding: Option<String>,
+
+    /// Maximum number of tokens for the whole output; lower-priority files are
+    /// summarized, truncated or dropped to fit
+    #[arg(long)]
+    pub max_tokens: Option<usize>,
+
+    /// Order of files within a priority tier when applying --max-tokens: size, recency, path
+    #[arg(long, value_parser = ["size", "recency", "path"])]
+    pub budget_strategy: Option<String>,
+
+    /// Split the output into numbered parts at file boundaries when it exceeds a
+    /// size (e.g. 500kb, 2mb) or token count (e.g. 100k-tokens)
+    #[arg(long, value_name = "SIZE|TOKENS")]
+    pub split_output: Option<String>,
+
+    /// Don't include a directory tree in the output
+    #[arg(long)]
+    pub no_tree: bool,
+
+    /// Collapse directories deeper than this in the directory tree
+    #[arg(long)]
+    pub tree_depth: Option<usize>,
+
+    /// Only show packed files in the directory tree (hide binary, skipped and omitted files)
+    #[arg(long)]
+    pub no_tree_excluded: bool,
+
+    /// Compress code to its API surface: signatures and docs, no function bodies
     #[arg(long)]
     pub compress: bool,
 
@@ -57,14 +97,74 @@ pub struct Cli {
     #[arg(long)]
     pub skip_sensitive_check: bool,
 
-    /// Remote repository URL (GitHub, GitLab, etc.)
+    /// What to do with files that contain secrets: skip the whole file (default),
+    /// redact the secrets, or fail without writing any output
+    #[arg(long, value_parser = ["skip", "redact", "fail"])]
+    pub security_mode: Option<String>,
+
+    /// Also write the secret findings to a standalone report file
+    #[arg(long, value_name = "PATH", global = true)]
+    pub security_report: Option<String>,
+
+    /// Security report format (default: sarif for .sarif files, json otherwise)
+    #[arg(long, value_parser = ["sarif", "json"], global = true)]
+    pub security_report_format: Option<String>,
+
+    /// Pack a commit, tag or branch of a local git repository, read from the object
+    /// database without checking it out
+    #[arg(long = "ref", value_name = "REF", conflicts_with = "remote")]
+    pub git_ref: Option<String>,
+
+    /// Only pack files changed since a git ref (branch, tag or commit), up to HEAD (or --ref),
+    /// or up to the index or working tree with --staged or --working-tree
+    #[arg(long, value_name = "REF")]
+    pub since: Option<String>,
+
+    /// Only pack files with staged changes
+    #[arg(long, conflicts_with_all = ["working_tree", "git_ref"])]
+    pub staged: bool,
+
+    /// Only pack files changed in the working tree, including untracked files
+    #[arg(long, conflicts_with = "git_ref")]
+    pub working_tree: bool,
+
+    /// Add each changed file's unified diff next to its content
+    #[arg(long)]
+    pub with_diff: bool,
+
+    /// Add git history: recent commits, and each file's last commit, commit count and contributors
+    #[arg(long)]
+    pub git_history: bool,
+
+    /// Number of recent commits listed with --git-history (default: 10)
+    #[arg(long, value_name = "N")]
+    pub history_commits: Option<usize>,
+
+    /// List the top N contributors of each file with --git-history
+    #[arg(long, value_name = "N")]
+    pub top_contributors: Option<usize>,
+
+    /// Remote repository URL (GitHub, GitLab, Bitbucket, SSH); tree, commit and pull request URLs select what to pack
     #[arg(long)]
     pub remote: Option<String>,
 
-    /// Branch name, tag, or commit hash for remote repository (default: main)
+    /// Branch name, tag, or commit hash for remote repository, unless the URL names one (default: the remote's default branch)
     #[arg(long)]
     pub remote_branch: Option<String>,
 
+    /// Number of commits to fetch from the remote repository, 0 for the full history (default: 1).
+    /// Local paths and file:// URLs always get the full history
+    #[arg(long, value_name = "N")]
+    pub clone_depth: Option<u32>,
+
+    /// Clone remote repositories from scratch instead of using the local cache
+    #[arg(long)]
+    pub no_cache: bool,
+
+    /// Directory of the remote clone cache (default: the user's cache directory)
+    #[arg(long, global = true, value_name = "DIR")]
+    pub cache_dir: Option<PathBuf>,
+
     /// Open output file after generation
     #[arg(long)]
     pub open: bool,
@@ -81,6 +181,11 @@ pub struct Cli {
     #[arg(long)]
     pub remove_comments: bool,
 
+    /// Which comments survive comment removal: all (remove all), keep-docs, keep-license.
+    /// Implies --remove-comments
+    #[arg(long, value_parser = ["all", "keep-docs", "keep-license"])]
+    pub comment_mode: Option<String>,
+
     /// Don't use patterns from .gitignore files
     #[arg(long)]
     pub no_gitignore: bool,
@@ -90,6 +195,62 @@ pub struct Cli {
     pub no_default_patterns: bool,
 }
 
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Scan for secrets without packing. Exits 1 if a finding reaches the
+    /// severity threshold, 2 if the check could not run
+    Check {
+        /// Path to scan (defaults to current directory)
+        path: Option<String>,
+
+        /// Only scan the staged content of added or modified files, for pre-commit hooks
+        #[arg(long)]
+        staged: bool,
+
+        /// Lowest severity that fails the check (default: security.fail_on, or low)
+        #[arg(long, value_parser = ["low", "medium", "high"])]
+        fail_on: Option<String>,
+    },
+
+    /// Manage secret scanning
+    Security {
+        #[command(subcommand)]
+        command: SecurityCommand,
+    },
+
+    /// Manage the cache of remote repository clones
+    Cache {
+        #[command(subcommand)]
+        command: CacheCommand,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum SecurityCommand {
+    /// Record the current secret findings in a baseline file; later runs only
+    /// report (or fail on) secrets that are not in it
+    Baseline {
+        /// Repository to scan (defaults to current directory)
+        path: Option<String>,
+
+        /// Baseline file to write (default: .remix-secrets-baseline.json in the repository)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum CacheCommand {
+    /// List the cached clones with their size and when they were last used
+    List,
+
+    /// Evict clones that are too old or over the cache size limit
+    Prune,
+
+    /// Remove every cached clone
+    Clear,
+}
+
 impl Cli {
     /// Converts a comma-separated string to a vector of strings
     pub fn parse_comma_separated(&self, input: &Option<String>) -> Option<Vec<String>> {
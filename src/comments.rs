@@ -1,388 +1,256 @@
-/// Removes comments from source code based on file extension
-pub fn remove_comments(content: &str, file_extension: &str) -> String {
-    match file_extension {
-        "rs" => remove_rust_comments(content),
-        "js" | "ts" | "jsx" | "tsx" => remove_js_comments(content),
-        "py" => remove_python_comments(content),
-        "c" | "cpp" | "h" | "hpp" | "cs" | "java" | "go" | "swift" | "kt" => {
-            remove_c_style_comments(content)
-        }
-        "rb" => remove_ruby_comments(content),
-        "php" => remove_php_comments(content),
-        "html" | "xml" | "svg" => remove_html_comments(content),
-        "css" | "scss" | "sass" | "less" => remove_css_comments(content),
-        "sh" | "bash" => remove_shell_comments(content),
-        "yaml" | "yml" => remove_yaml_comments(content),
-        _ => content.to_string(),
-    }
+use crate::syntax::SyntaxLanguage;
+use anyhow::{anyhow, Result};
+use log::debug;
+use tree_sitter::Node;
+
+/// Which comments `remove_comments` leaves in place
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommentMode {
+    /// Remove every comment
+    All,
+    /// Keep doc comments (`///`, `//!`, `/** */`, Go and Ruby declaration comments)
+    KeepDocs,
+    /// Keep only the license header at the top of the file
+    KeepLicense,
 }
 
-/// Determines if comment removal is supported for this file type
-pub fn is_comment_removal_supported(extension: &str) -> bool {
-    matches!(
-        extension,
-        "rs" | "js"
-            | "ts"
-            | "jsx"
-            | "tsx"
-            | "py"
-            | "c"
-            | "cpp"
-            | "h"
-            | "hpp"
-            | "cs"
-            | "java"
-            | "go"
-            | "swift"
-            | "kt"
-            | "rb"
-            | "php"
-            | "html"
-            | "xml"
-            | "svg"
-            | "css"
-            | "scss"
-            | "sass"
-            | "less"
-            | "sh"
-            | "bash"
-            | "yaml"
-            | "yml"
-    )
-}
-
-/// Removes comments from Rust code
-fn remove_rust_comments(content: &str) -> String {
-    remove_c_style_comments(content) // Rust uses the same comment style as C
+impl CommentMode {
+    pub fn from_name(name: &str) -> Result<Self> {
+        match name.to_lowercase().replace('-', "_").as_str() {
+            "all" => Ok(Self::All),
+            "keep_docs" | "docs" => Ok(Self::KeepDocs),
+            "keep_license" | "license" => Ok(Self::KeepLicense),
+            _ => Err(anyhow!("Unknown comment mode: {}", name)),
+        }
+    }
 }
 
-/// Removes comments from JavaScript/TypeScript code
-fn remove_js_comments(content: &str) -> String {
-    remove_c_style_comments(content) // JS/TS use the same comment style as C
-}
+/// Extensions of CSS dialects without a bundled grammar
+const UNSUPPORTED_DIALECTS: &[&str] = &["scss", "sass", "less"];
+
+/// Words that mark a leading comment as a license header
+const LICENSE_KEYWORDS: &[&str] = &["copyright", "license", "licence", "spdx-license-identifier"];
+
+/// Removes comments from source code based on file extension.
+///
+/// Comments are located with the language's tree-sitter grammar, so string
+/// literals, raw strings, lifetimes, regex literals, template strings and nested
+/// block comments are never mistaken for (or cut off as) comments. Only the
+/// comment text is removed; surrounding whitespace and line breaks are kept.
+/// Python docstrings are string literals and are always kept. Files that don't
+/// parse cleanly are returned unchanged.
+pub fn remove_comments(content: &str, file_extension: &str, mode: CommentMode) -> String {
+    let language = match SyntaxLanguage::from_extension(file_extension) {
+        Some(language) => language,
+        None => return content.to_string(),
+    };
+
+    let tree = match language.parse(content) {
+        Some(tree) => tree,
+        None => {
+            debug!(
+                "Could not parse .{} file, leaving its comments in place",
+                file_extension
+            );
+            return content.to_string();
+        }
+    };
 
-/// Removes comments from Python code
-fn remove_python_comments(content: &str) -> String {
-    let mut result = String::new();
-    let mut in_multiline_string = false;
-    let mut multiline_quotes = "";
+    let mut comments = Vec::new();
+    collect_comments(tree.root_node(), &mut comments);
 
-    let lines = content.lines();
-    for line in lines {
-        let line_trim = line.trim();
+    let license_header = match mode {
+        CommentMode::KeepLicense => license_header(&comments, content),
+        _ => 0,
+    };
 
-        // Handle multi-line strings (which could contain # that aren't comments)
-        if in_multiline_string {
-            result.push_str(line);
-            result.push('\n');
+    let mut result = String::with_capacity(content.len());
+    let mut cursor = 0;
+    for (index, comment) in comments.iter().enumerate() {
+        let start = comment.start_byte();
+        let text = &content[start..comment.end_byte()];
 
-            if line.contains(multiline_quotes) {
-                // Count the quotes at the end to see if we're closing the multi-line string
-                let parts: Vec<&str> = line.rsplitn(2, multiline_quotes).collect();
-                if parts.len() > 1 && !parts[0].ends_with('\\') {
-                    in_multiline_string = false;
-                }
-            }
+        // Keep the shebang, it is needed to run the script
+        if start == 0 && text.starts_with("#!") {
             continue;
         }
 
-        // Check for multi-line string start
-        if (line_trim.contains("'''") || line_trim.contains("\"\"\""))
-            && !(line_trim.starts_with("#"))
-        {
-            if line_trim.contains("'''") {
-                multiline_quotes = "'''";
-            } else {
-                multiline_quotes = "\"\"\"";
-            }
-
-            // Check if it opens and closes on the same line
-            let count = line_trim.matches(multiline_quotes).count();
-            if count % 2 == 1 {
-                in_multiline_string = true;
-            }
-
-            result.push_str(line);
-            result.push('\n');
+        let keep = match mode {
+            CommentMode::All => false,
+            CommentMode::KeepDocs => is_doc_comment(language, comment, content),
+            CommentMode::KeepLicense => index < license_header,
+        };
+        if keep {
             continue;
         }
 
-        // Regular comment handling
-        if let Some(comment_pos) = line.find('#') {
-            let preceding = &line[0..comment_pos];
-            // Check if the # is in a string
-            let in_string = preceding.chars().filter(|&c| c == '"' || c == '\'').count() % 2 == 1;
-
-            if in_string {
-                result.push_str(line);
-            } else {
-                result.push_str(preceding);
-            }
-            result.push('\n');
-        } else {
-            result.push_str(line);
-            result.push('\n');
-        }
+        // Some grammars include the line break in line comments; keep it
+        result.push_str(&content[cursor..start]);
+        cursor = start + text.trim_end_matches(['\n', '\r']).len();
     }
+    result.push_str(&content[cursor..]);
 
     result
 }
 
-/// Removes comments from C-style languages (C, C++, Java, C#, etc.)
-fn remove_c_style_comments(content: &str) -> String {
-    let mut result = String::new();
-    let mut chars = content.chars().peekable();
-    let mut in_string = false;
-    let mut in_char = false;
-    let mut in_single_line_comment = false;
-    let mut in_multi_line_comment = false;
-    let mut string_quote = '"';
-
-    while let Some(c) = chars.next() {
-        match c {
-            // String handling
-            '"' | '\'' => {
-                if !in_single_line_comment && !in_multi_line_comment {
-                    if c == '"' && !in_char {
-                        if !in_string {
-                            string_quote = '"';
-                            in_string = true;
-                        } else if string_quote == '"' {
-                            in_string = false;
-                        }
-                        result.push(c);
-                    } else if c == '\'' && !in_string {
-                        in_char = !in_char;
-                        result.push(c);
-                    } else {
-                        result.push(c);
-                    }
-                }
-            }
-            // Escape sequence handling
-            '\\' => {
-                if !in_single_line_comment && !in_multi_line_comment && (in_string || in_char) {
-                    result.push(c);
-                    if let Some(next) = chars.next() {
-                        result.push(next);
-                    }
-                } else if !in_single_line_comment && !in_multi_line_comment {
-                    result.push(c);
-                }
-            }
-            // Comment handling
-            '/' => {
-                if !in_string && !in_char && !in_single_line_comment && !in_multi_line_comment {
-                    if let Some(&next) = chars.peek() {
-                        if next == '/' {
-                            in_single_line_comment = true;
-                            chars.next(); // consume the second '/'
-                        } else if next == '*' {
-                            in_multi_line_comment = true;
-                            chars.next(); // consume the '*'
-                        } else {
-                            result.push(c);
-                        }
-                    } else {
-                        result.push(c);
-                    }
-                } else if in_multi_line_comment {
-                    if let Some(prev) = result.chars().last() {
-                        if prev == '*' {
-                            result.pop(); // remove the '*'
-                            in_multi_line_comment = false;
-                        }
-                    }
-                } else if !in_single_line_comment && !in_multi_line_comment {
-                    result.push(c);
-                }
-            }
-            // New line handling
-            '\n' => {
-                if in_single_line_comment {
-                    in_single_line_comment = false;
-                }
-                result.push(c);
-            }
-            // Star handling for multi-line comments
-            '*' => {
-                if !in_string && !in_char && !in_single_line_comment && in_multi_line_comment {
-                    if let Some(&next) = chars.peek() {
-                        if next == '/' {
-                            in_multi_line_comment = false;
-                            chars.next(); // consume the '/'
-                        }
-                    }
-                } else if !in_single_line_comment && !in_multi_line_comment {
-                    result.push(c);
-                }
-            }
-            // Regular character handling
-            _ => {
-                if !in_single_line_comment && !in_multi_line_comment {
-                    result.push(c);
-                }
-            }
-        }
-    }
-
-    result
+/// Determines if comment removal is supported for this file type
+pub fn is_comment_removal_supported(extension: &str) -> bool {
+    SyntaxLanguage::from_extension(extension).is_some()
 }
 
-/// Removes comments from Ruby code
-fn remove_ruby_comments(content: &str) -> String {
-    // Ruby uses # for single line comments similar to Python
-    remove_python_comments(content)
+/// Whether a file type is a stylesheet dialect whose comments are left in
+/// place: the CSS grammar can't parse their `//` comments and nesting, and no
+/// grammar for them is bundled
+pub fn is_unsupported_dialect(extension: &str) -> bool {
+    UNSUPPORTED_DIALECTS.contains(&extension.to_lowercase().as_str())
 }
 
-/// Removes comments from PHP code
-fn remove_php_comments(content: &str) -> String {
-    // PHP supports both C-style comments and # comments
-    let content = remove_c_style_comments(content);
+/// Whether a node is a comment in any of the bundled grammars
+/// (`comment`, `line_comment`, `block_comment`, `html_comment`, XML's `Comment`, ...)
+fn is_comment(node: &Node) -> bool {
+    node.kind().to_ascii_lowercase().ends_with("comment")
+}
 
-    // Handle # comments
-    let mut result = String::new();
-    for line in content.lines() {
-        if let Some(comment_pos) = line.find('#') {
-            let preceding = &line[0..comment_pos];
-            result.push_str(preceding);
-            result.push('\n');
-        } else {
-            result.push_str(line);
-            result.push('\n');
-        }
+/// Collect all comment nodes, in source order
+fn collect_comments<'tree>(node: Node<'tree>, comments: &mut Vec<Node<'tree>>) {
+    if is_comment(&node) {
+        comments.push(node);
+        return;
     }
 
-    result
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        collect_comments(child, comments);
+    }
 }
 
-/// Removes comments from HTML/XML code
-fn remove_html_comments(content: &str) -> String {
-    // Use a simple state machine approach
-    let mut result = String::with_capacity(content.len());
-    let mut state = 0; // 0: normal, 1: saw '<', 2: saw '<!', 3: saw '<!-', 4: in comment
+/// Last row covered by a comment, not counting a trailing line break
+fn end_row(comment: &Node, text: &str) -> usize {
+    comment.start_position().row + text.trim_end_matches(['\n', '\r']).matches('\n').count()
+}
 
-    let mut chars = content.chars().peekable();
-    while let Some(c) = chars.next() {
-        match state {
-            0 => {
-                if c == '<' {
-                    state = 1;
-                    result.push(c);
-                } else {
-                    result.push(c);
-                }
-            }
-            1 => {
-                if c == '!' {
-                    state = 2;
-                    result.push(c);
-                } else {
-                    state = 0;
-                    result.push(c);
-                }
-            }
-            2 => {
-                if c == '-' {
-                    state = 3;
-                    result.push(c);
-                } else {
-                    state = 0;
-                    result.push(c);
-                }
-            }
-            3 => {
-                if c == '-' {
-                    // Start of comment detected
-                    state = 4;
-                    // Remove "<!--" that we've accumulated
-                    result.truncate(result.len() - 3);
-                } else {
-                    state = 0;
-                    result.push(c);
-                }
-            }
-            4 => {
-                // In comment, look for "-->"
-                if c == '-' {
-                    // Check for end of comment
-                    if chars.peek() == Some(&'-') {
-                        chars.next(); // consume second '-'
-                        if chars.peek() == Some(&'>') {
-                            chars.next(); // consume '>'
-                            state = 0; // Back to normal
-                        }
-                    }
-                }
-                // Don't add anything to result while in comment
-            }
-            _ => unreachable!(),
+/// Whether a comment is documentation in its language
+fn is_doc_comment(language: SyntaxLanguage, comment: &Node, content: &str) -> bool {
+    let text = &content[comment.start_byte()..comment.end_byte()];
+
+    match language {
+        // Go and Ruby have no doc comment syntax: a comment directly above a
+        // declaration documents it
+        SyntaxLanguage::Go => is_attached_to(
+            comment,
+            content,
+            &[
+                "package_clause",
+                "function_declaration",
+                "method_declaration",
+                "type_declaration",
+                "const_declaration",
+                "var_declaration",
+            ],
+        ),
+        SyntaxLanguage::Ruby => is_attached_to(
+            comment,
+            content,
+            &["class", "module", "method", "singleton_method"],
+        ),
+        SyntaxLanguage::Python
+        | SyntaxLanguage::Bash
+        | SyntaxLanguage::Css
+        | SyntaxLanguage::Html
+        | SyntaxLanguage::Xml
+        | SyntaxLanguage::Yaml => false,
+        // `///` and `//!` (Rust, C#, Swift, Doxygen), `/** */` (JSDoc, Javadoc,
+        // KDoc, PHPDoc) and `/*! */`
+        _ => {
+            (text.starts_with("///") && !text.starts_with("////"))
+                || text.starts_with("//!")
+                || (text.starts_with("/**") && !text.starts_with("/**/"))
+                || text.starts_with("/*!")
         }
     }
-
-    result
 }
 
-/// Removes comments from CSS code
-fn remove_css_comments(content: &str) -> String {
-    let mut result = String::new();
-    let mut i = 0;
+/// Whether a comment is part of a comment block that ends on the line right
+/// above a declaration of one of `kinds`
+fn is_attached_to(comment: &Node, content: &str, kinds: &[&str]) -> bool {
+    let mut current = *comment;
 
-    while i < content.len() {
-        if i + 1 < content.len() && &content[i..i + 2] == "/*" {
-            let comment_end = content[i + 2..].find("*/");
-            if let Some(end) = comment_end {
-                i = i + 2 + end + 2;
-            } else {
-                result.push_str(&content[i..]);
-                break;
+    while let Some(next) = current.next_sibling() {
+        let text = &content[current.start_byte()..current.end_byte()];
+        if next.start_position().row != end_row(&current, text) + 1 {
+            return false;
+        }
+        if !is_comment(&next) {
+            // Look through wrappers such as Ruby's `body_statement` that start
+            // at the declaration
+            let mut node = Some(next);
+            while let Some(candidate) = node {
+                if kinds.contains(&candidate.kind()) {
+                    return true;
+                }
+                node = candidate
+                    .child(0)
+                    .filter(|child| child.start_byte() == candidate.start_byte());
             }
-        } else {
-            result.push(content.chars().nth(i).unwrap());
-            i += 1;
+            return false;
         }
+        current = next;
     }
 
-    result
+    false
 }
 
-/// Removes comments from shell scripts
-fn remove_shell_comments(content: &str) -> String {
-    let mut result = String::new();
-
-    for line in content.lines() {
-        if let Some(comment_pos) = line.find('#') {
-            // Check if the # is part of a command, not a comment
-            let preceding = &line[0..comment_pos];
-            if preceding.contains("echo") || preceding.contains("printf") {
-                result.push_str(line);
-            } else {
-                result.push_str(preceding);
-            }
-            result.push('\n');
-        } else {
-            result.push_str(line);
-            result.push('\n');
-        }
+/// Number of leading comments that make up the license header, or 0 if the file
+/// doesn't start with one.
+///
+/// The header is the first block of comments on consecutive lines, preceded by
+/// nothing but whitespace, a shebang or a `<?php`/`<?xml` line, that mentions a
+/// copyright or license.
+fn license_header(comments: &[Node], content: &str) -> usize {
+    // A shebang is a comment in some grammars
+    let skip = usize::from(
+        comments
+            .first()
+            .is_some_and(|c| c.start_byte() == 0 && content.starts_with("#!")),
+    );
+    let first = match comments.get(skip) {
+        Some(first) => first,
+        None => return 0,
+    };
+
+    let prelude_is_trivial = content[..first.start_byte()].lines().all(|line| {
+        let line = line.trim();
+        line.is_empty() || line.starts_with("#!") || line.starts_with("<?")
+    });
+    if !prelude_is_trivial {
+        return 0;
     }
 
-    result
-}
-
-/// Removes comments from YAML files
-fn remove_yaml_comments(content: &str) -> String {
-    let mut result = String::new();
+    // Extend the block while the next comment starts on the following line
+    let mut end = skip + 1;
+    while let Some(next) = comments.get(end) {
+        let previous = &comments[end - 1];
+        let previous_text = &content[previous.start_byte()..previous.end_byte()];
+        let gap = &content[previous.end_byte()..next.start_byte()];
 
-    for line in content.lines() {
-        if let Some(comment_pos) = line.find('#') {
-            let preceding = &line[0..comment_pos];
-            result.push_str(preceding);
-            result.push('\n');
-        } else {
-            result.push_str(line);
-            result.push('\n');
+        if !gap.trim().is_empty()
+            || next.start_position().row > end_row(previous, previous_text) + 1
+        {
+            break;
         }
+        end += 1;
     }
 
-    result
+    let mentions_license = comments[skip..end].iter().any(|comment| {
+        let text = content[comment.start_byte()..comment.end_byte()].to_lowercase();
+        LICENSE_KEYWORDS
+            .iter()
+            .any(|keyword| text.contains(keyword))
+    });
+
+    if mentions_license {
+        end
+    } else {
+        0
+    }
 }
@@ -0,0 +1,191 @@
+use crate::syntax::SyntaxLanguage;
+use log::debug;
+use tree_sitter::Node;
+
+/// Replacement for the body of a brace-delimited function
+const BODY_PLACEHOLDER: &str = "{ ... }";
+
+/// Replacement for the body of a Python function
+const PYTHON_BODY_PLACEHOLDER: &str = "...";
+
+/// Node kinds of functions, methods and lambdas whose `body` field is elided
+fn is_function(language: SyntaxLanguage, kind: &str) -> bool {
+    match language {
+        SyntaxLanguage::Rust => kind == "function_item",
+        SyntaxLanguage::Python => kind == "function_definition",
+        SyntaxLanguage::JavaScript | SyntaxLanguage::TypeScript | SyntaxLanguage::Tsx => matches!(
+            kind,
+            "function_declaration"
+                | "function_expression"
+                | "function"
+                | "generator_function_declaration"
+                | "generator_function"
+                | "method_definition"
+                | "arrow_function"
+        ),
+        SyntaxLanguage::Go => matches!(
+            kind,
+            "function_declaration" | "method_declaration" | "func_literal"
+        ),
+        SyntaxLanguage::Java => matches!(
+            kind,
+            "method_declaration"
+                | "constructor_declaration"
+                | "compact_constructor_declaration"
+                | "lambda_expression"
+        ),
+        _ => false,
+    }
+}
+
+/// Languages whose function bodies `compress_content` knows how to elide
+fn is_compressible(language: SyntaxLanguage) -> bool {
+    matches!(
+        language,
+        SyntaxLanguage::Rust
+            | SyntaxLanguage::Python
+            | SyntaxLanguage::JavaScript
+            | SyntaxLanguage::TypeScript
+            | SyntaxLanguage::Tsx
+            | SyntaxLanguage::Go
+            | SyntaxLanguage::Java
+    )
+}
+
+/// A span of the source to replace: (start byte, end byte, replacement)
+type Replacement = (usize, usize, String);
+
+/// Compress source code down to its API surface.
+///
+/// Imports, type declarations, function and method signatures, doc comments and
+/// Python docstrings are kept; function bodies are replaced with a placeholder.
+/// Files in languages without a bundled grammar, or that fail to parse, are
+/// returned unchanged rather than risk a mangled view.
+pub fn compress_content(content: &str, extension: &str) -> String {
+    let language = match SyntaxLanguage::from_extension(extension).filter(|l| is_compressible(*l)) {
+        Some(language) => language,
+        None => return content.to_string(),
+    };
+
+    let tree = match language.parse(content) {
+        Some(tree) => tree,
+        None => {
+            debug!(
+                "Could not parse .{} file, leaving it uncompressed",
+                extension
+            );
+            return content.to_string();
+        }
+    };
+
+    let mut replacements = Vec::new();
+    collect_bodies(tree.root_node(), language, content, &mut replacements);
+
+    // Replacements are collected in source order and never overlap
+    let mut output = String::with_capacity(content.len());
+    let mut cursor = 0;
+    for (start, end, replacement) in replacements {
+        output.push_str(&content[cursor..start]);
+        output.push_str(&replacement);
+        cursor = end;
+    }
+    output.push_str(&content[cursor..]);
+
+    collapse_blank_lines(&output)
+}
+
+/// Walk the syntax tree and record the body of every function for elision
+fn collect_bodies(
+    node: Node,
+    language: SyntaxLanguage,
+    content: &str,
+    replacements: &mut Vec<Replacement>,
+) {
+    if is_function(language, node.kind()) {
+        if let Some(replacement) = node
+            .child_by_field_name("body")
+            .and_then(|body| body_replacement(body, language, content))
+        {
+            // Nested functions disappear with the body
+            replacements.push(replacement);
+            return;
+        }
+    }
+
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        collect_bodies(child, language, content, replacements);
+    }
+}
+
+fn body_replacement(body: Node, language: SyntaxLanguage, content: &str) -> Option<Replacement> {
+    match (language, body.kind()) {
+        (SyntaxLanguage::Python, "block") => python_body_replacement(body, content),
+        (_, "block" | "statement_block" | "constructor_body") => Some((
+            body.start_byte(),
+            body.end_byte(),
+            BODY_PLACEHOLDER.to_string(),
+        )),
+        // Expression bodies of arrow functions and lambdas are left alone
+        _ => None,
+    }
+}
+
+/// Elide a Python function body, keeping its docstring if it has one
+fn python_body_replacement(body: Node, content: &str) -> Option<Replacement> {
+    let mut cursor = body.walk();
+    let statements: Vec<Node> = body
+        .named_children(&mut cursor)
+        .filter(|child| child.kind() != "comment")
+        .collect();
+
+    let docstring = statements.first().filter(|statement| {
+        statement.kind() == "expression_statement"
+            && statement
+                .named_child(0)
+                .is_some_and(|expr| expr.kind() == "string")
+    });
+
+    let line_start = content[..body.start_byte()]
+        .rfind('\n')
+        .map_or(0, |i| i + 1);
+    let indent = &content[line_start..body.start_byte()];
+
+    match docstring {
+        Some(_) if statements.len() == 1 => None,
+        // Only multi-line bodies have an indentation to put the placeholder at
+        Some(docstring) if indent.trim().is_empty() => Some((
+            docstring.end_byte(),
+            body.end_byte(),
+            format!("\n{}{}", indent, PYTHON_BODY_PLACEHOLDER),
+        )),
+        _ => Some((
+            body.start_byte(),
+            body.end_byte(),
+            PYTHON_BODY_PLACEHOLDER.to_string(),
+        )),
+    }
+}
+
+/// Collapse runs of blank lines left behind by elided bodies into a single one
+fn collapse_blank_lines(content: &str) -> String {
+    let mut output = String::with_capacity(content.len());
+    let mut previous_blank = false;
+
+    for line in content.lines() {
+        let blank = line.trim().is_empty();
+        if blank && previous_blank {
+            continue;
+        }
+        previous_blank = blank;
+
+        output.push_str(line);
+        output.push('\n');
+    }
+
+    if !content.ends_with('\n') {
+        output.pop();
+    }
+
+    output
+}
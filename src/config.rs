@@ -2,6 +2,7 @@ use crate::cli::Cli;
 use anyhow::{Context, Result};
 use log::{info, warn};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs;
 use std::io::Write;
 use std::path::{Path, PathBuf};
@@ -9,9 +10,42 @@ use std::path::{Path, PathBuf};
 const CONFIG_FILENAME: &str = "remix.config.json";
 const DEFAULT_MAX_FILE_SIZE: u64 = 100_000; // 100KB
 
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TreeConfig {
+    /// Whether to include a directory tree in the output
+    #[serde(default = "default_tree_enabled")]
+    pub enabled: bool,
+
+    /// Collapse directories below this depth into a file count
+    #[serde(default)]
+    pub max_depth: Option<usize>,
+
+    /// Also show binary, skipped and omitted files, marked as such
+    #[serde(default = "default_tree_include_excluded")]
+    pub include_excluded: bool,
+}
+
+fn default_tree_enabled() -> bool {
+    true
+}
+
+fn default_tree_include_excluded() -> bool {
+    true
+}
+
+impl Default for TreeConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_tree_enabled(),
+            max_depth: None,
+            include_excluded: default_tree_include_excluded(),
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct OutputConfig {
-    /// Output format (md, json, txt, toon)
+    /// Output format (md, json, txt, toon, xml)
     #[serde(default = "default_format")]
     pub format: String,
 
@@ -29,6 +63,18 @@ pub struct OutputConfig {
     /// Whether to remove comments from supported file types
     #[serde(default)]
     pub remove_comments: bool,
+
+    /// Which comments to keep when removing comments: all (remove all), keep_docs, keep_license
+    #[serde(default = "default_comment_mode")]
+    pub comment_mode: String,
+
+    /// Split the output into numbered parts of at most this size (e.g. "500kb", "100k-tokens")
+    #[serde(default)]
+    pub split_output: Option<String>,
+
+    /// Directory tree configuration
+    #[serde(default)]
+    pub tree: TreeConfig,
 }
 
 fn default_format() -> String {
@@ -39,6 +85,10 @@ fn default_output_path() -> String {
     "./remix-output.txt".to_string()
 }
 
+fn default_comment_mode() -> String {
+    "all".to_string()
+}
+
 impl Default for OutputConfig {
     fn default() -> Self {
         Self {
@@ -47,6 +97,9 @@ impl Default for OutputConfig {
             path: default_output_path(),
             instruction_file_path: None,
             remove_comments: false,
+            comment_mode: default_comment_mode(),
+            split_output: None,
+            tree: TreeConfig::default(),
         }
     }
 }
@@ -98,16 +151,285 @@ pub struct SecurityConfig {
     /// Enable security check for sensitive information
     #[serde(default = "default_enable_security_check")]
     pub enable_security_check: bool,
+
+    /// What to do with files that contain secrets: skip, redact or fail
+    #[serde(default = "default_security_mode")]
+    pub mode: String,
+
+    /// Extra secret rules, run after the built-in ones
+    #[serde(default)]
+    pub custom_rules: Vec<SecretRuleConfig>,
+
+    /// Secrets that are known to be safe and should not be reported
+    #[serde(default)]
+    pub allowlist: SecretAllowlistConfig,
+
+    /// Baseline of known findings, relative to the repository root
+    /// (default: .remix-secrets-baseline.json, used if it exists)
+    #[serde(default)]
+    pub baseline: Option<String>,
+
+    /// Write the findings to this file as a standalone security report
+    #[serde(default)]
+    pub report: Option<String>,
+
+    /// Security report format: sarif or json (default: sarif for .sarif files, json otherwise)
+    #[serde(default)]
+    pub report_format: Option<String>,
+
+    /// Lowest severity that makes `remix check` fail: low, medium or high
+    #[serde(default = "default_fail_on")]
+    pub fail_on: String,
+}
+
+/// A user-defined secret rule
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SecretRuleConfig {
+    /// Rule id shown in findings and redaction placeholders
+    pub id: String,
+
+    /// Regular expression; the secret is the first capture group, or the whole match
+    pub regex: String,
+
+    /// Human-readable description of what the rule finds
+    #[serde(default)]
+    pub description: Option<String>,
+
+    /// Minimum Shannon entropy (bits per character) of the secret to report it
+    #[serde(default)]
+    pub entropy: Option<f64>,
+
+    /// Severity of a finding: low, medium or high (default)
+    #[serde(default)]
+    pub severity: Option<String>,
+
+    /// How to fix a finding, shown in security reports
+    #[serde(default)]
+    pub remediation: Option<String>,
+
+    /// Words of which at least one must appear in a file for the rule to run
+    #[serde(default)]
+    pub keywords: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct SecretAllowlistConfig {
+    /// Glob patterns of files whose secrets are ignored (e.g. "tests/fixtures/**")
+    #[serde(default)]
+    pub paths: Vec<String>,
+
+    /// Regular expressions; secrets matching any of them are ignored
+    #[serde(default)]
+    pub regexes: Vec<String>,
+
+    /// Fingerprints of individual findings to ignore, as shown in the output
+    #[serde(default)]
+    pub fingerprints: Vec<String>,
 }
 
 fn default_enable_security_check() -> bool {
     true
 }
 
+fn default_security_mode() -> String {
+    "skip".to_string()
+}
+
+fn default_fail_on() -> String {
+    "low".to_string()
+}
+
 impl Default for SecurityConfig {
     fn default() -> Self {
         Self {
             enable_security_check: default_enable_security_check(),
+            mode: default_security_mode(),
+            custom_rules: Vec::new(),
+            allowlist: SecretAllowlistConfig::default(),
+            baseline: None,
+            report: None,
+            report_format: None,
+            fail_on: default_fail_on(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TokenCountConfig {
+    /// Token encoding used for counting (o200k_base, cl100k_base, heuristic)
+    #[serde(default = "default_token_encoding")]
+    pub encoding: String,
+}
+
+fn default_token_encoding() -> String {
+    "o200k_base".to_string()
+}
+
+impl Default for TokenCountConfig {
+    fn default() -> Self {
+        Self {
+            encoding: default_token_encoding(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BudgetConfig {
+    /// Maximum number of tokens for the whole output (no limit if unset)
+    pub max_tokens: Option<usize>,
+
+    /// Order of files within a priority tier (size, recency, path)
+    #[serde(default = "default_budget_strategy")]
+    pub strategy: String,
+
+    /// Patterns for files that should be packed before everything else (glob syntax)
+    #[serde(default)]
+    pub priority_patterns: Vec<String>,
+}
+
+fn default_budget_strategy() -> String {
+    "size".to_string()
+}
+
+impl Default for BudgetConfig {
+    fn default() -> Self {
+        Self {
+            max_tokens: None,
+            strategy: default_budget_strategy(),
+            priority_patterns: Vec::new(),
+        }
+    }
+}
+
+/// Git-aware packing: pack a commit, limit the pack to changed files, add history
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GitConfig {
+    /// Pack this commit, tag or branch from the git object database instead of the working tree
+    #[serde(default, rename = "ref")]
+    pub reference: Option<String>,
+
+    /// Only pack files changed since this ref (branch, tag or commit)
+    #[serde(default)]
+    pub since: Option<String>,
+
+    /// Only pack files with staged changes
+    #[serde(default)]
+    pub staged: bool,
+
+    /// Only pack files changed in the working tree, including staged and untracked files
+    #[serde(default)]
+    pub working_tree: bool,
+
+    /// Add each changed file's unified diff next to its content
+    #[serde(default)]
+    pub with_diff: bool,
+
+    /// Add git history: recent commits, and each file's last commit and commit count
+    #[serde(default)]
+    pub history: bool,
+
+    /// Number of recent commits to list in the git history
+    #[serde(default = "default_history_commits")]
+    pub history_commits: usize,
+
+    /// Number of top contributors to list for each file (0 for none)
+    #[serde(default)]
+    pub top_contributors: usize,
+}
+
+fn default_history_commits() -> usize {
+    10
+}
+
+impl Default for GitConfig {
+    fn default() -> Self {
+        Self {
+            reference: None,
+            since: None,
+            staged: false,
+            working_tree: false,
+            with_diff: false,
+            history: false,
+            history_commits: default_history_commits(),
+            top_contributors: 0,
+        }
+    }
+}
+
+/// Cloning of remote repositories
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RemoteConfig {
+    /// Number of commits to fetch (0 for the full history). Local paths and
+    /// `file://` URLs always get the full history.
+    #[serde(default = "default_clone_depth")]
+    pub depth: u32,
+
+    /// Keep clones in a local cache and fetch only new commits on later runs
+    #[serde(default = "default_cache")]
+    pub cache: bool,
+
+    /// Cache directory (default: the user's cache directory, e.g. ~/.cache/remix)
+    #[serde(default)]
+    pub cache_dir: Option<PathBuf>,
+
+    /// Evict the least recently used clones above this total size in bytes (0 for no limit)
+    #[serde(default = "default_cache_max_size")]
+    pub cache_max_size: u64,
+
+    /// Evict clones not used for this many days (0 to keep them)
+    #[serde(default = "default_cache_max_age_days")]
+    pub cache_max_age_days: u64,
+
+    /// Credentials by host, e.g. "github.com"; "*" applies to every other host
+    #[serde(default)]
+    pub credentials: BTreeMap<String, HostCredentials>,
+}
+
+/// How to authenticate with one git host
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct HostCredentials {
+    /// Environment variable with an access token for HTTPS, tried before REMIX_GIT_TOKEN
+    #[serde(default)]
+    pub token_env: Option<String>,
+
+    /// User name sent with the token or SSH key, if the URL has none
+    #[serde(default)]
+    pub username: Option<String>,
+
+    /// SSH private key, tried after ssh-agent
+    #[serde(default)]
+    pub ssh_key: Option<PathBuf>,
+
+    /// Environment variable with the passphrase of the SSH key
+    #[serde(default)]
+    pub ssh_passphrase_env: Option<String>,
+}
+
+fn default_clone_depth() -> u32 {
+    1
+}
+
+fn default_cache() -> bool {
+    true
+}
+
+fn default_cache_max_size() -> u64 {
+    2 * 1024 * 1024 * 1024 // 2GB
+}
+
+fn default_cache_max_age_days() -> u64 {
+    30
+}
+
+impl Default for RemoteConfig {
+    fn default() -> Self {
+        Self {
+            depth: default_clone_depth(),
+            cache: default_cache(),
+            cache_dir: None,
+            cache_max_size: default_cache_max_size(),
+            cache_max_age_days: default_cache_max_age_days(),
+            credentials: BTreeMap::new(),
         }
     }
 }
@@ -138,6 +460,22 @@ pub struct Config {
     #[serde(default)]
     pub output: OutputConfig,
 
+    /// Token counting configuration
+    #[serde(default)]
+    pub token_count: TokenCountConfig,
+
+    /// Token budget configuration
+    #[serde(default)]
+    pub budget: BudgetConfig,
+
+    /// Git-aware packing configuration
+    #[serde(default)]
+    pub git: GitConfig,
+
+    /// Remote repository configuration
+    #[serde(default)]
+    pub remote: RemoteConfig,
+
     /// User instruction to add at the top of the output
     pub instruction: Option<String>,
 }
@@ -205,6 +543,10 @@ impl Default for Config {
             compress: false,
             security: SecurityConfig::default(),
             output: OutputConfig::default(),
+            token_count: TokenCountConfig::default(),
+            budget: BudgetConfig::default(),
+            git: GitConfig::default(),
+            remote: RemoteConfig::default(),
             instruction: None,
         }
     }
@@ -236,6 +578,18 @@ impl Config {
             config.security.enable_security_check = false;
         }
 
+        if let Some(mode) = &cli.security_mode {
+            config.security.mode = mode.clone();
+        }
+
+        if let Some(report) = &cli.security_report {
+            config.security.report = Some(report.clone());
+        }
+
+        if let Some(format) = &cli.security_report_format {
+            config.security.report_format = Some(format.clone());
+        }
+
         if cli.no_gitignore {
             config.ignore.use_gitignore = false;
         }
@@ -244,6 +598,62 @@ impl Config {
             config.ignore.use_default_patterns = false;
         }
 
+        if let Some(encoding) = &cli.token_encoding {
+            config.token_count.encoding = encoding.clone();
+        }
+
+        if let Some(max_tokens) = cli.max_tokens {
+            config.budget.max_tokens = Some(max_tokens);
+        }
+
+        if let Some(strategy) = &cli.budget_strategy {
+            config.budget.strategy = strategy.clone();
+        }
+
+        if let Some(reference) = &cli.git_ref {
+            config.git.reference = Some(reference.clone());
+        }
+
+        if let Some(since) = &cli.since {
+            config.git.since = Some(since.clone());
+        }
+
+        if cli.staged {
+            config.git.staged = true;
+        }
+
+        if cli.working_tree {
+            config.git.working_tree = true;
+        }
+
+        if cli.with_diff {
+            config.git.with_diff = true;
+        }
+
+        if cli.git_history {
+            config.git.history = true;
+        }
+
+        if let Some(commits) = cli.history_commits {
+            config.git.history_commits = commits;
+        }
+
+        if let Some(contributors) = cli.top_contributors {
+            config.git.top_contributors = contributors;
+        }
+
+        if let Some(depth) = cli.clone_depth {
+            config.remote.depth = depth;
+        }
+
+        if cli.no_cache {
+            config.remote.cache = false;
+        }
+
+        if let Some(cache_dir) = &cli.cache_dir {
+            config.remote.cache_dir = Some(cache_dir.clone());
+        }
+
         if let Some(format) = &cli.format {
             config.output.format = format.clone();
         }
@@ -255,6 +665,7 @@ impl Config {
                 "json" => "json",
                 "txt" | "text" => "txt",
                 "toon" => "toon",
+                "xml" => "xml",
                 _ => "md",
             };
             let path_buf = PathBuf::from(&config.output.path);
@@ -266,6 +677,22 @@ impl Config {
             config.output.path = output_path.to_string_lossy().to_string();
         }
 
+        if let Some(split_output) = &cli.split_output {
+            config.output.split_output = Some(split_output.clone());
+        }
+
+        if cli.no_tree {
+            config.output.tree.enabled = false;
+        }
+
+        if let Some(depth) = cli.tree_depth {
+            config.output.tree.max_depth = Some(depth);
+        }
+
+        if cli.no_tree_excluded {
+            config.output.tree.include_excluded = false;
+        }
+
         if cli.open {
             config.output.open_file = true;
         }
@@ -274,6 +701,12 @@ impl Config {
             config.output.remove_comments = true;
         }
 
+        // Choosing which comments to keep implies removing the others
+        if let Some(mode) = &cli.comment_mode {
+            config.output.remove_comments = true;
+            config.output.comment_mode = mode.clone();
+        }
+
         if let Some(instruction) = &cli.instruction {
             config.instruction = Some(instruction.clone());
         }
@@ -0,0 +1,264 @@
+use crate::cache::normalize_url;
+use crate::config::{HostCredentials, RemoteConfig};
+use git2::{Cred, CredentialType, ErrorClass, ErrorCode, RemoteCallbacks};
+use log::debug;
+use std::cell::RefCell;
+use std::collections::{HashSet, VecDeque};
+use std::path::PathBuf;
+
+/// Environment variable with an access token for any host
+pub const TOKEN_ENV: &str = "REMIX_GIT_TOKEN";
+
+/// Environment variable with an access token for GitHub only
+pub const GITHUB_TOKEN_ENV: &str = "GITHUB_TOKEN";
+
+/// User name sent with a token when the URL has none; GitHub needs this one,
+/// and GitLab and most other hosts accept any name
+const TOKEN_USERNAME: &str = "x-access-token";
+
+/// Key files tried after ssh-agent, in `~/.ssh`
+const DEFAULT_SSH_KEYS: [&str; 3] = ["id_ed25519", "id_ecdsa", "id_rsa"];
+
+/// Looks up an environment variable
+type EnvLookup = Box<dyn Fn(&str) -> Option<String>>;
+
+/// A way to authenticate with a git host
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Method {
+    SshAgent,
+    SshKey(PathBuf),
+    /// A token from an environment variable
+    Token(String),
+    CredentialHelper,
+}
+
+impl Method {
+    fn describe(&self) -> String {
+        match self {
+            Self::SshAgent => "ssh-agent".to_string(),
+            Self::SshKey(path) => format!("SSH key {}", path.display()),
+            Self::Token(var) => format!("token from ${}", var),
+            Self::CredentialHelper => "git credential helper".to_string(),
+        }
+    }
+}
+
+/// Credentials offered to a git host, each method at most once per
+/// connection.
+///
+/// SSH URLs try ssh-agent, the host's key file and then the default keys in
+/// `~/.ssh`; HTTPS URLs try the host's token variable, `REMIX_GIT_TOKEN`,
+/// `GITHUB_TOKEN` (for github.com only) and then the git credential helper.
+/// Tokens are never offered over plain `http://`. Later connections start
+/// with the method that the previous one ended with.
+pub struct Credentials {
+    host: String,
+    host_config: HostCredentials,
+    env: EnvLookup,
+    ssh_methods: Vec<Method>,
+    https_methods: Vec<Method>,
+    ssh: VecDeque<Method>,
+    https: VecDeque<Method>,
+    /// The method offered last, which worked if the connection succeeded
+    last: Option<Method>,
+    tried: Vec<Method>,
+}
+
+impl Credentials {
+    /// Credentials for a URL from the config and the process environment
+    pub fn for_url(url: &str, config: &RemoteConfig) -> Self {
+        Self::with_env(url, config, |name| std::env::var(name).ok())
+    }
+
+    /// Credentials for a URL, reading environment variables with `env`
+    pub fn with_env(
+        url: &str,
+        config: &RemoteConfig,
+        env: impl Fn(&str) -> Option<String> + 'static,
+    ) -> Self {
+        let normalized = normalize_url(url);
+        let host = normalized.split('/').next().unwrap_or_default().to_string();
+        let host_config = config
+            .credentials
+            .get(&host)
+            .or_else(|| config.credentials.get("*"))
+            .cloned()
+            .unwrap_or_default();
+
+        let mut ssh = vec![Method::SshAgent];
+        if let Some(key) = &host_config.ssh_key {
+            ssh.push(Method::SshKey(key.clone()));
+        }
+        if let Some(ssh_dir) = dirs::home_dir().map(|home| home.join(".ssh")) {
+            for name in DEFAULT_SSH_KEYS {
+                let key = ssh_dir.join(name);
+                if key.exists() && host_config.ssh_key.as_ref() != Some(&key) {
+                    ssh.push(Method::SshKey(key));
+                }
+            }
+        }
+
+        // Tokens would be sent in cleartext over anything but HTTPS
+        let mut token_vars: Vec<&str> = Vec::new();
+        if url.to_lowercase().starts_with("https://") {
+            if let Some(var) = &host_config.token_env {
+                token_vars.push(var);
+            }
+            token_vars.push(TOKEN_ENV);
+            if host == "github.com" {
+                token_vars.push(GITHUB_TOKEN_ENV);
+            }
+        }
+        let mut https: Vec<Method> = token_vars
+            .into_iter()
+            .filter(|var| env(var).is_some())
+            .map(|var| Method::Token(var.to_string()))
+            .collect();
+        https.push(Method::CredentialHelper);
+
+        Self {
+            host,
+            host_config,
+            env: Box::new(env),
+            ssh: ssh.iter().cloned().collect(),
+            https: https.iter().cloned().collect(),
+            ssh_methods: ssh,
+            https_methods: https,
+            last: None,
+            tried: Vec::new(),
+        }
+    }
+
+    /// Offer every method again for a new connection, starting with the one
+    /// the previous connection ended with
+    pub fn new_connection(&mut self) {
+        for (methods, queue) in [
+            (&self.ssh_methods, &mut self.ssh),
+            (&self.https_methods, &mut self.https),
+        ] {
+            *queue = methods.iter().cloned().collect();
+            let last = queue
+                .iter()
+                .position(|method| Some(method) == self.last.as_ref())
+                .and_then(|index| queue.remove(index));
+            if let Some(method) = last {
+                queue.push_front(method);
+            }
+        }
+    }
+
+    /// Record that `method` is offered
+    fn offer(&mut self, method: &Method) {
+        debug!("Trying {} for {}", method.describe(), self.host);
+        self.tried.push(method.clone());
+        self.last = Some(method.clone());
+    }
+
+    /// The next credential for a request from libgit2, or an error once every
+    /// method allowed for it was tried
+    pub fn credential(
+        &mut self,
+        url: &str,
+        username_from_url: Option<&str>,
+        allowed: CredentialType,
+    ) -> Result<Cred, git2::Error> {
+        let configured_username = self.host_config.username.clone();
+        let username = username_from_url.or(configured_username.as_deref());
+
+        // SSH asks for the user name first when the URL has none
+        if allowed.contains(CredentialType::USERNAME) {
+            return Cred::username(username.unwrap_or("git"));
+        }
+
+        if allowed.contains(CredentialType::SSH_KEY) {
+            let username = username.unwrap_or("git");
+            if let Some(method) = self.ssh.pop_front() {
+                self.offer(&method);
+                return match &method {
+                    Method::SshKey(key) => {
+                        let passphrase = self
+                            .host_config
+                            .ssh_passphrase_env
+                            .as_deref()
+                            .and_then(|var| (self.env)(var));
+                        Cred::ssh_key(username, None, key, passphrase.as_deref())
+                    }
+                    _ => Cred::ssh_key_from_agent(username),
+                };
+            }
+        }
+
+        if allowed.contains(CredentialType::USER_PASS_PLAINTEXT) {
+            while let Some(method) = self.https.pop_front() {
+                self.offer(&method);
+                match &method {
+                    Method::Token(var) => {
+                        if let Some(token) = (self.env)(var) {
+                            return Cred::userpass_plaintext(
+                                username.unwrap_or(TOKEN_USERNAME),
+                                &token,
+                            );
+                        }
+                    }
+                    _ => {
+                        let helper = git2::Config::open_default()
+                            .and_then(|config| Cred::credential_helper(&config, url, username));
+                        if let Ok(cred) = helper {
+                            return Ok(cred);
+                        }
+                    }
+                }
+            }
+        }
+
+        Err(git2::Error::from_str(&format!(
+            "no more credentials to try for {}",
+            self.host
+        )))
+    }
+
+    /// Descriptions of the methods tried so far, in order, once for every
+    /// time they were offered
+    pub fn tried(&self) -> Vec<String> {
+        self.tried.iter().map(Method::describe).collect()
+    }
+
+    /// Turn an error from talking to the host into one that says which
+    /// methods were tried, if authentication failed
+    pub fn explain(&self, error: git2::Error) -> anyhow::Error {
+        let message = error.message().to_lowercase();
+        let auth_failed = error.code() == ErrorCode::Auth
+            || error.class() == ErrorClass::Ssh
+            || message.contains("authentication")
+            || message.contains("credentials");
+        if !auth_failed || self.tried.is_empty() {
+            return error.into();
+        }
+        let mut tried = self.tried();
+        let mut seen = HashSet::new();
+        tried.retain(|method| seen.insert(method.clone()));
+
+        anyhow::Error::new(error).context(format!(
+            "Authentication with {} failed; tried {}. Load a key into ssh-agent, set {} \
+             (or {} for github.com), or configure remote.credentials.\"{}\"",
+            self.host,
+            tried.join(", "),
+            TOKEN_ENV,
+            GITHUB_TOKEN_ENV,
+            self.host
+        ))
+    }
+}
+
+/// Remote callbacks for a new connection that ask `credentials` for every
+/// authentication request
+pub fn remote_callbacks(credentials: &RefCell<Credentials>) -> RemoteCallbacks<'_> {
+    credentials.borrow_mut().new_connection();
+    let mut callbacks = RemoteCallbacks::new();
+    callbacks.credentials(move |url, username_from_url, allowed| {
+        credentials
+            .borrow_mut()
+            .credential(url, username_from_url, allowed)
+    });
+    callbacks
+}
@@ -1,12 +1,60 @@
 use crate::config::OutputConfig;
-use crate::packer::PackedRepository;
+use crate::history::FileHistory;
+use crate::packer::{FileContent, PackedRepository};
+use crate::splitter::{part_path, split_output, SplitLimit};
 use crate::utils::{format_size, open_file};
 use anyhow::{Context, Result};
 use log::{info, warn};
 use std::fs;
 use std::io::Write;
 use std::path::Path;
-use crate::security::SecurityCheckStatus;
+use crate::security::{SecretFinding, SecurityCheckStatus};
+
+/// Number of files listed in the token statistics section
+const TOP_TOKEN_FILES: usize = 5;
+
+/// Names accepted by `--format`
+const KNOWN_FORMATS: &[&str] = &["md", "markdown", "json", "txt", "text", "toon", "xml"];
+
+/// Opening of the XML `<file_summary>` block, following repomix's wording
+const XML_FILE_SUMMARY_HEADER: &str = "\
+This file is a merged representation of the entire codebase, combined into a single document by Remix.
+
+<file_summary>
+This section contains a summary of this file.
+
+<purpose>
+This file contains a packed representation of the entire repository's contents.
+It is designed to be easily consumable by AI systems for analysis, code review,
+or other automated processes.
+</purpose>
+
+<file_format>
+The content is organized as follows:
+1. This summary section
+2. Repository information
+3. Directory structure
+4. Repository files, each consisting of:
+  - File path as an attribute
+  - Full contents of the file
+</file_format>
+
+<usage_guidelines>
+- This file should be treated as read-only. Any changes should be made to the
+  original repository files, not this packed version.
+- When processing this file, use the file path to distinguish
+  between different files in the repository.
+- Be aware that this file may contain sensitive information. Handle it with
+  the same level of security as you would the original repository.
+</usage_guidelines>
+
+";
+
+/// Fixed notes at the start of the XML `<notes>` block
+const XML_NOTES: &str = "\
+- Some files may have been excluded based on .gitignore rules and Remix's configuration
+- Binary files are not included in this packed representation. Please refer to the Repository Structure section for a complete list of file paths, including binary files
+";
 
 pub fn output_result(repo: &PackedRepository, config: &OutputConfig) -> Result<()> {
     let output_path = &config.path;
@@ -14,18 +62,61 @@ pub fn output_result(repo: &PackedRepository, config: &OutputConfig) -> Result<(
 
     info!("Generating output in {} format to {}", format, output_path);
 
-    let content = match format.as_str() {
-        "md" | "markdown" => format_markdown(repo),
+    if let Some(spec) = &config.split_output {
+        let limit = SplitLimit::parse(spec)?;
+        let parts = split_output(repo, format, limit)?;
+
+        if parts.len() > 1 {
+            let paths: Vec<String> = (1..=parts.len())
+                .map(|part| part_path(output_path, part))
+                .collect();
+
+            for (path, content) in paths.iter().zip(&parts) {
+                write_output(path, content)?;
+            }
+
+            info!("Output written to {} parts", parts.len());
+
+            if config.open_file {
+                info!("Opening first output part");
+                open_file(&paths[0])
+                    .context(format!("Failed to open output file: {}", paths[0]))?;
+            }
+
+            return Ok(());
+        }
+    }
+
+    if !KNOWN_FORMATS.contains(&format.as_str()) {
+        warn!("Unknown format '{}', defaulting to markdown", format);
+    }
+    let content = format_output(repo, format)?;
+
+    write_output(output_path, &content)?;
+
+    // Open the file if requested
+    if config.open_file {
+        info!("Opening output file");
+        open_file(output_path).context(format!("Failed to open output file: {}", output_path))?;
+    }
+
+    Ok(())
+}
+
+/// Render the whole output in a format, as a single part; unknown formats
+/// are rendered as markdown
+pub fn format_output(repo: &PackedRepository, format: &str) -> Result<String> {
+    Ok(match format {
         "json" => format_json(repo)?,
         "txt" | "text" => format_text(repo),
         "toon" => format_toon(repo)?,
-        _ => {
-            warn!("Unknown format '{}', defaulting to markdown", format);
-            format_markdown(repo)
-        }
-    };
+        "xml" => format_xml(repo),
+        _ => format_markdown(repo),
+    })
+}
 
-    // Write the output to a file
+/// Write generated output to a file
+fn write_output(output_path: &str, content: &str) -> Result<()> {
     let mut file = fs::File::create(output_path)
         .context(format!("Failed to create output file: {}", output_path))?;
 
@@ -34,16 +125,22 @@ pub fn output_result(repo: &PackedRepository, config: &OutputConfig) -> Result<(
 
     info!("Output written to {}", output_path);
 
-    // Open the file if requested
-    if config.open_file {
-        info!("Opening output file");
-        open_file(output_path).context(format!("Failed to open output file: {}", output_path))?;
-    }
-
     Ok(())
 }
 
 pub fn format_markdown(repo: &PackedRepository) -> String {
+    let mut output = markdown_preamble(repo);
+
+    output.push_str("\n# Files\n\n");
+
+    let files: Vec<&FileContent> = repo.files.iter().collect();
+    output.push_str(&markdown_files(&files));
+
+    output
+}
+
+/// Markdown for everything that precedes the file contents: instruction, summary and reports
+pub(crate) fn markdown_preamble(repo: &PackedRepository) -> String {
     let mut output = String::new();
 
     // Add user instruction if provided
@@ -55,6 +152,9 @@ pub fn format_markdown(repo: &PackedRepository) -> String {
 
     // Add repository summary
     output.push_str("# Repository Summary\n\n");
+    for (label, value) in remote_lines(repo) {
+        output.push_str(&format!("- **{}:** {}\n", label, value));
+    }
     output.push_str(&format!("- **Files:** {}\n", repo.summary.file_count));
     output.push_str(&format!(
         "- **Directories:** {}\n",
@@ -64,6 +164,13 @@ pub fn format_markdown(repo: &PackedRepository) -> String {
         "- **Total Size:** {}\n",
         format_size(repo.summary.total_size)
     ));
+    output.push_str(&format!(
+        "- **Tokens:** {} ({})\n",
+        repo.summary.total_tokens, repo.summary.token_encoding
+    ));
+    if let Some(budget) = repo.summary.token_budget {
+        output.push_str(&format!("- **Token Budget:** {}\n", budget));
+    }
     output.push_str(&format!(
         "- **Binary Files:** {}\n",
         repo.summary.binary_file_count
@@ -76,6 +183,58 @@ pub fn format_markdown(repo: &PackedRepository) -> String {
         ));
     }
 
+    let top_files = top_files_by_tokens(repo);
+    if !top_files.is_empty() {
+        output.push_str("\n## Largest Files by Tokens\n\n");
+        for (i, file) in top_files.iter().enumerate() {
+            output.push_str(&format!(
+                "{}. `{}` - {} tokens ({:.1}%)\n",
+                i + 1,
+                file.relative_path,
+                file.token_count,
+                token_share(file, repo)
+            ));
+        }
+        output.push('\n');
+    }
+
+    // Add the directory tree
+    if let Some(tree) = &repo.directory_tree {
+        output.push_str("\n# Directory Structure\n\n```\n");
+        output.push_str(&tree.render());
+        output.push_str("```\n\n");
+    }
+
+    // Add recent commits and per-file activity
+    if let Some(history) = &repo.git_history {
+        output.push_str("## Git History\n\n");
+        for commit in &history.recent_commits {
+            output.push_str(&format!(
+                "- `{}` {} {}: {}\n",
+                commit.id,
+                short_date(&commit.date),
+                commit.author,
+                commit.message
+            ));
+        }
+
+        let files = files_by_churn(repo);
+        if !files.is_empty() {
+            output.push_str("\n| File | Last commit | Commits | Top contributors |\n");
+            output.push_str("|------|-------------|---------|------------------|\n");
+            for (file, history) in files {
+                output.push_str(&format!(
+                    "| `{}` | {} | {} | {} |\n",
+                    file.relative_path,
+                    short_date(&history.last_commit),
+                    history.commit_count,
+                    contributors(history)
+                ));
+            }
+        }
+        output.push('\n');
+    }
+
     // Add security check results if available
     match &repo.security_check_status {
         SecurityCheckStatus::Disabled => {
@@ -84,25 +243,85 @@ pub fn format_markdown(repo: &PackedRepository) -> String {
         }
         SecurityCheckStatus::CompletedNoFindings => {
             output.push_str("## Security Check\n\n");
-            output.push_str("✅ **Security check completed - no suspicious files found**\n");
+            output.push_str("✅ **Security check completed - no secrets found**\n");
         }
         SecurityCheckStatus::CompletedWithFindings => {
             output.push_str("\n## Security Check Results\n\n");
+            let findings = repo.security_findings.as_deref().unwrap_or_default();
             output.push_str(&format!(
-                "⚠️ **{} suspicious file(s) detected that may contain sensitive information:**\n\n",
-                repo.suspicious_files.as_ref().map_or(0, |v| v.len())
+                "⚠️ **{} secret(s) detected:**\n\n",
+                findings.len()
             ));
 
-            for (i, file) in repo.suspicious_files.as_ref().unwrap_or(&vec![]).iter().enumerate() {
-                output.push_str(&format!("{}. `{}`\n", i + 1, file));
+            for (i, finding) in findings.iter().enumerate() {
+                output.push_str(&format!(
+                    "{}. `{}:{}:{}` **{}**: `{}` (fingerprint `{}`)\n",
+                    i + 1,
+                    finding.file,
+                    finding.line,
+                    finding.column,
+                    finding.rule_id,
+                    finding.snippet,
+                    finding.fingerprint
+                ));
             }
 
             output
                 .push_str("\n> **Note:** Please review these files before sharing this output.\n");
         }
-        SecurityCheckStatus::Failed(error) => {
-            output.push_str("## Security Check\n\n");
-            output.push_str(&format!("❌ **Security check failed**: {}\n", error));
+    }
+
+    // Add secrets that were redacted, or whose files were left out
+    if let Some(redactions) = &repo.redactions {
+        if !redactions.is_empty() {
+            output.push_str("\n## Redacted Secrets\n\n");
+            output.push_str(
+                "The following secrets were replaced with `[REDACTED:<rule>]` placeholders:\n\n",
+            );
+
+            for (i, finding) in redactions.iter().enumerate() {
+                output.push_str(&format!(
+                    "{}. `{}:{}:{}` **{}**\n",
+                    i + 1,
+                    finding.file,
+                    finding.line,
+                    finding.column,
+                    finding.rule_id
+                ));
+            }
+        }
+    }
+
+    if let Some(skipped) = &repo.skipped_sensitive_files {
+        if !skipped.is_empty() {
+            output.push_str("\n## Files Skipped for Secrets\n\n");
+            output.push_str("The following files contain secrets and were left out:\n\n");
+
+            for (i, file) in skipped.iter().enumerate() {
+                output.push_str(&format!("{}. `{}`\n", i + 1, file));
+            }
+        }
+    }
+
+    if let Some(findings) = &repo.on_disk_only_findings {
+        if !findings.is_empty() {
+            output.push_str("\n## Secrets Not in This Output\n\n");
+            output.push_str(
+                "These secrets are in the files on disk but not in the packed content, \
+                 e.g. because they were in removed comments or function bodies:\n\n",
+            );
+
+            for (i, finding) in findings.iter().enumerate() {
+                output.push_str(&format!(
+                    "{}. `{}:{}:{}` **{}** (on disk only): `{}`\n",
+                    i + 1,
+                    finding.file,
+                    finding.line,
+                    finding.column,
+                    finding.rule_id,
+                    finding.snippet
+                ));
+            }
         }
     }
 
@@ -120,13 +339,40 @@ pub fn format_markdown(repo: &PackedRepository) -> String {
         }
     }
 
-    output.push_str("\n# Files\n\n");
+    // Add files that were cut down to fit the token budget
+    if let Some(omitted_files) = &repo.omitted_files {
+        if !omitted_files.is_empty() {
+            output.push_str("\n## Omitted Files\n\n");
+            output.push_str(&format!(
+                "The following files were truncated, summarized or dropped to fit the token budget of {} tokens:\n\n",
+                repo.summary.token_budget.unwrap_or_default()
+            ));
+
+            for (i, file) in omitted_files.iter().enumerate() {
+                output.push_str(&format!(
+                    "{}. `{}` - {} (kept {} of {} tokens)\n",
+                    i + 1,
+                    file.relative_path,
+                    file.kind.label(),
+                    file.kept_tokens,
+                    file.original_tokens
+                ));
+            }
+        }
+    }
+
+    output
+}
+
+/// Markdown for a set of files, grouped by directory
+pub(crate) fn markdown_files(files: &[&FileContent]) -> String {
+    let mut output = String::new();
 
     // Group files by directory
-    let mut files_by_dir: std::collections::BTreeMap<String, Vec<&crate::packer::FileContent>> =
+    let mut files_by_dir: std::collections::BTreeMap<String, Vec<&FileContent>> =
         std::collections::BTreeMap::new();
 
-    for file in &repo.files {
+    for &file in files {
         let path = Path::new(&file.relative_path);
         let parent = path
             .parent()
@@ -155,6 +401,7 @@ pub fn format_markdown(repo: &PackedRepository) -> String {
             // Add file metadata
             output.push_str(&format!("- **Path:** {}\n", file.relative_path));
             output.push_str(&format!("- **Size:** {}\n", format_size(file.size)));
+            output.push_str(&format!("- **Tokens:** {}\n", file.token_count));
 
             if !file.extension.is_empty() {
                 output.push_str(&format!("- **Type:** {}\n", file.extension));
@@ -171,6 +418,12 @@ pub fn format_markdown(repo: &PackedRepository) -> String {
             output.push_str(&file.content);
             output.push('\n');
             output.push_str("```\n\n");
+
+            if let Some(diff) = &file.diff {
+                output.push_str("**Changes:**\n\n```diff\n");
+                output.push_str(diff.trim_end());
+                output.push_str("\n```\n\n");
+            }
         }
     }
 
@@ -186,7 +439,203 @@ pub fn format_toon(repo: &PackedRepository) -> Result<String> {
     rtoon::encode_default(&value).context("Failed to encode repository to TOON")
 }
 
+/// Format the repository using repomix's XML layout
+pub fn format_xml(repo: &PackedRepository) -> String {
+    let mut output = xml_preamble(repo);
+
+    if let Some(tree) = &repo.directory_tree {
+        output.push_str(&xml_directory_structure(&tree.render_indented()));
+    }
+
+    let files: Vec<&FileContent> = repo.files.iter().collect();
+    output.push_str(&xml_files(&files));
+
+    output.push_str(&xml_instruction(repo));
+
+    output
+}
+
+/// The `<file_summary>` block that opens the XML output
+pub(crate) fn xml_preamble(repo: &PackedRepository) -> String {
+    let mut output = String::new();
+
+    output.push_str(XML_FILE_SUMMARY_HEADER);
+
+    output.push_str("<notes>\n");
+    output.push_str(XML_NOTES);
+    if let Some(binary_files) = &repo.binary_files {
+        for file in binary_files {
+            output.push_str(&format!("- Binary file omitted: {}\n", escape_xml(file)));
+        }
+    }
+    if let Some(omitted_files) = &repo.omitted_files {
+        for file in omitted_files {
+            output.push_str(&format!(
+                "- File {} to fit the token budget: {} (kept {} of {} tokens)\n",
+                file.kind.label(),
+                escape_xml(&file.relative_path),
+                file.kept_tokens,
+                file.original_tokens
+            ));
+        }
+    }
+    if let Some(redactions) = &repo.redactions {
+        for finding in redactions {
+            output.push_str(&format!(
+                "- Secret redacted: {}:{}:{} [{}]\n",
+                escape_xml(&finding.file),
+                finding.line,
+                finding.column,
+                escape_xml(&finding.rule_id)
+            ));
+        }
+    }
+    if let Some(skipped) = &repo.skipped_sensitive_files {
+        for file in skipped {
+            output.push_str(&format!(
+                "- File omitted because it contains secrets: {}\n",
+                escape_xml(file)
+            ));
+        }
+    }
+    if let Some(findings) = &repo.on_disk_only_findings {
+        for finding in findings {
+            output.push_str(&format!(
+                "- Secret on disk only, not in this output: {}\n",
+                escape_xml(&finding_line(finding))
+            ));
+        }
+    }
+    output.push_str("</notes>\n\n");
+
+    output.push_str("<additional_info>\n");
+    for (label, value) in remote_lines(repo) {
+        output.push_str(&format!("{}: {}\n", label, escape_xml(&value)));
+    }
+    output.push_str(&format!("Files: {}\n", repo.summary.file_count));
+    output.push_str(&format!("Directories: {}\n", repo.summary.directory_count));
+    output.push_str(&format!(
+        "Total Size: {}\n",
+        format_size(repo.summary.total_size)
+    ));
+    output.push_str(&format!(
+        "Tokens: {} ({})\n",
+        repo.summary.total_tokens, repo.summary.token_encoding
+    ));
+    if !repo.summary.extensions.is_empty() {
+        output.push_str(&format!(
+            "Extensions: {}\n",
+            repo.summary.extensions.join(", ")
+        ));
+    }
+
+    match &repo.security_check_status {
+        SecurityCheckStatus::Disabled => {
+            output.push_str("Security check: disabled\n");
+        }
+        SecurityCheckStatus::CompletedNoFindings => {
+            output.push_str("Security check: no secrets found\n");
+        }
+        SecurityCheckStatus::CompletedWithFindings => {
+            output.push_str("Security check: secrets detected, review before sharing:\n");
+            for finding in repo.security_findings.as_deref().unwrap_or_default() {
+                output.push_str(&format!("- {}\n", escape_xml(&finding_line(finding))));
+            }
+        }
+    }
+    output.push_str("</additional_info>\n\n");
+
+    output.push_str("</file_summary>\n\n");
+
+    output
+}
+
+/// The `<directory_structure>` block for an already rendered tree
+pub(crate) fn xml_directory_structure(tree: &str) -> String {
+    format!(
+        "<directory_structure>\n{}</directory_structure>\n\n",
+        escape_xml(tree)
+    )
+}
+
+/// The `<files>` block with one `<file path="...">` element per file
+pub(crate) fn xml_files(files: &[&FileContent]) -> String {
+    let mut output = String::new();
+
+    output.push_str("<files>\n");
+    output.push_str("This section contains the contents of the repository's files.\n\n");
+
+    for file in files {
+        output.push_str(&format!(
+            "<file path=\"{}\">\n{}\n</file>\n\n",
+            escape_xml(&file.relative_path),
+            cdata(&file.content)
+        ));
+
+        if let Some(diff) = &file.diff {
+            output.push_str(&format!(
+                "<diff path=\"{}\">\n{}\n</diff>\n\n",
+                escape_xml(&file.relative_path),
+                cdata(diff.trim_end())
+            ));
+        }
+    }
+
+    output.push_str("</files>\n");
+
+    output
+}
+
+/// The trailing `<instruction>` block, empty if no instruction was given
+pub(crate) fn xml_instruction(repo: &PackedRepository) -> String {
+    match &repo.instruction {
+        Some(instruction) => format!("\n<instruction>\n{}\n</instruction>\n", cdata(instruction)),
+        None => String::new(),
+    }
+}
+
+/// One-line description of a secret: `path:line:column [rule] snippet`
+fn finding_line(finding: &SecretFinding) -> String {
+    format!(
+        "{}:{}:{} [{}] {} (fingerprint {})",
+        finding.file,
+        finding.line,
+        finding.column,
+        finding.rule_id,
+        finding.snippet,
+        finding.fingerprint
+    )
+}
+
+/// Escape text for use in XML character data and attribute values
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// Wrap text in a CDATA section, splitting any `]]>` so it can't end the section early
+fn cdata(text: &str) -> String {
+    format!("<![CDATA[{}]]>", text.replace("]]>", "]]]]><![CDATA[>"))
+}
+
 pub fn format_text(repo: &PackedRepository) -> String {
+    let mut output = text_preamble(repo);
+
+    output.push_str("\nFILES:\n\n");
+
+    // Output each file
+    for file in &repo.files {
+        output.push_str(&text_file(file));
+    }
+
+    output
+}
+
+/// Plain text for everything that precedes the file contents: instruction, summary and reports
+pub(crate) fn text_preamble(repo: &PackedRepository) -> String {
     let mut output = String::new();
 
     // Add user instruction if provided
@@ -198,12 +647,22 @@ pub fn format_text(repo: &PackedRepository) -> String {
 
     // Add repository summary
     output.push_str("REPOSITORY SUMMARY:\n\n");
+    for (label, value) in remote_lines(repo) {
+        output.push_str(&format!("{}: {}\n", label, value));
+    }
     output.push_str(&format!("Files: {}\n", repo.summary.file_count));
     output.push_str(&format!("Directories: {}\n", repo.summary.directory_count));
     output.push_str(&format!(
         "Total Size: {}\n",
         format_size(repo.summary.total_size)
     ));
+    output.push_str(&format!(
+        "Tokens: {} ({})\n",
+        repo.summary.total_tokens, repo.summary.token_encoding
+    ));
+    if let Some(budget) = repo.summary.token_budget {
+        output.push_str(&format!("Token Budget: {}\n", budget));
+    }
     output.push_str(&format!(
         "Binary Files: {}\n",
         repo.summary.binary_file_count
@@ -216,6 +675,60 @@ pub fn format_text(repo: &PackedRepository) -> String {
         ));
     }
 
+    let top_files = top_files_by_tokens(repo);
+    if !top_files.is_empty() {
+        output.push_str("\nLARGEST FILES BY TOKENS:\n\n");
+        for (i, file) in top_files.iter().enumerate() {
+            output.push_str(&format!(
+                "{}. {} - {} tokens ({:.1}%)\n",
+                i + 1,
+                file.relative_path,
+                file.token_count,
+                token_share(file, repo)
+            ));
+        }
+        output.push('\n');
+    }
+
+    // Add the directory tree
+    if let Some(tree) = &repo.directory_tree {
+        output.push_str("\nDIRECTORY STRUCTURE:\n\n");
+        output.push_str(&tree.render());
+        output.push('\n');
+    }
+
+    // Add recent commits and per-file activity
+    if let Some(history) = &repo.git_history {
+        output.push_str("GIT HISTORY:\n\n");
+        for commit in &history.recent_commits {
+            output.push_str(&format!(
+                "{} {} {}: {}\n",
+                commit.id,
+                short_date(&commit.date),
+                commit.author,
+                commit.message
+            ));
+        }
+
+        let files = files_by_churn(repo);
+        if !files.is_empty() {
+            output.push_str("\nFILE ACTIVITY (last commit, commits, top contributors):\n\n");
+            for (file, history) in files {
+                output.push_str(&format!(
+                    "{} - {}, {} commits",
+                    file.relative_path,
+                    short_date(&history.last_commit),
+                    history.commit_count
+                ));
+                if !history.top_contributors.is_empty() {
+                    output.push_str(&format!(", {}", contributors(history)));
+                }
+                output.push('\n');
+            }
+        }
+        output.push('\n');
+    }
+
     // Add security check results if available
     match &repo.security_check_status {
         SecurityCheckStatus::Disabled => {
@@ -224,22 +737,69 @@ pub fn format_text(repo: &PackedRepository) -> String {
         }
         SecurityCheckStatus::CompletedNoFindings => {
             output.push_str("SECURITY CHECK:\n\n");
-            output.push_str("Security check completed - no suspicious files found.\n\n");
+            output.push_str("Security check completed - no secrets found.\n\n");
         }
         SecurityCheckStatus::CompletedWithFindings => {
             output.push_str("SECURITY CHECK:\n\n");
             output.push_str("WARNING: ");
-            output.push_str(&format!("{} suspicious file(s) detected that may contain sensitive information:\n\n", repo.suspicious_files.as_ref().map_or(0, |v| v.len())));
+            let findings = repo.security_findings.as_deref().unwrap_or_default();
+            output.push_str(&format!("{} secret(s) detected:\n\n", findings.len()));
 
-            for (i, file) in repo.suspicious_files.as_ref().unwrap_or(&vec![]).iter().enumerate() {
-                output.push_str(&format!("{}. {}\n", i + 1, file));
+            for (i, finding) in findings.iter().enumerate() {
+                output.push_str(&format!("{}. {}\n", i + 1, finding_line(finding)));
             }
 
             output.push_str("\nPlease review these files before sharing this output.\n");
         }
-        SecurityCheckStatus::Failed(error) => {
-            output.push_str("SECURITY CHECK:\n\n");
-            output.push_str(&format!("Security check failed: {}\n", error));
+    }
+
+    // Add secrets that were redacted, or whose files were left out
+    if let Some(redactions) = &repo.redactions {
+        if !redactions.is_empty() {
+            output.push_str("\nREDACTED SECRETS:\n\n");
+            output.push_str(
+                "The following secrets were replaced with [REDACTED:<rule>] placeholders:\n\n",
+            );
+
+            for (i, finding) in redactions.iter().enumerate() {
+                output.push_str(&format!(
+                    "{}. {}:{}:{} [{}]\n",
+                    i + 1,
+                    finding.file,
+                    finding.line,
+                    finding.column,
+                    finding.rule_id
+                ));
+            }
+        }
+    }
+
+    if let Some(skipped) = &repo.skipped_sensitive_files {
+        if !skipped.is_empty() {
+            output.push_str("\nFILES SKIPPED FOR SECRETS:\n\n");
+            output.push_str("The following files contain secrets and were left out:\n\n");
+
+            for (i, file) in skipped.iter().enumerate() {
+                output.push_str(&format!("{}. {}\n", i + 1, file));
+            }
+        }
+    }
+
+    if let Some(findings) = &repo.on_disk_only_findings {
+        if !findings.is_empty() {
+            output.push_str("\nSECRETS NOT IN THIS OUTPUT:\n\n");
+            output.push_str(
+                "These secrets are in the files on disk but not in the packed content, \
+                 e.g. because they were in removed comments or function bodies:\n\n",
+            );
+
+            for (i, finding) in findings.iter().enumerate() {
+                output.push_str(&format!(
+                    "{}. (on disk only) {}\n",
+                    i + 1,
+                    finding_line(finding)
+                ));
+            }
         }
     }
 
@@ -257,22 +817,122 @@ pub fn format_text(repo: &PackedRepository) -> String {
         }
     }
 
-    output.push_str("\nFILES:\n\n");
-
-    // Output each file
-    for file in &repo.files {
-        output.push_str(&format!("FILE: {}\n", file.relative_path));
-        output.push_str(&format!("SIZE: {}\n", format_size(file.size)));
+    // Add files that were cut down to fit the token budget
+    if let Some(omitted_files) = &repo.omitted_files {
+        if !omitted_files.is_empty() {
+            output.push_str("\nOMITTED FILES:\n\n");
+            output.push_str(&format!(
+                "The following files were truncated, summarized or dropped to fit the token budget of {} tokens:\n\n",
+                repo.summary.token_budget.unwrap_or_default()
+            ));
 
-        if !file.extension.is_empty() {
-            output.push_str(&format!("TYPE: {}\n", file.extension));
+            for (i, file) in omitted_files.iter().enumerate() {
+                output.push_str(&format!(
+                    "{}. {} - {} (kept {} of {} tokens)\n",
+                    i + 1,
+                    file.relative_path,
+                    file.kind.label(),
+                    file.kept_tokens,
+                    file.original_tokens
+                ));
+            }
         }
+    }
+
+    output
+}
+
+/// Plain text for a single file
+pub(crate) fn text_file(file: &FileContent) -> String {
+    let mut output = String::new();
+
+    output.push_str(&format!("FILE: {}\n", file.relative_path));
+    output.push_str(&format!("SIZE: {}\n", format_size(file.size)));
+    output.push_str(&format!("TOKENS: {}\n", file.token_count));
+
+    if !file.extension.is_empty() {
+        output.push_str(&format!("TYPE: {}\n", file.extension));
+    }
 
-        output.push_str("\nCONTENT:\n");
-        output.push_str(&file.content);
+    output.push_str("\nCONTENT:\n");
+    output.push_str(&file.content);
+    output.push_str("\n\n");
+
+    if let Some(diff) = &file.diff {
+        output.push_str("DIFF:\n");
+        output.push_str(diff.trim_end());
         output.push_str("\n\n");
-        output.push_str("--------------------------------\n\n");
     }
+    output.push_str("--------------------------------\n\n");
 
     output
 }
+
+/// Get the files with the highest token counts, largest first
+fn top_files_by_tokens(repo: &PackedRepository) -> Vec<&FileContent> {
+    let mut files: Vec<&FileContent> = repo.files.iter().filter(|f| f.token_count > 0).collect();
+    files.sort_by(|a, b| {
+        b.token_count
+            .cmp(&a.token_count)
+            .then_with(|| a.relative_path.cmp(&b.relative_path))
+    });
+    files.truncate(TOP_TOKEN_FILES);
+    files
+}
+
+/// Label and value of the summary lines naming the remote repository and
+/// commit a pack was made from
+fn remote_lines(repo: &PackedRepository) -> Vec<(&'static str, String)> {
+    let remote = match &repo.remote {
+        Some(remote) => remote,
+        None => return Vec::new(),
+    };
+    let mut lines = vec![
+        ("Repository", remote.url.clone()),
+        ("Ref", remote.reference.clone()),
+        ("Commit", remote.commit.clone()),
+    ];
+    if let Some(subpath) = &remote.subpath {
+        lines.push(("Path", subpath.clone()));
+    }
+    lines
+}
+
+/// Files with git history, most commits first
+fn files_by_churn(repo: &PackedRepository) -> Vec<(&FileContent, &FileHistory)> {
+    let mut files: Vec<(&FileContent, &FileHistory)> = repo
+        .files
+        .iter()
+        .filter_map(|file| file.history.as_ref().map(|history| (file, history)))
+        .collect();
+    files.sort_by(|(a, a_history), (b, b_history)| {
+        b_history
+            .commit_count
+            .cmp(&a_history.commit_count)
+            .then_with(|| a.relative_path.cmp(&b.relative_path))
+    });
+    files
+}
+
+/// Top contributors of a file as `name (commits)`, comma separated
+fn contributors(history: &FileHistory) -> String {
+    history
+        .top_contributors
+        .iter()
+        .map(|contributor| format!("{} ({})", contributor.name, contributor.commits))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// The date part of an RFC 3339 timestamp
+fn short_date(date: &str) -> &str {
+    date.get(..10).unwrap_or(date)
+}
+
+/// Percentage of the total token count taken up by a file
+fn token_share(file: &FileContent, repo: &PackedRepository) -> f64 {
+    if repo.summary.total_tokens == 0 {
+        return 0.0;
+    }
+    file.token_count as f64 * 100.0 / repo.summary.total_tokens as f64
+}
@@ -0,0 +1,204 @@
+use crate::config::GitConfig;
+use anyhow::{Context, Result};
+use chrono::{DateTime, SecondsFormat};
+use git2::{Commit, DiffOptions, Repository, Sort, Time};
+use log::{debug, info};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Most commits walked to count per-file activity, so huge histories stay fast
+const MAX_HISTORY_COMMITS: usize = 10_000;
+
+/// Length of the abbreviated commit ids shown in the output
+const SHORT_ID_LENGTH: usize = 7;
+
+/// A commit in the recent history
+#[derive(Debug, Serialize, Clone)]
+pub struct CommitSummary {
+    pub id: String,
+    pub author: String,
+    /// Commit time in RFC 3339 (UTC)
+    pub date: String,
+    /// First line of the commit message
+    pub message: String,
+}
+
+/// Number of commits by one author
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+pub struct Contributor {
+    pub name: String,
+    pub commits: usize,
+}
+
+/// Git activity of a single file
+#[derive(Debug, Serialize, Clone)]
+pub struct FileHistory {
+    /// Time of the last commit that changed the file, in RFC 3339 (UTC)
+    pub last_commit: String,
+    /// Number of commits that changed the file (churn)
+    pub commit_count: usize,
+    /// Authors with the most commits to the file, most active first
+    pub top_contributors: Vec<Contributor>,
+}
+
+/// Git history of the packed repository
+#[derive(Debug, Serialize, Clone)]
+pub struct GitHistory {
+    /// The most recent commits, newest first
+    pub recent_commits: Vec<CommitSummary>,
+}
+
+/// Per-file counters while walking the history
+#[derive(Default)]
+struct FileActivity {
+    last_commit: Option<Time>,
+    commit_count: usize,
+    authors: HashMap<String, usize>,
+}
+
+/// Walk the history of the repository containing `base_path`, from HEAD or the
+/// packed ref, newest first.
+///
+/// Returns the recent commits and the history of every file under `base_path`,
+/// keyed by its path relative to `base_path` with `/` separators. Merge commits
+/// are compared with their first parent, and renames start a new history.
+pub fn collect_history(
+    base_path: &Path,
+    config: &GitConfig,
+) -> Result<(GitHistory, HashMap<String, FileHistory>)> {
+    let repo = Repository::discover(base_path)
+        .with_context(|| format!("Not a git repository: {}", base_path.display()))?;
+    let prefix = match repo.workdir() {
+        Some(workdir) => {
+            let workdir = workdir
+                .canonicalize()
+                .context("Failed to resolve the git working directory")?;
+            let mut base = base_path
+                .canonicalize()
+                .with_context(|| format!("Path not found: {}", base_path.display()))?;
+            // A single file is relative to its directory
+            if base.is_file() {
+                base.pop();
+            }
+            base.strip_prefix(&workdir)
+                .map(Path::to_path_buf)
+                .unwrap_or_default()
+        }
+        None => PathBuf::new(),
+    };
+
+    let start = match &config.reference {
+        Some(reference) => repo
+            .revparse_single(reference)
+            .and_then(|object| object.peel_to_commit())
+            .with_context(|| format!("Unknown git ref: {}", reference))?,
+        None => repo
+            .head()
+            .and_then(|head| head.peel_to_commit())
+            .context("Failed to read HEAD")?,
+    };
+
+    let mut revwalk = repo.revwalk().context("Failed to walk the git history")?;
+    revwalk.set_sorting(Sort::TIME)?;
+    revwalk.push(start.id())?;
+
+    let mut recent_commits = Vec::new();
+    let mut activity: HashMap<String, FileActivity> = HashMap::new();
+    let mut walked = 0;
+    for id in revwalk {
+        if walked == MAX_HISTORY_COMMITS {
+            info!(
+                "Git history is limited to the last {} commits",
+                MAX_HISTORY_COMMITS
+            );
+            break;
+        }
+        walked += 1;
+
+        let commit = repo.find_commit(id?)?;
+        let author = author_name(&commit);
+        if recent_commits.len() < config.history_commits {
+            recent_commits.push(CommitSummary {
+                id: commit.id().to_string()[..SHORT_ID_LENGTH].to_string(),
+                author: author.clone(),
+                date: format_time(commit.time()),
+                message: commit.summary().unwrap_or_default().to_string(),
+            });
+        }
+
+        for path in changed_paths(&repo, &commit)? {
+            let relative_path = match path.strip_prefix(&prefix) {
+                Ok(relative_path) => relative_path.to_string_lossy().replace('\\', "/"),
+                Err(_) => continue,
+            };
+
+            let file = activity.entry(relative_path).or_default();
+            file.last_commit.get_or_insert(commit.time());
+            file.commit_count += 1;
+            *file.authors.entry(author.clone()).or_default() += 1;
+        }
+    }
+    debug!("Walked {} commits for the git history", walked);
+
+    let files = activity
+        .into_iter()
+        .filter_map(|(path, file)| {
+            let mut contributors: Vec<Contributor> = file
+                .authors
+                .into_iter()
+                .map(|(name, commits)| Contributor { name, commits })
+                .collect();
+            contributors.sort_by(|a, b| b.commits.cmp(&a.commits).then(a.name.cmp(&b.name)));
+            contributors.truncate(config.top_contributors);
+
+            Some((
+                path,
+                FileHistory {
+                    last_commit: format_time(file.last_commit?),
+                    commit_count: file.commit_count,
+                    top_contributors: contributors,
+                },
+            ))
+        })
+        .collect();
+
+    Ok((GitHistory { recent_commits }, files))
+}
+
+/// Paths changed by a commit, compared with its first parent
+fn changed_paths(repo: &Repository, commit: &Commit) -> Result<Vec<PathBuf>> {
+    let tree = commit.tree()?;
+    let parent_tree = match commit.parent(0) {
+        Ok(parent) => Some(parent.tree()?),
+        Err(_) => None,
+    };
+
+    // Only the changed paths are needed, not the content changes
+    let mut options = DiffOptions::new();
+    options.skip_binary_check(true);
+    let diff = repo
+        .diff_tree_to_tree(parent_tree.as_ref(), Some(&tree), Some(&mut options))
+        .with_context(|| format!("Failed to diff commit {}", commit.id()))?;
+
+    Ok(diff
+        .deltas()
+        .filter_map(|delta| delta.new_file().path().map(Path::to_path_buf))
+        .collect())
+}
+
+fn author_name(commit: &Commit) -> String {
+    let author = commit.author();
+    author
+        .name()
+        .or_else(|| author.email())
+        .unwrap_or("unknown")
+        .to_string()
+}
+
+/// Format a git time as RFC 3339 in UTC
+fn format_time(time: Time) -> String {
+    DateTime::from_timestamp(time.seconds(), 0)
+        .map(|date| date.to_rfc3339_opts(SecondsFormat::Secs, true))
+        .unwrap_or_default()
+}
@@ -1,9 +1,23 @@
+pub mod archive;
+pub mod budget;
+pub mod cache;
+pub mod changes;
+pub mod check;
 pub mod cli;
 pub mod comments;
+pub mod compress;
 pub mod config;
+pub mod credentials;
 pub mod formatter;
+pub mod history;
 pub mod packer;
 pub mod remote;
+pub mod report;
 pub mod scanner;
 pub mod security;
+pub mod source;
+pub mod splitter;
+pub mod syntax;
+pub mod tokenizer;
+pub mod tree;
 pub mod utils;
@@ -1,16 +1,30 @@
+mod archive;
+mod budget;
+mod cache;
+mod changes;
+mod check;
 mod cli;
 mod comments;
+mod compress;
 mod config;
+mod credentials;
 mod formatter;
+mod history;
 mod packer;
 mod remote;
+mod report;
 mod scanner;
 mod security;
+mod source;
+mod splitter;
+mod syntax;
+mod tokenizer;
+mod tree;
 mod utils;
 
 use anyhow::{Context, Result};
 use clap::Parser;
-use cli::Cli;
+use cli::{CacheCommand, Cli, Command, SecurityCommand};
 use console::style;
 use env_logger::Env;
 use indicatif::{ProgressBar, ProgressStyle};
@@ -70,6 +84,109 @@ async fn main() -> Result<()> {
         return Ok(());
     }
 
+    if let Some(Command::Check {
+        path,
+        staged,
+        fail_on,
+    }) = &cli.command
+    {
+        main_spinner.finish_and_clear();
+
+        let mut check_config = config.merge_with_cli(&cli);
+        if let Some(fail_on) = fail_on {
+            check_config.security.fail_on = fail_on.clone();
+        }
+        let path = match path {
+            Some(path) => PathBuf::from(path),
+            None => std::env::current_dir()?,
+        };
+
+        let code = match check::run_check(&path, *staged, &check_config).and_then(|result| {
+            check::print_check_result(&result);
+            let findings: Vec<_> = result.findings.iter().map(|f| f.finding.clone()).collect();
+            report::write_security_report(&findings, &[], &check_config.security)?;
+            Ok(result.exit_code())
+        }) {
+            Ok(code) => code,
+            Err(e) => {
+                eprintln!("{} {:#}", style("Error:").bold().red(), e);
+                check::EXIT_ERROR
+            }
+        };
+        std::process::exit(code);
+    }
+
+    if let Some(Command::Security { command }) = &cli.command {
+        match command {
+            SecurityCommand::Baseline { path, output } => {
+                main_spinner.set_message("Scanning for secrets...");
+                let repository = match path {
+                    Some(path) => PathBuf::from(path),
+                    None => std::env::current_dir()?,
+                };
+                let output = output.clone().unwrap_or_else(|| {
+                    let root = security::SecretBaseline::root_for(&repository);
+                    security::SecretBaseline::path_for(&root, &config.security)
+                });
+
+                let merged_config = config.merge_with_cli(&cli);
+                let scanner = security::SecretScanner::from_config(&merged_config.security)?;
+                let source = source::open_path(&repository, &merged_config)?;
+                let files = scanner::scan_repository(source.as_ref(), &merged_config)?;
+                let findings =
+                    security::perform_security_check(source.as_ref(), &files, &scanner);
+                security::SecretBaseline::from_findings(&findings).save(&output)?;
+
+                main_spinner.finish_with_message(format!(
+                    "{} Wrote {} findings to {}",
+                    style("✓").bold().green(),
+                    findings.len(),
+                    output.display()
+                ));
+            }
+        }
+        return Ok(());
+    }
+
+    if let Some(Command::Cache { command }) = &cli.command {
+        main_spinner.finish_and_clear();
+
+        let merged_config = config.merge_with_cli(&cli);
+        let cache = cache::RepoCache::from_config(&merged_config.remote);
+        match command {
+            CacheCommand::List => {
+                let entries = cache.entries()?;
+                cache::print_entries(&entries);
+                println!(
+                    "{} cached repositories in {}",
+                    entries.len(),
+                    cache.root().display()
+                );
+            }
+            CacheCommand::Prune => {
+                let evicted = cache.prune(&merged_config.remote, None)?;
+                let size: u64 = evicted.iter().map(|entry| entry.size).sum();
+                println!(
+                    "{} Removed {} cached repositories ({})",
+                    style("✓").bold().green(),
+                    evicted.len(),
+                    utils::format_size(size)
+                );
+            }
+            CacheCommand::Clear => {
+                let removed = cache.clear()?;
+                let size: u64 = removed.iter().map(|entry| entry.size).sum();
+                println!(
+                    "{} Removed {} cached repositories ({})",
+                    style("✓").bold().green(),
+                    removed.len(),
+                    utils::format_size(size)
+                );
+            }
+        }
+        return Ok(());
+    }
+
     // Determine the target path
     let target_path = if let Some(path) = &cli.path {
         PathBuf::from(path)
@@ -86,10 +203,7 @@ async fn main() -> Result<()> {
 
     // If processing a remote repository
     if let Some(remote_url) = &cli.remote {
-        let branch = cli
-            .remote_branch
-            .as_ref()
-            .map_or_else(|| "main".to_string(), |s| s.clone());
+        let branch = cli.remote_branch.as_deref().unwrap_or("default branch");
         main_spinner.set_message(format!(
             "Processing remote repository: {} ({})",
             style(remote_url).cyan(),
@@ -97,15 +211,22 @@ async fn main() -> Result<()> {
         ));
         info!("Processing remote repository: {} ({})", remote_url, branch);
 
-        let temp_dir = remote::clone_repository(remote_url, &branch)
-            .context("Failed to clone remote repository")?;
+        let merged_config = config.merge_with_cli(&cli);
+        let checkout =
+            remote::clone_repository(remote_url, cli.remote_branch.as_deref(), &merged_config)
+                .context("Failed to clone remote repository")?;
 
         main_spinner.set_message("Processing repository...");
-        let merged_config = config.merge_with_cli(&cli);
-        let result = packer::pack_repository(&temp_dir, &merged_config).await?;
+        let result = packer::pack_checkout(&checkout, &merged_config).await?;
+
+        info!(
+            "Packed {} files ({} tokens, {})",
+            result.summary.file_count, result.summary.total_tokens, result.summary.token_encoding
+        );
 
         main_spinner.set_message("Formatting output...");
         formatter::output_result(&result, &merged_config.output)?;
+        report::output_security_report(&result, &merged_config.security)?;
     } else {
         // Process local repository
         main_spinner.set_message(format!(
@@ -118,8 +239,14 @@ async fn main() -> Result<()> {
         let merged_config = config.merge_with_cli(&cli);
         let result = packer::pack_repository(&target_path, &merged_config).await?;
 
+        info!(
+            "Packed {} files ({} tokens, {})",
+            result.summary.file_count, result.summary.total_tokens, result.summary.token_encoding
+        );
+
         main_spinner.set_message("Formatting output...");
         formatter::output_result(&result, &merged_config.output)?;
+        report::output_security_report(&result, &merged_config.security)?;
     }
 
     main_spinner.finish_with_message(format!(
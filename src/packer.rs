@@ -1,16 +1,30 @@
-use crate::comments;
-use crate::config::Config;
+use crate::budget::{self, OmissionKind, OmittedFile};
+use crate::comments::{self, CommentMode};
+use crate::compress::compress_content;
+use crate::config::{BudgetConfig, Config};
+use crate::formatter;
+use crate::history::{self, FileHistory, GitHistory};
+use crate::remote::{Checkout, RemoteSource};
 use crate::scanner::{scan_repository, FileInfo};
-use crate::security;
-use anyhow::{Context, Result};
+use crate::security::{
+    self, SecretBaseline, SecretFinding, SecretMatch, SecretScanner, SecurityMode,
+};
+use crate::source::{self, FileSource};
+use crate::tokenizer::Tokenizer;
+use crate::tree::{DirectoryTree, TreeEntry};
+use anyhow::{bail, Result};
 use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
 use log::{debug, info, warn};
 use rayon::prelude::*;
 use serde::Serialize;
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 
+/// Most times the packed output is rendered to check it fits the token budget
+const MAX_BUDGET_PASSES: usize = 3;
+
 #[derive(Debug, Serialize, Clone)]
 pub struct FileContent {
     pub relative_path: String,
@@ -18,16 +32,34 @@ pub struct FileContent {
     pub content: String,
     pub size: u64,
     pub is_binary: bool,
+    pub token_count: usize,
+    /// Unified diff of the file's changes, in git-aware mode with diffs
+    pub diff: Option<String>,
+    /// Last commit, commit count and contributors, with git history enabled
+    pub history: Option<FileHistory>,
 }
 
 #[derive(Debug, Serialize, Clone)]
 pub struct PackedRepository {
     pub files: Vec<FileContent>,
     pub summary: RepositorySummary,
+    pub directory_tree: Option<DirectoryTree>,
     pub instruction: Option<String>,
-    pub suspicious_files: Option<Vec<String>>,
+    /// Recent commits, with git history enabled
+    pub git_history: Option<GitHistory>,
+    /// Repository URL, ref and commit, when packing a remote repository
+    pub remote: Option<RemoteSource>,
+    pub security_findings: Option<Vec<SecretFinding>>,
     pub security_check_status: security::SecurityCheckStatus,  // NEW: Track security check status
+    /// Secrets in packed files on disk that aren't in the packed content, e.g.
+    /// because they were in removed comments or elided function bodies
+    pub on_disk_only_findings: Option<Vec<SecretFinding>>,
+    /// Secrets replaced with a placeholder in the packed content
+    pub redactions: Option<Vec<SecretFinding>>,
+    /// Files left out because they contain secrets
+    pub skipped_sensitive_files: Option<Vec<String>>,
     pub binary_files: Option<Vec<String>>,
+    pub omitted_files: Option<Vec<OmittedFile>>,
 }
 
 #[derive(Debug, Serialize, Clone)]
@@ -37,10 +69,38 @@ pub struct RepositorySummary {
     pub directory_count: usize,
     pub extensions: Vec<String>,
     pub binary_file_count: usize,
+    pub total_tokens: usize,
+    pub token_encoding: String,
+    pub token_budget: Option<usize>,
 }
 
+/// Pack the files at `path`: an archive, a git ref of the repository if
+/// `git.reference` is set, or the directory or file on disk
 pub async fn pack_repository(path: &Path, config: &Config) -> Result<PackedRepository> {
-    info!("Packing repository at {}", path.display());
+    let source = source::open_path(path, config)?;
+    pack_source(source.as_ref(), config).await
+}
+
+/// Pack the files of a source
+pub async fn pack_source<S: FileSource + ?Sized>(
+    source: &S,
+    config: &Config,
+) -> Result<PackedRepository> {
+    pack(source, None, config).await
+}
+
+/// Pack a checkout of a remote repository, with its URL, ref and commit
+pub async fn pack_checkout(checkout: &Checkout, config: &Config) -> Result<PackedRepository> {
+    let source = source::open_path(&checkout.pack_path(), config)?;
+    pack(source.as_ref(), Some(checkout.source().clone()), config).await
+}
+
+async fn pack<S: FileSource + ?Sized>(
+    source: &S,
+    remote: Option<RemoteSource>,
+    config: &Config,
+) -> Result<PackedRepository> {
+    info!("Packing {}", source.describe());
 
     // Create a multi-progress bar for tracking multiple processes
     let multi_progress = MultiProgress::new();
@@ -55,11 +115,35 @@ pub async fn pack_repository(path: &Path, config: &Config) -> Result<PackedRepos
     scan_progress.set_prefix("[Scan]");
     scan_progress.set_message("Scanning repository...");
 
-    let files = scan_repository(path, config)?;
+    let files = scan_repository(source, config)?;
     scan_progress.finish_with_message(format!("Found {} files", files.len()));
 
     debug!("Found {} files to process", files.len());
 
+    let tokenizer = Tokenizer::from_name(&config.token_count.encoding)?;
+    let comment_mode = CommentMode::from_name(&config.output.comment_mode)?;
+    let security_mode = SecurityMode::from_name(&config.security.mode)?;
+    let scanner = SecretScanner::from_config(&config.security)?;
+    let baseline = match source.root() {
+        _ if !config.security.enable_security_check => SecretBaseline::default(),
+        Some(root) => SecretBaseline::load_for(&SecretBaseline::root_for(root), &config.security)?,
+        // Outside a working copy only an explicit baseline is used, relative
+        // to the current directory
+        None if config.security.baseline.is_some() => {
+            SecretBaseline::load_for(Path::new(""), &config.security)?
+        }
+        None => SecretBaseline::default(),
+    };
+    let secret_check = SecretCheck {
+        scanner: &scanner,
+        mode: security_mode,
+        prefix: source
+            .root()
+            .filter(|_| config.security.enable_security_check)
+            .map(SecretBaseline::prefix_for)
+            .unwrap_or_default(),
+    };
+
     // Track binary files separately
     let binary_files: Vec<String> = files
         .iter()
@@ -84,12 +168,18 @@ pub async fn pack_repository(path: &Path, config: &Config) -> Result<PackedRepos
     let progress = Arc::new(Mutex::new(process_progress));
 
     // Process files in parallel
-    let file_contents: Vec<FileContent> = files
+    let reads: Vec<FileRead> = files
         .par_iter()
         .filter_map(|file| {
-            let result = match read_file_content(file, config) {
-                Ok(Some(content)) => Some(content),
-                Ok(none) => none,
+            let result = match read_file_content(
+                source,
+                file,
+                config,
+                &tokenizer,
+                comment_mode,
+                &secret_check,
+            ) {
+                Ok(read) => Some(read),
                 Err(e) => {
                     warn!("Error reading file {}: {}", file.path.display(), e);
                     None
@@ -99,7 +189,7 @@ pub async fn pack_repository(path: &Path, config: &Config) -> Result<PackedRepos
             // Update the progress bar
             if let Ok(pb) = progress.lock() {
                 pb.inc(1);
-                if let Some(content) = &result {
+                if let Some(FileRead::Packed(content, _)) = &result {
                     pb.set_message(format!("Processed {}", content.relative_path));
                 }
             }
@@ -108,13 +198,106 @@ pub async fn pack_repository(path: &Path, config: &Config) -> Result<PackedRepos
         })
         .collect();
 
+    let mut file_contents = Vec::new();
+    let mut findings = Vec::new();
+    let mut on_disk_only_findings = Vec::new();
+    let mut redactions = Vec::new();
+    let mut skipped_sensitive_files = Vec::new();
+    for read in reads {
+        match read {
+            FileRead::Packed(content, secrets) => {
+                file_contents.push(content);
+                redactions.extend(secrets.packed.iter().cloned());
+                findings.extend(secrets.packed);
+                on_disk_only_findings.extend(secrets.on_disk_only);
+            }
+            FileRead::Sensitive(path, secrets) => {
+                skipped_sensitive_files.push(path);
+                findings.extend(secrets.packed);
+                on_disk_only_findings.extend(secrets.on_disk_only);
+            }
+            FileRead::Skipped => {}
+        }
+    }
+
     // Finish the progress bar
     if let Ok(pb) = progress.lock() {
         pb.finish_with_message(format!("Processed {} files", file_contents.len()));
     }
 
+    // Say once which stylesheet dialects kept their comments, not once per file
+    if config.output.remove_comments && !config.compress {
+        let mut dialects: Vec<String> = file_contents
+            .iter()
+            .filter(|file| comments::is_unsupported_dialect(&file.extension))
+            .map(|file| format!(".{}", file.extension))
+            .collect();
+        dialects.sort();
+        dialects.dedup();
+        if !dialects.is_empty() {
+            info!(
+                "Comment removal is not supported for {} files, keeping their comments",
+                dialects.join(", ")
+            );
+        }
+    }
+
+    for list in [&mut findings, &mut on_disk_only_findings, &mut redactions] {
+        list.sort_by(|a, b| (&a.file, a.line, a.column).cmp(&(&b.file, b.line, b.column)));
+    }
+    skipped_sensitive_files.sort();
+
+    // Known secrets are not reported, and don't fail the run
+    let total = findings.len() + on_disk_only_findings.len();
+    findings.retain(|finding| !baseline.contains(finding));
+    on_disk_only_findings.retain(|finding| !baseline.contains(finding));
+    let known = total - findings.len() - on_disk_only_findings.len();
+    if known > 0 {
+        info!("{} known secrets hidden by the secrets baseline", known);
+    }
+
+    // In fail mode every file with secrets is held back, so any remaining
+    // finding is a new secret that would have been packed
+    if security_mode == SecurityMode::Fail && !findings.is_empty() {
+        let mut files: Vec<&str> = findings.iter().map(|f| f.file.as_str()).collect();
+        files.dedup();
+        let lines: Vec<String> = findings
+            .iter()
+            .map(|f| format!("  {}:{}:{} [{}]", f.file, f.line, f.column, f.rule_id))
+            .collect();
+        bail!(
+            "Found {} secrets in {} files, not writing any output (security mode is \"fail\"):\n{}",
+            lines.len(),
+            files.len(),
+            lines.join("\n")
+        );
+    }
+
     info!("Processed {} files", file_contents.len());
 
+    // Add each file's git activity if requested
+    let git_history = if let Some(root) = source.root().filter(|_| config.git.history) {
+        let (git_history, mut file_histories) = history::collect_history(root, &config.git)?;
+        for file in &mut file_contents {
+            file.history = file_histories.remove(&file.relative_path.replace('\\', "/"));
+        }
+        Some(git_history)
+    } else {
+        None
+    };
+
+    // Text files that were scanned but not packed, e.g. because of sensitive content
+    let processed: HashSet<&str> = file_contents
+        .iter()
+        .map(|f| f.relative_path.as_str())
+        .collect();
+    let skipped_files: Vec<String> = files
+        .iter()
+        .filter(|file| !file.is_binary)
+        .map(|file| file.relative_path.to_string_lossy().to_string())
+        .filter(|path| !processed.contains(path.as_str()))
+        .collect();
+
     // Perform security check if enabled
     let security_progress = multi_progress.add(ProgressBar::new_spinner());
     security_progress.set_style(
@@ -124,36 +307,26 @@ pub async fn pack_repository(path: &Path, config: &Config) -> Result<PackedRepos
     );
     security_progress.set_prefix("[Security]");
 
-    let (suspicious_files, security_status) = if !config.security.enable_security_check {
+    let (security_findings, security_status) = if !config.security.enable_security_check {
         security_progress.finish_with_message("Security check disabled");
         (None, security::SecurityCheckStatus::Disabled)
+    } else if !findings.is_empty() {
+        security_progress.finish_with_message(format!("Found {} secrets", findings.len()));
+        info!(
+            "Found {} secrets that should be reviewed before sharing",
+            findings.len()
+        );
+        (Some(findings), security::SecurityCheckStatus::CompletedWithFindings)
     } else {
-        security_progress.set_message("Performing security check...");
-        match security::perform_security_check(path) {
-            Ok(files) => {
-                if !files.is_empty() {
-                    security_progress
-                        .finish_with_message(format!("Found {} suspicious files", files.len()));
-                    info!(
-                        "Found {} suspicious files that may contain sensitive information",
-                        files.len()
-                    );
-                    (Some(files), security::SecurityCheckStatus::CompletedWithFindings)
-                } else {
-                    security_progress.finish_with_message("No suspicious files found");
-                    (None, security::SecurityCheckStatus::CompletedNoFindings)
-                }
-            }
-            Err(e) => {
-                security_progress.finish_with_message(format!("Security check failed: {}", e));
-                warn!("Security check failed: {}", e);
-                (None, security::SecurityCheckStatus::Failed(e.to_string()))
-            }
-        }
+        security_progress.finish_with_message("No secrets found");
+        (None, security::SecurityCheckStatus::CompletedNoFindings)
     };
-
-    // Generate a summary of the repository
-    let summary = generate_summary(&file_contents, binary_files.len());
+    if !on_disk_only_findings.is_empty() {
+        info!(
+            "{} more secrets are on disk but not in the packed content",
+            on_disk_only_findings.len()
+        );
+    }
 
     // Read custom instruction file if provided
     let instruction = match &config.output.instruction_file_path {
@@ -181,35 +354,247 @@ pub async fn pack_repository(path: &Path, config: &Config) -> Result<PackedRepos
         _none => config.instruction.clone(),
     };
 
-    Ok(PackedRepository {
-        files: file_contents,
-        summary,
+    let mut packed = PackedRepository {
+        files: Vec::new(),
+        summary: generate_summary(&[], binary_files.len(), &tokenizer),
+        directory_tree: None,
         instruction,
-        suspicious_files,  // Now properly tracks security check results
+        git_history,
+        remote,
+        security_findings,
         security_check_status: security_status,  // Fix: use correct variable name
+        on_disk_only_findings: config
+            .security
+            .enable_security_check
+            .then_some(on_disk_only_findings),
+        redactions: config.security.enable_security_check.then_some(redactions),
+        skipped_sensitive_files: config
+            .security
+            .enable_security_check
+            .then_some(skipped_sensitive_files),
         binary_files: Some(binary_files),
-    })
+        omitted_files: None,
+    };
+
+    // Fit the files into the token budget if one is set
+    let max_tokens = match config.budget.max_tokens {
+        Some(max_tokens) => max_tokens,
+        None => {
+            set_files(
+                &mut packed,
+                file_contents,
+                Vec::new(),
+                &skipped_files,
+                &tokenizer,
+                config,
+            );
+            return Ok(packed);
+        }
+    };
+    let modified_times: HashMap<String, std::time::SystemTime> = files
+        .iter()
+        .filter_map(|file| {
+            file.modified
+                .map(|modified| (file.relative_path.to_string_lossy().to_string(), modified))
+        })
+        .collect();
+
+    // Measure what the format adds around file contents once: render every
+    // file with an empty body, both as a packed file and as an omission, so the
+    // preamble, headers, tree lines and omission notes are all counted
+    let framing_files: Vec<FileContent> = file_contents
+        .iter()
+        .map(|file| FileContent {
+            relative_path: file.relative_path.clone(),
+            extension: file.extension.clone(),
+            content: String::new(),
+            size: file.size,
+            is_binary: file.is_binary,
+            token_count: file.token_count,
+            diff: file.diff.clone(),
+            history: file.history.clone(),
+        })
+        .collect();
+    let framing_omitted: Vec<OmittedFile> = file_contents
+        .iter()
+        .map(|file| OmittedFile {
+            relative_path: file.relative_path.clone(),
+            kind: OmissionKind::Truncated,
+            original_tokens: file.token_count,
+            kept_tokens: file.token_count,
+        })
+        .collect();
+    set_files(
+        &mut packed,
+        framing_files,
+        framing_omitted,
+        &skipped_files,
+        &tokenizer,
+        config,
+    );
+    let framing_tokens =
+        tokenizer.count(&formatter::format_output(&packed, &config.output.format)?);
+
+    // The budget charges its own header estimate per file, so hand that back
+    let header_tokens: usize = file_contents
+        .iter()
+        .map(|file| budget::header_cost(file, &tokenizer))
+        .sum();
+    let mut file_budget = (max_tokens + header_tokens).saturating_sub(framing_tokens);
+    debug!(
+        "{} tokens of the budget are left for files after {} tokens of framing",
+        file_budget, framing_tokens
+    );
+
+    // Headers of dropped files are refunded above even though they aren't
+    // rendered, so check the output and tighten the budget a few times at most
+    for pass in 1..=MAX_BUDGET_PASSES {
+        let budget_config = BudgetConfig {
+            max_tokens: Some(file_budget),
+            ..config.budget.clone()
+        };
+        let (kept, omitted) = budget::apply_token_budget(
+            file_contents.clone(),
+            &modified_times,
+            &budget_config,
+            config.compress,
+            &tokenizer,
+        )?;
+        set_files(
+            &mut packed,
+            kept,
+            omitted,
+            &skipped_files,
+            &tokenizer,
+            config,
+        );
+
+        let total = tokenizer.count(&formatter::format_output(&packed, &config.output.format)?);
+        if total <= max_tokens {
+            break;
+        }
+        if file_budget == 0 || pass == MAX_BUDGET_PASSES {
+            warn!(
+                "The output takes {} tokens, over the budget of {}",
+                total, max_tokens
+            );
+            break;
+        }
+        file_budget = file_budget.saturating_sub(total - max_tokens);
+    }
+
+    Ok(packed)
 }
 
-fn read_file_content(file: &FileInfo, config: &Config) -> Result<Option<FileContent>> {
+/// Put the packed files into the result, with the summary, directory tree and
+/// omitted files that go with them
+fn set_files(
+    packed: &mut PackedRepository,
+    files: Vec<FileContent>,
+    omitted_files: Vec<OmittedFile>,
+    skipped_files: &[String],
+    tokenizer: &Tokenizer,
+    config: &Config,
+) {
+    let binary_files = packed.binary_files.as_deref().unwrap_or_default();
+    packed.summary = generate_summary(&files, binary_files.len(), tokenizer);
+    packed.summary.token_budget = config.budget.max_tokens;
+    packed.directory_tree = if config.output.tree.enabled {
+        Some(build_directory_tree(
+            &files,
+            binary_files,
+            skipped_files,
+            &omitted_files,
+            config,
+        ))
+    } else {
+        None
+    };
+    packed.files = files;
+    packed.omitted_files = config.budget.max_tokens.map(|_| omitted_files);
+}
+
+/// Build the directory tree of packed files, optionally with excluded files marked
+fn build_directory_tree(
+    files: &[FileContent],
+    binary_files: &[String],
+    skipped_files: &[String],
+    omitted_files: &[OmittedFile],
+    config: &Config,
+) -> DirectoryTree {
+    let omissions: HashMap<&str, OmissionKind> = omitted_files
+        .iter()
+        .map(|f| (f.relative_path.as_str(), f.kind))
+        .collect();
+
+    let mut entries: Vec<TreeEntry> = files
+        .iter()
+        .map(|file| match omissions.get(file.relative_path.as_str()) {
+            Some(kind) => TreeEntry::marked(&file.relative_path, kind.label()),
+            None => TreeEntry::new(&file.relative_path),
+        })
+        .collect();
+
+    if config.output.tree.include_excluded {
+        entries.extend(binary_files.iter().map(|path| TreeEntry::marked(path, "binary")));
+        entries.extend(skipped_files.iter().map(|path| TreeEntry::marked(path, "skipped")));
+        entries.extend(
+            omitted_files
+                .iter()
+                .filter(|f| f.kind == OmissionKind::Dropped)
+                .map(|f| TreeEntry::marked(&f.relative_path, f.kind.label())),
+        );
+    }
+
+    DirectoryTree::new(entries, config.output.tree.max_depth)
+}
+
+/// How the secrets of the packed files are checked
+struct SecretCheck<'a> {
+    scanner: &'a SecretScanner,
+    mode: SecurityMode,
+    /// Where the packed directory is in its working copy. Findings are
+    /// reported relative to the working copy, like in the secrets baseline.
+    prefix: PathBuf,
+}
+
+/// Secrets found in one file
+#[derive(Default)]
+struct FileSecrets {
+    /// Secrets in the content as it would be packed, located in the file on disk
+    packed: Vec<SecretFinding>,
+    /// Secrets that only exist on disk, e.g. in comments that were removed
+    on_disk_only: Vec<SecretFinding>,
+}
+
+/// Outcome of reading a single file
+enum FileRead {
+    /// The file is packed; its packed secrets were redacted
+    Packed(FileContent, FileSecrets),
+    /// The file contains secrets and is left out (skip and fail modes)
+    Sensitive(String, FileSecrets),
+    /// The file is not packed for another reason, such as being binary
+    Skipped,
+}
+
+fn read_file_content<S: FileSource + ?Sized>(
+    source: &S,
+    file: &FileInfo,
+    config: &Config,
+    tokenizer: &Tokenizer,
+    comment_mode: CommentMode,
+    secret_check: &SecretCheck,
+) -> Result<FileRead> {
     // Don't try to read binary files unless they were explicitly included
     if file.is_binary {
         debug!("Skipping binary file: {}", file.path.display());
-        return Ok(None);
+        return Ok(FileRead::Skipped);
     }
 
+    let relative_path = file.relative_path.to_string_lossy().replace('\\', "/");
+
     // Read the file content
-    let content = fs::read_to_string(&file.path)
-        .context(format!("Failed to read file: {}", file.path.display()))?;
-
-    // Check for sensitive content if security check is enabled
-    if config.security.enable_security_check && security::check_sensitive_content(&content) {
-        warn!(
-            "Skipping file with sensitive content: {}",
-            file.path.display()
-        );
-        return Ok(None);
-    }
+    let content = source.read_to_string(&file.relative_path)?;
 
     // Get the file extension
     let extension = file
@@ -220,123 +605,157 @@ fn read_file_content(file: &FileInfo, config: &Config) -> Result<Option<FileCont
         .to_lowercase();
 
     // Process the content (compression or comment removal)
-    let processed_content = if config.compress {
+    let mut processed_content = if config.compress {
         compress_content(&content, &extension)
     } else if config.output.remove_comments && comments::is_comment_removal_supported(&extension) {
-        comments::remove_comments(&content, &extension)
+        comments::remove_comments(&content, &extension, comment_mode)
     } else {
-        content
+        if config.output.remove_comments && comments::is_unsupported_dialect(&extension) {
+            debug!(
+                "Comment removal is not supported for .{} files, keeping the comments in {}",
+                extension, relative_path
+            );
+        }
+        content.clone()
     };
 
-    Ok(Some(FileContent {
-        relative_path: file.relative_path.to_string_lossy().to_string(),
-        extension,
-        content: processed_content,
-        size: file.size,
-        is_binary: file.is_binary,
-    }))
-}
-
-fn compress_content(content: &str, _extension: &str) -> String {
-    // This is a basic implementation for code compression
-    // In a full implementation, you would use a proper parser for each language
-    // and extract only the structural elements like function signatures, class declarations, etc.
-
-    // For now, let's just do some basic line-based filtering
-    let lines: Vec<&str> = content.lines().collect();
-
-    // Skip compression for small files
-    if lines.len() < 10 {
-        return content.to_string();
-    }
-
-    // Basic compression by taking first line of blocks and removing empty lines
-    let mut compressed = Vec::new();
-    let mut in_comment_block = false;
-    let mut consecutive_empty_lines = 0;
-
-    for line in lines {
-        let trimmed = line.trim();
-
-        // Handle comment blocks
-        if trimmed.starts_with("/*") || trimmed.starts_with("/**") {
-            in_comment_block = true;
-            compressed.push(line);
-            continue;
+    // Check the content that would be packed for secrets
+    let mut diff = file.diff.clone();
+    let mut secrets = FileSecrets::default();
+    if config.security.enable_security_check {
+        let scanner = secret_check.scanner;
+        let secret_path = security::finding_path(&secret_check.prefix, &file.relative_path);
+        let mut packed_secrets = scanner.scan_file(&secret_path, &processed_content);
+        // Comment removal and compression can drop a `remix:allow-secret`
+        // comment, so secrets allowed on disk stay allowed
+        if processed_content != content {
+            let suppressed = scanner.suppressed_fingerprints(&secret_path, &content);
+            packed_secrets.retain(|secret| {
+                !suppressed.contains(&secret.fingerprint(&secret_path, &processed_content))
+            });
         }
+        secrets = locate_secrets(
+            scanner,
+            &secret_path,
+            &content,
+            &processed_content,
+            &packed_secrets,
+        );
 
-        if in_comment_block {
-            if trimmed.ends_with("*/") {
-                in_comment_block = false;
-                compressed.push(line);
+        // The diff is packed too, and its removed lines can hold secrets that
+        // are no longer in the file. Those are reported at their diff line.
+        let diff_secrets = match &diff {
+            Some(diff) => {
+                let diff_secrets = scanner.scan_file(&secret_path, diff);
+                for secret in &diff_secrets {
+                    let finding = secret.to_finding(&secret_path, diff);
+                    if let Some(index) = secrets
+                        .on_disk_only
+                        .iter()
+                        .position(|known| known.fingerprint == finding.fingerprint)
+                    {
+                        let finding = secrets.on_disk_only.remove(index);
+                        secrets.packed.push(finding);
+                    } else if !secrets
+                        .packed
+                        .iter()
+                        .any(|known| known.fingerprint == finding.fingerprint)
+                    {
+                        secrets.packed.push(finding);
+                    }
+                }
+                diff_secrets
             }
-            continue;
-        }
-
-        // Skip empty lines after the first one
-        if trimmed.is_empty() {
-            consecutive_empty_lines += 1;
-            if consecutive_empty_lines <= 1 {
-                compressed.push(line);
+            None => Vec::new(),
+        };
+
+        if !packed_secrets.is_empty() || !diff_secrets.is_empty() {
+            if secret_check.mode != SecurityMode::Redact {
+                warn!(
+                    "Skipping file with {} secret(s): {}",
+                    secrets.packed.len(),
+                    relative_path
+                );
+                return Ok(FileRead::Sensitive(relative_path, secrets));
             }
-            continue;
-        }
-        consecutive_empty_lines = 0;
-
-        // Skip comment lines
-        if trimmed.starts_with("//") {
-            continue;
-        }
 
-        // Always include certain structural elements
-        if trimmed.starts_with("fn ")
-            || trimmed.starts_with("pub fn ")
-            || trimmed.starts_with("class ")
-            || trimmed.starts_with("interface ")
-            || trimmed.starts_with("trait ")
-            || trimmed.starts_with("struct ")
-            || trimmed.starts_with("enum ")
-            || trimmed.starts_with("type ")
-            || trimmed.starts_with("pub struct ")
-            || trimmed.starts_with("pub enum ")
-            || trimmed.starts_with("export ")
-            || trimmed.starts_with("import ")
-            || trimmed.starts_with("use ")
-            || trimmed.starts_with("const ")
-            || trimmed.starts_with("let ")
-            || trimmed.starts_with("var ")
-            || trimmed.starts_with("function ")
-        {
-            compressed.push(line);
-            continue;
+            debug!(
+                "Redacting {} secret(s) in {}",
+                secrets.packed.len(),
+                relative_path
+            );
+            processed_content = security::redact_secrets(&processed_content, &packed_secrets);
+            diff = diff.map(|diff| security::redact_secrets(&diff, &diff_secrets));
         }
+    }
 
-        // Include opening and closing braces
-        if trimmed == "{" || trimmed == "}" {
-            compressed.push(line);
-            continue;
-        }
+    let token_count = tokenizer.count(&processed_content);
+
+    Ok(FileRead::Packed(
+        FileContent {
+            relative_path: file.relative_path.to_string_lossy().to_string(),
+            extension,
+            content: processed_content,
+            size: file.size,
+            is_binary: file.is_binary,
+            token_count,
+            diff,
+            history: None,
+        },
+        secrets,
+    ))
+}
 
-        // For implementation blocks, only include the first line
-        if trimmed.contains("impl") || trimmed.contains(" for ") {
-            compressed.push(line);
-            continue;
-        }
+/// Sort a file's secrets into those in the packed content and those only on disk.
+///
+/// Secrets are matched up by fingerprint, which doesn't depend on position, so
+/// packed secrets are reported where they are in the file on disk.
+fn locate_secrets(
+    scanner: &SecretScanner,
+    file: &str,
+    on_disk: &str,
+    packed: &str,
+    packed_secrets: &[SecretMatch],
+) -> FileSecrets {
+    if on_disk == packed {
+        return FileSecrets {
+            packed: packed_secrets
+                .iter()
+                .map(|secret| secret.to_finding(file, packed))
+                .collect(),
+            on_disk_only: Vec::new(),
+        };
+    }
 
-        // Skip most implementation details
-        if !trimmed.contains("(") && !trimmed.contains(")") {
-            continue;
-        }
+    let mut on_disk_only = scanner.findings(file, on_disk);
+    let packed = packed_secrets
+        .iter()
+        .map(|secret| {
+            let fingerprint = secret.fingerprint(file, packed);
+            match on_disk_only
+                .iter()
+                .position(|finding| finding.fingerprint == fingerprint)
+            {
+                Some(index) => on_disk_only.remove(index),
+                None => secret.to_finding(file, packed),
+            }
+        })
+        .collect();
 
-        compressed.push(line);
+    FileSecrets {
+        packed,
+        on_disk_only,
     }
-
-    compressed.join("\n")
 }
 
-fn generate_summary(files: &[FileContent], binary_file_count: usize) -> RepositorySummary {
+fn generate_summary(
+    files: &[FileContent],
+    binary_file_count: usize,
+    tokenizer: &Tokenizer,
+) -> RepositorySummary {
     let file_count = files.len();
     let total_size: u64 = files.iter().map(|f| f.size).sum();
+    let total_tokens: usize = files.iter().map(|f| f.token_count).sum();
 
     // Count directories (unique parent paths)
     let mut directories = std::collections::HashSet::new();
@@ -368,5 +787,8 @@ fn generate_summary(files: &[FileContent], binary_file_count: usize) -> Reposito
         directory_count,
         extensions,
         binary_file_count,
+        total_tokens,
+        token_encoding: tokenizer.encoding().name().to_string(),
+        token_budget: None,
     }
 }
@@ -1,20 +1,83 @@
-use anyhow::{Context, Result};
-use git2::{BranchType, Oid, Repository};
+use crate::cache::{strip_credentials, EntryLock, RepoCache};
+use crate::config::Config;
+use crate::credentials::{remote_callbacks, Credentials};
+use crate::scanner::select_tree_blobs;
+use anyhow::{anyhow, Context, Result};
+use git2::build::CheckoutBuilder;
+use git2::{AutotagOption, Direction, FetchOptions, ObjectType, Oid, Remote, Repository};
 use indicatif::{ProgressBar, ProgressStyle};
 use log::{debug, info};
-use regex::Regex;
-use std::path::PathBuf;
+use serde::Serialize;
+use std::cell::RefCell;
+use std::fs;
+use std::path::{Path, PathBuf};
 use tempfile::TempDir;
 
+/// A checkout of a remote repository in a temporary directory, removed when
+/// it is dropped
+#[derive(Debug)]
+pub struct Checkout {
+    dir: TempDir,
+    /// Directory to pack, relative to the checkout
+    pack_dir: PathBuf,
+    source: RemoteSource,
+    /// Keeps the cached clone whose objects the checkout reads from being evicted
+    _cache_lock: Option<EntryLock>,
+}
+
+impl Checkout {
+    /// Root of the checkout
+    pub fn path(&self) -> &Path {
+        self.dir.path()
+    }
+
+    /// Directory to pack: the subpath named by the URL, or the root
+    pub fn pack_path(&self) -> PathBuf {
+        self.path().join(&self.pack_dir)
+    }
+
+    /// Where the checkout came from
+    pub fn source(&self) -> &RemoteSource {
+        &self.source
+    }
+}
+
+/// The remote repository and commit a pack was made from
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+pub struct RemoteSource {
+    /// Repository URL, without credentials
+    pub url: String,
+    /// Branch, tag or commit that was requested, or the default branch
+    pub reference: String,
+    /// Full id of the commit that was checked out
+    pub commit: String,
+    /// Directory or file of the repository that was packed
+    pub subpath: Option<String>,
+}
+
 /// Clone a remote repository to a temporary directory.
 ///
-/// Supports various Git URL formats:
-/// - Full URLs: https://github.com/username/repo
-/// - GitHub shorthand: username/repo
-/// - Branch URLs: https://github.com/username/repo/tree/branch
-/// - Commit URLs: https://github.com/username/repo/commit/hash
-pub fn clone_repository(url: &str, branch: &str) -> Result<PathBuf> {
-    info!("Cloning repository: {}", url);
+/// See [`RemoteSpec`] for the URL formats. A ref in the URL takes precedence
+/// over `branch`, and without either the remote's default branch is used. A
+/// path in the URL limits the checkout to that directory or file.
+///
+/// Only the requested branch, tag or commit is fetched, one commit deep unless
+/// `remote.depth` or the git options need more history, and files that would
+/// not be packed are not checked out. Objects are fetched into the clone cache
+/// unless it is disabled, so later runs only download new commits.
+pub fn clone_repository(url: &str, branch: Option<&str>, config: &Config) -> Result<Checkout> {
+    info!("Cloning repository: {}", strip_credentials(url));
+
+    // Parse the URL to extract repository information
+    let spec = RemoteSpec::parse(url)?;
+    clone_spec(spec, branch, config)
+}
+
+/// Clone the repository of a parsed URL, like [`clone_repository`]
+pub fn clone_spec(mut spec: RemoteSpec, branch: Option<&str>, config: &Config) -> Result<Checkout> {
+    if spec.reference.is_none() {
+        spec.reference = branch.map(str::to_string);
+    }
 
     // Create a progress bar for the clone operation
     let progress = ProgressBar::new_spinner();
@@ -24,10 +87,7 @@ pub fn clone_repository(url: &str, branch: &str) -> Result<PathBuf> {
             .unwrap(),
     );
     progress.set_prefix("[Clone]");
-    progress.set_message(format!("Parsing URL: {}", url));
-
-    // Parse the URL to extract repository information
-    let (repo_url, target_ref) = parse_git_url(url, branch)?;
+    let repo_url = strip_credentials(&spec.url);
 
     // Create temporary directory
     progress.set_message("Creating temporary directory...");
@@ -57,137 +117,508 @@ pub fn clone_repository(url: &str, branch: &str) -> Result<PathBuf> {
         }
     });
 
-    // Perform the repository cloning
-    let clone_result = match Repository::clone(&repo_url, &temp_path) {
-        Ok(repo) => {
-            // Successfully cloned, now check out specific branch/commit if requested
-            if !target_ref.is_empty() && target_ref != "main" && target_ref != "master" {
-                checkout_target(&repo, &target_ref)?;
-            }
-            Ok(())
-        }
-        Err(e) => Err(anyhow::anyhow!(
-            "Failed to clone repository: {}: {}",
-            repo_url,
-            e
-        )),
-    };
+    // Fetch only the requested snapshot
+    let clone_result = fetch_snapshot(&mut spec, &temp_path, config)
+        .with_context(|| format!("Failed to clone repository: {}", repo_url));
 
     // Stop the progress spinner thread
     clone_ticker.thread().unpark();
 
-    // Check if clone was successful
-    match clone_result {
-        Ok(_) => {
-            // Important: we need to leak the TempDir so it doesn't get deleted
-            // when this function returns. It will be cleaned up when the program exits.
-            std::mem::forget(temp_dir);
+    // The checkout is removed when the returned Checkout is dropped
+    let (pack_dir, commit, cache_lock) = clone_result?;
+    info!("Repository cloned successfully to {}", temp_path.display());
+    let source = RemoteSource {
+        url: strip_credentials(&spec.url),
+        reference: spec.reference.unwrap_or_else(|| commit.to_string()),
+        commit: commit.to_string(),
+        subpath: spec.subpath,
+    };
+    info!("Checked out {} at {}", source.reference, source.commit);
+    Ok(Checkout {
+        dir: temp_dir,
+        pack_dir,
+        source,
+        _cache_lock: cache_lock,
+    })
+}
+
+/// What to fetch for the requested branch, tag or commit
+pub enum FetchTarget {
+    /// A branch, tag or `HEAD` advertised by the remote
+    Ref(String),
+    /// A full commit id, fetched directly
+    Commit(Oid),
+    /// An abbreviated commit id, only found by fetching every branch
+    Revision(String),
+}
 
-            info!("Repository cloned successfully to {}", temp_path.display());
-            Ok(temp_path)
+impl FetchTarget {
+    fn refspecs(&self) -> Vec<String> {
+        match self {
+            Self::Ref(name) => vec![format!("+{}:{}", name, self.local_revision())],
+            Self::Commit(oid) => vec![format!("+{}:{}", oid, self.local_revision())],
+            Self::Revision(_) => vec![
+                "+refs/heads/*:refs/remotes/origin/*".to_string(),
+                "+refs/tags/*:refs/tags/*".to_string(),
+            ],
         }
-        Err(e) => {
-            // Clone error - propagate it
-            Err(e)
+    }
+
+    /// Revision of the fetched target in the local repository. Every target
+    /// gets its own ref, so a cached clone can hold several of them.
+    fn local_revision(&self) -> String {
+        match self {
+            Self::Ref(name) => format!("refs/remix/{}", name.trim_start_matches("refs/")),
+            Self::Commit(oid) => format!("refs/remix/commits/{}", oid),
+            Self::Revision(revision) => revision.clone(),
         }
     }
 }
 
-/// Helper function to check out a specific branch or commit
-fn checkout_target(repo: &Repository, target_ref: &str) -> Result<()> {
-    // Try to parse as commit hash
-    if let Ok(oid) = Oid::from_str(target_ref) {
-        debug!("Checking out commit: {}", target_ref);
-        let commit = repo
-            .find_commit(oid)
-            .context(format!("Failed to find commit: {}", target_ref))?;
+/// libgit2's depth for fetching the rest of a shallow repository's history
+const DEPTH_UNSHALLOW: i32 = i32::MAX;
 
-        // Detach HEAD to the specified commit
-        repo.set_head_detached(commit.id())
-            .context(format!("Failed to set HEAD to commit: {}", target_ref))?;
+/// Fetch the requested ref into a new repository at `path` and check it out.
+/// Returns the directory to pack, relative to `path`.
+///
+/// Only the requested branch, tag or commit is fetched, `depth` commits deep,
+/// and only the files that the ignore and include config would pack, under
+/// the subpath of the URL if any, are written to disk. With the cache
+/// enabled, the objects are fetched into the cached clone of the URL and
+/// shared with the new repository. The returned lock keeps other processes
+/// from evicting the cached clone while the checkout uses it.
+fn fetch_snapshot(
+    spec: &mut RemoteSpec,
+    path: &Path,
+    config: &Config,
+) -> Result<(PathBuf, Oid, Option<EntryLock>)> {
+    let repo = Repository::init(path).context("Failed to create the repository")?;
+    let url = spec.url.clone();
 
-        // Reset the working directory to match the commit
-        let obj = repo
-            .find_object(commit.id(), None)
-            .context(format!("Failed to find object for commit: {}", target_ref))?;
+    let mut cache_lock = None;
+    let commit_id = if config.remote.cache {
+        let cache = RepoCache::from_config(&config.remote);
+        cache_lock = Some(cache.lock_use(&url)?);
+        // Another process fetching the same URL would race for its refs
+        let fetch_lock = cache.lock_fetch(&url)?;
+        let cached = cache.open(&url)?;
+        let commit_id = fetch_commit(&cached, spec, config)?;
+        share_objects(&repo, &cached)?;
+        drop(fetch_lock);
 
-        repo.reset(&obj, git2::ResetType::Hard, None)
-            .context(format!("Failed to reset to commit: {}", target_ref))?;
+        let evicted = cache.prune(&config.remote, Some(&cache.entry_path(&url)))?;
+        if !evicted.is_empty() {
+            info!("Evicted {} cached clones", evicted.len());
+        }
+        commit_id
     } else {
-        // Try to check out the branch
-        debug!("Checking out branch: {}", target_ref);
+        fetch_commit(&repo, spec, config)?
+    };
 
-        // First try to find a local branch with that name
-        if let Ok(branch) = repo.find_branch(target_ref, BranchType::Local) {
-            repo.set_head(branch.get().name().unwrap_or(""))
-                .context(format!("Failed to set HEAD to branch: {}", target_ref))?;
-        } else {
-            // Try to find a remote branch
-            let remote_branch_name = format!("origin/{}", target_ref);
-            if let Ok(branch) = repo.find_branch(&remote_branch_name, BranchType::Remote) {
-                // Create a local branch tracking the remote branch
-                let _branch_ref = branch.get().name().unwrap_or("");
-                repo.branch(
-                    target_ref,
-                    &repo.find_commit(branch.get().peel_to_commit()?.id())?,
-                    false,
-                )
-                .context(format!(
-                    "Failed to create local branch from remote: {}",
-                    target_ref
-                ))?;
-
-                // Set HEAD to the new local branch
-                repo.set_head(&format!("refs/heads/{}", target_ref))
-                    .context(format!("Failed to set HEAD to branch: {}", target_ref))?;
-            } else {
-                // No branch found - report error
-                return Err(anyhow::anyhow!(
-                    "Could not find branch {} in repository",
-                    target_ref
-                ));
-            }
+    // Reopen the repository so that it sees the shared objects
+    let repo = Repository::open(path).context("Failed to open the repository")?;
+    let commit = repo.find_commit(commit_id)?;
+    debug!("Checking out commit {}", commit.id());
+
+    // Leave out files that would not be packed anyway
+    let tree = commit.tree().context("Failed to read the commit tree")?;
+    let mut paths = select_tree_blobs(&repo, &tree, config)?;
+    let mut pack_dir = PathBuf::new();
+    if let Some(subpath) = &spec.subpath {
+        let subpath = Path::new(subpath);
+        let entry = tree.get_path(subpath).with_context(|| {
+            format!(
+                "Path {} does not exist in {}",
+                subpath.display(),
+                spec.reference.as_deref().unwrap_or("HEAD")
+            )
+        })?;
+        paths.retain(|(path, _)| path.starts_with(subpath));
+        // A single file is packed from its directory, which holds nothing else
+        pack_dir = match entry.kind() {
+            Some(ObjectType::Blob) => subpath.parent().unwrap_or(subpath).to_path_buf(),
+            _ => subpath.to_path_buf(),
+        };
+    }
+    debug!("Checking out {} files", paths.len());
+    if !paths.is_empty() {
+        let mut checkout = CheckoutBuilder::new();
+        checkout.force().disable_pathspec_match(true);
+        for (path, _) in &paths {
+            checkout.path(path);
+        }
+        repo.checkout_tree(commit.as_object(), Some(&mut checkout))
+            .context("Failed to check out files")?;
+    }
+    repo.set_head_detached(commit.id())
+        .context("Failed to set HEAD")?;
+    fs::create_dir_all(path.join(&pack_dir)).context("Failed to create the directory to pack")?;
 
-            // Reset working directory to match the branch head
-            let obj = repo
-                .revparse_single(&format!("refs/heads/{}", target_ref))
-                .context(format!(
-                    "Failed to find reference for branch: {}",
-                    target_ref
-                ))?;
+    Ok((pack_dir, commit.id(), cache_lock))
+}
 
-            repo.reset(&obj, git2::ResetType::Hard, None)
-                .context(format!("Failed to reset to branch: {}", target_ref))?;
+/// Fetch the ref of `spec`, or the remote's default branch if it names none,
+/// into `repo` and return its commit
+fn fetch_commit(repo: &Repository, spec: &mut RemoteSpec, config: &Config) -> Result<Oid> {
+    let url = spec.url.as_str();
+    let credentials = RefCell::new(Credentials::for_url(url, &config.remote));
+    let mut remote = repo.remote_anonymous(url)?;
+    let target = resolve_target(&mut remote, spec, &credentials)?;
+    let url = spec.url.as_str();
+    let target_ref = spec.reference.as_deref().unwrap_or("HEAD");
+
+    let mut options = FetchOptions::new();
+    options
+        .remote_callbacks(remote_callbacks(&credentials))
+        .download_tags(AutotagOption::None);
+    match clone_depth(url, &target, config) {
+        Some(depth) => {
+            debug!("Fetching {} with depth {}", target_ref, depth);
+            options.depth(depth);
+        }
+        // A cached clone may have been fetched shallow before
+        None if repo.is_shallow() => {
+            debug!("Fetching the rest of the history for {}", target_ref);
+            options.depth(DEPTH_UNSHALLOW);
         }
+        None => debug!("Fetching {} with the full history", target_ref),
+    }
+    remote
+        .fetch(&target.refspecs(), Some(&mut options), None)
+        .map_err(|e| credentials.borrow().explain(e))
+        .context("Failed to fetch from the remote")?;
+
+    let commit = repo
+        .revparse_single(&target.local_revision())
+        .and_then(|object| object.peel_to_commit())
+        .with_context(|| format!("Could not find {} in the repository", target_ref))?;
+    Ok(commit.id())
+}
+
+/// Let `repo` read the objects of a cached clone instead of copying them
+fn share_objects(repo: &Repository, cached: &Repository) -> Result<()> {
+    let info_dir = repo.path().join("objects").join("info");
+    fs::create_dir_all(&info_dir).context("Failed to create the objects directory")?;
+    let objects = cached.path().join("objects");
+    fs::write(
+        info_dir.join("alternates"),
+        format!("{}\n", objects.display()),
+    )
+    .context("Failed to link the cached objects")?;
+
+    // Commits whose parents were not fetched
+    let shallow = cached.path().join("shallow");
+    if shallow.exists() {
+        fs::copy(&shallow, repo.path().join("shallow"))
+            .context("Failed to copy the shallow commits of the cached clone")?;
     }
 
     Ok(())
 }
 
-/// Parse a Git URL into its component parts and extract the repository URL and target reference.
-fn parse_git_url(url: &str, branch: &str) -> Result<(String, String)> {
-    // Handle GitHub shorthand format: username/repo
-    if !url.contains("://") && url.contains('/') {
-        let repo_url = format!("https://github.com/{}", url);
-        return Ok((repo_url, branch.to_string()));
+/// Find what to fetch for the ref of `spec` from the refs the remote
+/// advertises. Without a ref, the branch the remote's HEAD points to is
+/// fetched and becomes the ref of `spec`.
+fn resolve_target(
+    remote: &mut Remote,
+    spec: &mut RemoteSpec,
+    credentials: &RefCell<Credentials>,
+) -> Result<FetchTarget> {
+    if !spec.split_ref {
+        if let Some(oid) = spec.reference.as_deref().and_then(full_commit_id) {
+            return Ok(FetchTarget::Commit(oid));
+        }
+    }
+
+    let connection = remote
+        .connect_auth(Direction::Fetch, Some(remote_callbacks(credentials)), None)
+        .map_err(|e| credentials.borrow().explain(e))
+        .context("Failed to connect to the remote")?;
+    let advertised: Vec<String> = connection
+        .list()?
+        .iter()
+        .map(|head| head.name().to_string())
+        .collect();
+    let default_branch = connection
+        .default_branch()
+        .ok()
+        .and_then(|name| name.as_str().map(str::to_string));
+    drop(connection);
+
+    spec.resolve_ref(&advertised);
+    let target_ref = match &spec.reference {
+        Some(reference) => reference.as_str(),
+        None => {
+            let name = match default_branch {
+                Some(name) => name,
+                // Remotes that don't say which branch HEAD points to
+                None if advertised.iter().any(|name| name == "HEAD") => "HEAD".to_string(),
+                None => {
+                    return Err(anyhow!(
+                        "The remote has no default branch; choose one with --remote-branch"
+                    ))
+                }
+            };
+            debug!("Default branch of the remote is {}", name);
+            spec.reference = Some(short_ref_name(&name).to_string());
+            return Ok(FetchTarget::Ref(name));
+        }
+    };
+    if let Some(oid) = full_commit_id(target_ref) {
+        return Ok(FetchTarget::Commit(oid));
+    }
+
+    for name in [
+        target_ref.to_string(),
+        format!("refs/heads/{}", target_ref),
+        format!("refs/tags/{}", target_ref),
+    ] {
+        if advertised.contains(&name) {
+            return Ok(FetchTarget::Ref(name));
+        }
+    }
+
+    if target_ref.len() >= 4 && target_ref.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Ok(FetchTarget::Revision(target_ref.to_string()));
+    }
+
+    Err(anyhow!(
+        "Could not find branch or tag {} in repository",
+        target_ref
+    ))
+}
+
+/// A branch or tag name without its `refs/heads/` or `refs/tags/` prefix
+fn short_ref_name(name: &str) -> &str {
+    name.strip_prefix("refs/heads/")
+        .or_else(|| name.strip_prefix("refs/tags/"))
+        .unwrap_or(name)
+}
+
+/// The commit id `target_ref` names, if it is a full one
+fn full_commit_id(target_ref: &str) -> Option<Oid> {
+    if target_ref.len() != 40 {
+        return None;
+    }
+    Oid::from_str(target_ref).ok()
+}
+
+/// Commits to fetch, or `None` for the full history.
+///
+/// Changed files and git history need more than one snapshot, and an
+/// abbreviated commit id can be anywhere in the history. libgit2 can't fetch
+/// shallow from a local path or `file://` URL, so those always get the full
+/// history, whatever the configured depth.
+pub fn clone_depth(url: &str, target: &FetchTarget, config: &Config) -> Option<i32> {
+    let needs_history = config.git.history || config.git.since.is_some();
+    if config.remote.depth == 0
+        || needs_history
+        || matches!(target, FetchTarget::Revision(_))
+        || is_local(url)
+    {
+        return None;
+    }
+    Some(config.remote.depth.min(i32::MAX as u32) as i32)
+}
+
+/// Whether a URL points to a repository on this machine
+fn is_local(url: &str) -> bool {
+    url.starts_with("file://") || Path::new(url).exists()
+}
+
+/// URL path segments that start a route to a ref, commit or pull request
+/// after `owner/repo`, on hosts without GitLab's `/-/` separator
+const ROUTES: [&str; 8] = [
+    "tree",
+    "blob",
+    "src",
+    "commit",
+    "commits",
+    "branch",
+    "pull",
+    "pull-requests",
+];
+
+/// A remote repository given on the command line: where to fetch it from,
+/// and the ref and directory to pack if the URL names them.
+///
+/// Understands:
+/// - Full URLs: `https://github.com/owner/repo(.git)`
+/// - Shorthand: `owner/repo` (GitHub) or `gitlab.com/group/project`
+/// - SSH URLs: `git@host:owner/repo.git` and `ssh://git@host/owner/repo.git`
+/// - GitHub: `/tree/<ref>/<path>`, `/blob/<ref>/<path>`, `/commit/<sha>`, `/pull/<n>`
+/// - GitLab, with subgroups: `/-/tree/<ref>/<path>`, `/-/commit/<sha>`, `/-/merge_requests/<n>`
+/// - Bitbucket: `/src/<ref>/<path>`, `/commits/<sha>`, `/branch/<ref>`
+/// - `file://` URLs and local paths, used as they are
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemoteSpec {
+    /// URL to fetch from, without the route to a ref or path
+    pub url: String,
+    /// Lowercase host name, empty for local repositories
+    pub host: String,
+    /// User, organization or (GitLab) group path
+    pub owner: String,
+    /// Repository name, without `.git`
+    pub repo: String,
+    /// Branch, tag, commit id or full ref name (e.g. `refs/pull/12/head`)
+    pub reference: Option<String>,
+    /// Directory or file in the repository to pack
+    pub subpath: Option<String>,
+    /// Whether `reference` and `subpath` were split from one browse path,
+    /// like `tree/feature/x/src`, and should be split again where a branch or
+    /// tag with slashes matches
+    split_ref: bool,
+}
+
+impl RemoteSpec {
+    /// Parse a repository URL, shorthand or local path
+    pub fn parse(url: &str) -> Result<Self> {
+        let url = url.trim();
+        if url.is_empty() {
+            return Err(anyhow!("Empty repository URL"));
+        }
+
+        // Local repositories are used as they are
+        if is_local(url) {
+            let path = url.trim_start_matches("file://").trim_end_matches('/');
+            let name = path.rsplit(['/', '\\']).next().unwrap_or(path);
+            return Ok(Self::local(url, name));
+        }
+
+        // Query strings (e.g. Bitbucket's `?at=`) and fragments don't name a ref
+        let url = url.split(['?', '#']).next().unwrap_or(url);
+
+        let (prefix, host, path) = if let Some((scheme, rest)) = url.split_once("://") {
+            let (authority, path) = rest.split_once('/').unwrap_or((rest, ""));
+            let host = authority.rsplit('@').next().unwrap_or(authority);
+            let host = host.split(':').next().unwrap_or(host);
+            (format!("{}://{}/", scheme, authority), host, path)
+        } else if let Some((user_host, path)) = url
+            .split_once(':')
+            .filter(|(user_host, _)| user_host.contains('@') && !user_host.contains('/'))
+        {
+            // scp-like SSH syntax: git@host:owner/repo.git
+            let host = user_host.rsplit('@').next().unwrap_or(user_host);
+            (format!("{}:", user_host), host, path)
+        } else {
+            // Shorthand, with a host if the first segment looks like one
+            match url.split_once('/') {
+                Some((host, path)) if host.contains('.') && !host.starts_with('.') => {
+                    (format!("https://{}/", host), host, path)
+                }
+                _ => ("https://github.com/".to_string(), "github.com", url),
+            }
+        };
+
+        let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+        let (repo_path, route) = match segments.iter().position(|s| *s == "-") {
+            Some(separator) => (&segments[..separator], &segments[separator + 1..]),
+            None if segments.len() > 2 && ROUTES.contains(&segments[2]) => {
+                (&segments[..2], &segments[2..])
+            }
+            None => (&segments[..], &segments[..0]),
+        };
+        let (repo, owner) = match repo_path.split_last() {
+            Some((repo, owner)) if !owner.is_empty() => (repo, owner.join("/")),
+            _ => return Err(anyhow!("Invalid repository URL: {}", url)),
+        };
+
+        let mut spec = Self {
+            url: format!("{}{}", prefix, repo_path.join("/")),
+            host: host.to_lowercase(),
+            owner,
+            repo: repo.strip_suffix(".git").unwrap_or(repo).to_string(),
+            reference: None,
+            subpath: None,
+            split_ref: false,
+        };
+        spec.parse_route(route)
+            .with_context(|| format!("Unsupported repository URL: {}", url))?;
+        Ok(spec)
     }
 
-    // Handle branch URLs: https://github.com/username/repo/tree/branch
-    let branch_regex = Regex::new(r"(?:https?://[^/]+/[^/]+/[^/]+)/tree/([^/]+)").unwrap();
-    if let Some(captures) = branch_regex.captures(url) {
-        let base_url = branch_regex.replace(url, "$1").to_string();
-        let branch_name = captures.get(1).unwrap().as_str();
-        return Ok((base_url, branch_name.to_string()));
+    fn local(url: &str, name: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            host: String::new(),
+            owner: String::new(),
+            repo: name.strip_suffix(".git").unwrap_or(name).to_string(),
+            reference: None,
+            subpath: None,
+            split_ref: false,
+        }
     }
 
-    // Handle commit URLs: https://github.com/username/repo/commit/hash
-    let commit_regex = Regex::new(r"(?:https?://[^/]+/[^/]+/[^/]+)/commit/([^/]+)").unwrap();
-    if let Some(captures) = commit_regex.captures(url) {
-        let base_url = commit_regex.replace(url, "$1").to_string();
-        let commit_hash = captures.get(1).unwrap().as_str();
-        return Ok((base_url, commit_hash.to_string()));
+    /// Read the ref and path from the segments after `owner/repo`
+    fn parse_route(&mut self, route: &[&str]) -> Result<()> {
+        let (kind, rest) = match route.split_first() {
+            Some((kind, rest)) => (*kind, rest),
+            None => return Ok(()),
+        };
+        if rest.is_empty() {
+            return Err(anyhow!("{} without a ref", kind));
+        }
+        if rest.iter().any(|s| *s == "." || *s == "..") {
+            return Err(anyhow!("Relative path segments are not allowed"));
+        }
+
+        match kind {
+            // The ref and the path can both contain slashes
+            "tree" | "blob" | "src" => {
+                self.reference = Some(rest[0].to_string());
+                if rest.len() > 1 {
+                    self.subpath = Some(rest[1..].join("/"));
+                    self.split_ref = true;
+                }
+            }
+            "commit" | "commits" => self.reference = Some(rest[0].to_string()),
+            "branch" => self.reference = Some(rest.join("/")),
+            "pull" => self.reference = Some(format!("refs/pull/{}/head", pr_number(rest[0])?)),
+            "merge_requests" => {
+                self.reference = Some(format!("refs/merge-requests/{}/head", pr_number(rest[0])?))
+            }
+            "pull-requests" => {
+                return Err(anyhow!(
+                    "Bitbucket doesn't publish refs for pull requests; pass the source branch with --remote-branch"
+                ))
+            }
+            _ => return Err(anyhow!("Unknown route: {}", kind)),
+        }
+        Ok(())
+    }
+
+    /// Split a browse path between the ref and the subpath at the longest
+    /// branch or tag the remote advertises, so `tree/feature/x/src` means
+    /// branch `feature/x` if it exists
+    pub fn resolve_ref(&mut self, advertised: &[String]) {
+        if !std::mem::take(&mut self.split_ref) {
+            return;
+        }
+        let (reference, subpath) = match (&self.reference, &self.subpath) {
+            (Some(reference), Some(subpath)) => (reference, subpath),
+            _ => return,
+        };
+
+        let path = format!("{}/{}", reference, subpath);
+        let segments: Vec<&str> = path.split('/').collect();
+        for end in (1..=segments.len()).rev() {
+            let name = segments[..end].join("/");
+            let found = advertised.iter().any(|advertised| {
+                *advertised == format!("refs/heads/{}", name)
+                    || *advertised == format!("refs/tags/{}", name)
+            });
+            if found {
+                debug!("Resolved {} to ref {}", path, name);
+                self.subpath = (end < segments.len()).then(|| segments[end..].join("/"));
+                self.reference = Some(name);
+                return;
+            }
+        }
     }
+}
 
-    // Default case: regular URL
-    Ok((url.to_string(), branch.to_string()))
+fn pr_number(number: &str) -> Result<u64> {
+    number
+        .parse()
+        .map_err(|_| anyhow!("Invalid pull request number: {}", number))
 }
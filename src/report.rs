@@ -0,0 +1,276 @@
+use crate::config::SecurityConfig;
+use crate::packer::PackedRepository;
+use crate::security::{SecretFinding, SecretScanner, SecurityCheckStatus, Severity};
+use anyhow::{anyhow, Context, Result};
+use log::{info, warn};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+/// Key of the finding fingerprint in SARIF `partialFingerprints`
+const SARIF_FINGERPRINT_KEY: &str = "remixSecret/v1";
+
+/// File format of a security report
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportFormat {
+    /// SARIF 2.1.0, for code scanning dashboards
+    Sarif,
+    /// A flat list of findings
+    Json,
+}
+
+impl ReportFormat {
+    pub fn from_name(name: &str) -> Result<Self> {
+        match name.to_lowercase().as_str() {
+            "sarif" => Ok(Self::Sarif),
+            "json" => Ok(Self::Json),
+            _ => Err(anyhow!("Unknown security report format: {}", name)),
+        }
+    }
+
+    /// Format implied by a report path: SARIF for `.sarif` files, JSON otherwise
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("sarif") => Self::Sarif,
+            _ if path
+                .to_string_lossy()
+                .to_lowercase()
+                .ends_with(".sarif.json") =>
+            {
+                Self::Sarif
+            }
+            _ => Self::Json,
+        }
+    }
+}
+
+/// SARIF result level for a severity
+fn sarif_level(severity: Severity) -> &'static str {
+    match severity {
+        Severity::High => "error",
+        Severity::Medium => "warning",
+        Severity::Low => "note",
+    }
+}
+
+/// CVSS-style score used by code scanning dashboards to rank alerts
+fn security_severity(severity: Severity) -> &'static str {
+    match severity {
+        Severity::High => "8.9",
+        Severity::Medium => "5.5",
+        Severity::Low => "3.0",
+    }
+}
+
+/// Rule metadata for a finding
+struct RuleInfo {
+    description: String,
+    severity: Severity,
+    remediation: String,
+}
+
+impl RuleInfo {
+    fn for_rule(scanner: &SecretScanner, rule_id: &str) -> Self {
+        match scanner.rule(rule_id) {
+            Some(rule) => Self {
+                description: rule.description.clone(),
+                severity: rule.severity,
+                remediation: rule.remediation.clone(),
+            },
+            None => Self {
+                description: rule_id.to_string(),
+                severity: Severity::High,
+                remediation: "Revoke and rotate this secret and remove it from the code."
+                    .to_string(),
+            },
+        }
+    }
+}
+
+/// Write the security report for a pack requested in the config, if any
+pub fn output_security_report(repo: &PackedRepository, config: &SecurityConfig) -> Result<()> {
+    if config.report.is_none() {
+        return Ok(());
+    }
+
+    if let SecurityCheckStatus::Disabled = repo.security_check_status {
+        warn!("Security check is disabled, not writing a security report");
+        return Ok(());
+    }
+
+    write_security_report(
+        repo.security_findings.as_deref().unwrap_or_default(),
+        repo.on_disk_only_findings.as_deref().unwrap_or_default(),
+        config,
+    )
+}
+
+/// Write findings to the security report requested in the config, if any.
+///
+/// `on_disk_only` findings are secrets that exist in the repository but not in
+/// the packed output; they are labeled as such.
+pub fn write_security_report(
+    findings: &[SecretFinding],
+    on_disk_only: &[SecretFinding],
+    config: &SecurityConfig,
+) -> Result<()> {
+    let path = match &config.report {
+        Some(path) => Path::new(path),
+        None => return Ok(()),
+    };
+
+    let format = match &config.report_format {
+        Some(name) => ReportFormat::from_name(name)?,
+        None => ReportFormat::from_path(path),
+    };
+    let scanner = SecretScanner::from_config(config)?;
+
+    let report = match format {
+        ReportFormat::Sarif => sarif_report(findings, on_disk_only, &scanner),
+        ReportFormat::Json => json_report(findings, on_disk_only, &scanner),
+    };
+    let json =
+        serde_json::to_string_pretty(&report).context("Failed to serialize security report")?;
+    fs::write(path, json + "\n")
+        .with_context(|| format!("Failed to write security report: {}", path.display()))?;
+
+    info!(
+        "Security report with {} findings written to {}",
+        findings.len() + on_disk_only.len(),
+        path.display()
+    );
+    Ok(())
+}
+
+/// All findings, each with whether it is only on disk
+fn labeled<'a>(
+    findings: &'a [SecretFinding],
+    on_disk_only: &'a [SecretFinding],
+) -> impl Iterator<Item = (&'a SecretFinding, bool)> {
+    findings
+        .iter()
+        .map(|finding| (finding, false))
+        .chain(on_disk_only.iter().map(|finding| (finding, true)))
+}
+
+/// Build a SARIF 2.1.0 log with one run
+pub fn sarif_report(
+    findings: &[SecretFinding],
+    on_disk_only: &[SecretFinding],
+    scanner: &SecretScanner,
+) -> Value {
+    // Only rules with findings are listed, in a stable order
+    let rules: BTreeMap<&str, RuleInfo> = findings
+        .iter()
+        .chain(on_disk_only)
+        .map(|finding| {
+            (
+                finding.rule_id.as_str(),
+                RuleInfo::for_rule(scanner, &finding.rule_id),
+            )
+        })
+        .collect();
+    let rule_index: BTreeMap<&str, usize> = rules
+        .keys()
+        .enumerate()
+        .map(|(index, id)| (*id, index))
+        .collect();
+
+    let sarif_rules: Vec<Value> = rules
+        .iter()
+        .map(|(id, rule)| {
+            json!({
+                "id": id,
+                "shortDescription": { "text": rule.description },
+                "help": { "text": rule.remediation },
+                "defaultConfiguration": { "level": sarif_level(rule.severity) },
+                "properties": {
+                    "tags": ["security", "secret"],
+                    "security-severity": security_severity(rule.severity),
+                },
+            })
+        })
+        .collect();
+
+    let results: Vec<Value> = labeled(findings, on_disk_only)
+        .map(|(finding, on_disk_only)| {
+            let rule = &rules[finding.rule_id.as_str()];
+            let message = if on_disk_only {
+                format!(
+                    "{} found in {} (on disk only, not in the packed output)",
+                    rule.description, finding.file
+                )
+            } else {
+                format!("{} found in {}", rule.description, finding.file)
+            };
+            json!({
+                "ruleId": finding.rule_id,
+                "ruleIndex": rule_index[finding.rule_id.as_str()],
+                "level": sarif_level(rule.severity),
+                "message": { "text": message },
+                "locations": [{
+                    "physicalLocation": {
+                        "artifactLocation": { "uri": finding.file, "uriBaseId": "%SRCROOT%" },
+                        "region": {
+                            "startLine": finding.line,
+                            "startColumn": finding.column,
+                            "snippet": { "text": finding.snippet },
+                        },
+                    },
+                }],
+                "partialFingerprints": { SARIF_FINGERPRINT_KEY: finding.fingerprint },
+                "properties": { "onDiskOnly": on_disk_only },
+            })
+        })
+        .collect();
+
+    json!({
+        "$schema": SARIF_SCHEMA,
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "remix",
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "informationUri": env!("CARGO_PKG_REPOSITORY"),
+                    "rules": sarif_rules,
+                },
+            },
+            "results": results,
+        }],
+    })
+}
+
+/// Build a plain JSON report: one object per finding with its rule's metadata
+pub fn json_report(
+    findings: &[SecretFinding],
+    on_disk_only: &[SecretFinding],
+    scanner: &SecretScanner,
+) -> Value {
+    let findings: Vec<Value> = labeled(findings, on_disk_only)
+        .map(|(finding, on_disk_only)| {
+            let rule = RuleInfo::for_rule(scanner, &finding.rule_id);
+            json!({
+                "rule_id": finding.rule_id,
+                "description": rule.description,
+                "severity": rule.severity,
+                "file": finding.file,
+                "line": finding.line,
+                "column": finding.column,
+                "snippet": finding.snippet,
+                "fingerprint": finding.fingerprint,
+                "on_disk_only": on_disk_only,
+                "remediation": rule.remediation,
+            })
+        })
+        .collect();
+
+    json!({
+        "tool": "remix",
+        "version": env!("CARGO_PKG_VERSION"),
+        "findings": findings,
+    })
+}
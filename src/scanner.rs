@@ -1,6 +1,9 @@
+use crate::changes;
 use crate::config::Config;
-use anyhow::{Context, Result};
-use glob::{glob_with, MatchOptions};
+use crate::source::{FileSource, FileStat};
+use anyhow::{bail, Context, Result};
+use git2::{ObjectType, Oid, Repository, Tree, TreeWalkMode, TreeWalkResult};
+use glob::{glob_with, MatchOptions, Pattern};
 use ignore::{
     gitignore::{Gitignore, GitignoreBuilder},
     WalkBuilder,
@@ -11,7 +14,10 @@ use rayon::prelude::*;
 use std::collections::HashSet;
 use std::fs;
 use std::path::{Path, PathBuf};
-use tree_magic_mini as tree_magic;
+use std::time::SystemTime;
+
+/// Git file mode of a symbolic link
+const GIT_FILEMODE_LINK: i32 = 0o120000;
 
 #[derive(Debug, Clone)]
 pub struct FileInfo {
@@ -20,59 +26,71 @@ pub struct FileInfo {
     pub size: u64,
     pub mime_type: String,
     pub is_binary: bool,
+    pub modified: Option<SystemTime>,
+    /// Unified diff of the file's changes, in git-aware mode with diffs
+    pub diff: Option<String>,
 }
 
 impl FileInfo {
-    pub fn new(path: PathBuf, base_path: &Path) -> Result<Self> {
-        let metadata = fs::metadata(&path)
-            .context(format!("Failed to get metadata for {}", path.display()))?;
-
-        let size = metadata.len();
-
-        // Detect the MIME type of the file
-        let mime_type = tree_magic::from_filepath(&path).unwrap_or("application/octet-stream");
-
-        // Determine if the file is binary based on its MIME type
-        let is_binary = mime_type.starts_with("application/")
-            && !mime_type.contains("json")
-            && !mime_type.contains("xml")
-            && !mime_type.contains("javascript")
-            && !mime_type.contains("typescript")
-            || mime_type.starts_with("image/")
-            || mime_type.starts_with("audio/")
-            || mime_type.starts_with("video/");
-
-        let relative_path = path.strip_prefix(base_path).unwrap_or(&path).to_path_buf();
+    /// Information about a file of a source, with its MIME type detected
+    pub fn new<S: FileSource + ?Sized>(
+        source: &S,
+        relative_path: PathBuf,
+        stat: FileStat,
+    ) -> Result<Self> {
+        let mime_type = source.mime_type(&relative_path)?;
 
         Ok(Self {
-            path,
+            path: source.path(&relative_path),
             relative_path,
-            size,
+            size: stat.size,
             mime_type: mime_type.to_string(),
-            is_binary,
+            is_binary: is_binary_mime(mime_type),
+            modified: stat.modified,
+            diff: None,
         })
     }
 }
 
+/// Whether a MIME type is for binary content
+fn is_binary_mime(mime_type: &str) -> bool {
+    mime_type.starts_with("application/")
+        && !mime_type.contains("json")
+        && !mime_type.contains("xml")
+        && !mime_type.contains("script")
+        && !mime_type.contains("x-php")
+        && !mime_type.contains("x-ruby")
+        && !mime_type.contains("x-perl")
+        && !mime_type.contains("x-python")
+        || mime_type.starts_with("image/")
+        || mime_type.starts_with("audio/")
+        || mime_type.starts_with("video/")
+}
+
 /// Read a .remixignore file if it exists
 fn read_mixignore(base_path: &Path) -> Option<Gitignore> {
     let mixignore_path = base_path.join(".remixignore");
     if mixignore_path.exists() {
         debug!("Found .remixignore file");
-        let mut builder = GitignoreBuilder::new(base_path);
-        if builder
-            .add_line(
-                None,
-                &fs::read_to_string(mixignore_path).unwrap_or_default(),
-            )
-            .is_ok()
-        {
-            return builder.build().ok();
-        }
+        return parse_ignore_file(
+            base_path,
+            &fs::read_to_string(mixignore_path).unwrap_or_default(),
+        );
     }
     None
 }
 
+/// Build a matcher from the content of a gitignore-style file in `root`
+fn parse_ignore_file(root: &Path, content: &str) -> Option<Gitignore> {
+    let mut builder = GitignoreBuilder::new(root);
+    for line in content.lines() {
+        if builder.add_line(None, line).is_err() {
+            warn!("Invalid ignore pattern in {}: {}", root.display(), line);
+        }
+    }
+    builder.build().ok()
+}
+
 /// Get the default ignore patterns
 fn get_default_ignore_patterns() -> Vec<&'static str> {
     vec![
@@ -162,31 +180,20 @@ pub fn should_ignore_common(path: &Path) -> bool {
     false
 }
 
-pub fn scan_repository(base_path: &Path, config: &Config) -> Result<Vec<FileInfo>> {
-    info!("Scanning repository at {}", base_path.display());
-
-    // Create a progress bar for scanning
-    let spinner = ProgressBar::new_spinner();
-    spinner.set_style(
-        ProgressStyle::default_spinner()
-            .template("{spinner:.green} {msg}")
-            .unwrap(),
-    );
-    spinner.set_message("Scanning repository files...");
-
-    // Initialize the ignore system with the appropriate layers
-    let should_ignore = |path: &Path| -> bool {
-        // First check common patterns that should always be ignored
-        if should_ignore_common(path) {
-            return true;
-        }
+/// The custom, .remixignore and default ignore layers, matched against paths
+/// relative to the scan root
+struct IgnoreLayers<'a> {
+    config: &'a Config,
+    mixignore: Option<Gitignore>,
+}
 
-        let relative_path = path.strip_prefix(base_path).unwrap_or(path);
+impl IgnoreLayers<'_> {
+    fn is_ignored(&self, relative_path: &Path) -> bool {
         let path_str = normalize_path(&relative_path.to_string_lossy());
 
         // Layer 1 (highest priority): Custom ignore patterns
-        if !config.ignore.custom_patterns.is_empty() {
-            for pattern in &config.ignore.custom_patterns {
+        if !self.config.ignore.custom_patterns.is_empty() {
+            for pattern in &self.config.ignore.custom_patterns {
                 // Normalize the pattern too
                 let pattern = normalize_path(pattern);
 
@@ -201,18 +208,16 @@ pub fn scan_repository(base_path: &Path, config: &Config) -> Result<Vec<FileInfo
         }
 
         // Layer 2: .remixignore file
-        if config.ignore.use_mixignore {
-            if let Some(ref mixignore) = read_mixignore(base_path) {
-                let result = mixignore.matched_path_or_any_parents(relative_path, false);
-                if result.is_ignore() {
-                    debug!("Ignoring '{}' due to .remixignore", path_str);
-                    return true;
-                }
+        if let Some(ref mixignore) = self.mixignore {
+            let result = mixignore.matched_path_or_any_parents(relative_path, false);
+            if result.is_ignore() {
+                debug!("Ignoring '{}' due to .remixignore", path_str);
+                return true;
             }
         }
 
         // Layer 3: Default ignore patterns if enabled
-        if config.ignore.use_default_patterns {
+        if self.config.ignore.use_default_patterns {
             for pattern in get_default_ignore_patterns() {
                 if glob_match::glob_match(pattern, &path_str) {
                     debug!(
@@ -225,6 +230,145 @@ pub fn scan_repository(base_path: &Path, config: &Config) -> Result<Vec<FileInfo
         }
 
         false
+    }
+}
+
+/// Options used to match include patterns
+fn include_match_options() -> MatchOptions {
+    MatchOptions {
+        case_sensitive: false,
+        require_literal_separator: false,
+        require_literal_leading_dot: false,
+    }
+}
+
+/// Whether a file passes the size limit and, unless binary files were
+/// explicitly included, isn't binary
+pub(crate) fn within_limits(info: &FileInfo, config: &Config) -> bool {
+    if info.size > config.max_file_size {
+        debug!(
+            "Skipping large file: {} ({} bytes)",
+            info.path.display(),
+            info.size
+        );
+        false
+    } else if info.is_binary
+        && !config
+            .include
+            .iter()
+            .any(|p| p.contains("*.bin") || p.contains("binary"))
+    {
+        debug!(
+            "Skipping binary file: {} ({})",
+            info.path.display(),
+            info.mime_type
+        );
+        false
+    } else {
+        true
+    }
+}
+
+/// Scan the files of a source that the config would pack
+pub fn scan_repository<S: FileSource + ?Sized>(
+    source: &S,
+    config: &Config,
+) -> Result<Vec<FileInfo>> {
+    info!("Scanning {}", source.describe());
+
+    // Changes and history need a git working copy
+    let git = &config.git;
+    if source.root().is_none()
+        && (git.since.is_some() || git.staged || git.working_tree || git.history)
+    {
+        bail!("Git options can't be used with {}", source.describe());
+    }
+
+    // Create a progress bar for scanning
+    let spinner = ProgressBar::new_spinner();
+    spinner.set_style(
+        ProgressStyle::default_spinner()
+            .template("{spinner:.green} {msg}")
+            .unwrap(),
+    );
+    spinner.set_message("Collecting files...");
+    let mut files = source.select(config)?;
+
+    // Limit the files to those changed in git, if requested
+    let changes = match source.root() {
+        Some(root) => {
+            changes::changed_files(root, &config.git)?.map(|changes| (root.to_path_buf(), changes))
+        }
+        None => None,
+    };
+    if let Some((root, changes)) = &changes {
+        spinner.set_message("Limiting to changed files...");
+        files.retain(|path| changes.contains_key(&root.join(path)));
+    }
+
+    spinner.set_message("Processing file information...");
+
+    // Process file information in parallel
+    let file_infos: Vec<FileInfo> = files
+        .into_par_iter()
+        .filter_map(|path| {
+            let info = source.stat(&path).and_then(|stat| {
+                // Don't detect the type of files that are too large anyway
+                if stat.size > config.max_file_size {
+                    debug!(
+                        "Skipping large file: {} ({} bytes)",
+                        path.display(),
+                        stat.size
+                    );
+                    return Ok(None);
+                }
+                FileInfo::new(source, path.clone(), stat).map(Some)
+            });
+            match info {
+                Ok(Some(mut info)) => {
+                    if let Some((root, changes)) = &changes {
+                        info.diff = changes.get(&root.join(&path)).cloned().flatten();
+                    }
+
+                    within_limits(&info, config).then_some(info)
+                }
+                Ok(None) => None,
+                Err(e) => {
+                    warn!("Error processing file {}: {}", path.display(), e);
+                    None
+                }
+            }
+        })
+        .collect();
+
+    spinner.finish_with_message(format!("Found {} files to process", file_infos.len()));
+    info!("Found {} files to process", file_infos.len());
+
+    Ok(file_infos)
+}
+
+/// Walk a directory on disk and keep the files that the ignore and include
+/// config would pack, respecting `.gitignore` files if enabled.
+///
+/// Returns the paths as walked, i.e. joined to `base_path`.
+pub(crate) fn select_dir_files(base_path: &Path, config: &Config) -> Result<Vec<PathBuf>> {
+    // Initialize the ignore system with the appropriate layers
+    let layers = IgnoreLayers {
+        config,
+        mixignore: if config.ignore.use_mixignore {
+            read_mixignore(base_path)
+        } else {
+            None
+        },
+    };
+    let should_ignore = |path: &Path| -> bool {
+        // First check common patterns that should always be ignored
+        if should_ignore_common(path) {
+            return true;
+        }
+
+        let relative_path = path.strip_prefix(base_path).unwrap_or(path);
+        layers.is_ignored(relative_path)
     };
 
     // Use the ignore crate to build a walker that respects .gitignore if enabled
@@ -257,13 +401,9 @@ pub fn scan_repository(base_path: &Path, config: &Config) -> Result<Vec<FileInfo
     }
 
     // Filter files using our multi-layered ignore system
-    spinner.set_message("Collecting files...");
     let mut files: Vec<PathBuf> = walker
         .build()
-        .filter_map(|entry| {
-            spinner.tick();
-            entry.ok()
-        })
+        .filter_map(|entry| entry.ok())
         .filter(|entry| {
             let path = entry.path();
 
@@ -280,14 +420,10 @@ pub fn scan_repository(base_path: &Path, config: &Config) -> Result<Vec<FileInfo
 
     // Apply include patterns if specified
     if !config.include.is_empty() {
-        spinner.set_message(format!("Applying include patterns: {:?}", config.include));
+        debug!("Applying include patterns: {:?}", config.include);
 
         let mut included_files = HashSet::new();
-        let options = MatchOptions {
-            case_sensitive: false,
-            require_literal_separator: false,
-            require_literal_leading_dot: false,
-        };
+        let options = include_match_options();
 
         for pattern in &config.include {
             // Normalize pattern for consistent matching
@@ -313,48 +449,140 @@ pub fn scan_repository(base_path: &Path, config: &Config) -> Result<Vec<FileInfo
         }
     }
 
-    spinner.set_message("Processing file information...");
+    Ok(files)
+}
 
-    // Process file information in parallel
-    let file_infos: Vec<FileInfo> = files
-        .par_iter()
-        .filter_map(|path| {
-            match FileInfo::new(path.clone(), base_path) {
-                Ok(info) => {
-                    // Filter out files larger than the max size
-                    if info.size > config.max_file_size {
-                        debug!(
-                            "Skipping large file: {} ({} bytes)",
-                            info.path.display(),
-                            info.size
-                        );
-                        None
-                    } else if info.is_binary
-                        && !config
-                            .include
-                            .iter()
-                            .any(|p| p.contains("*.bin") || p.contains("binary"))
-                    {
-                        debug!(
-                            "Skipping binary file: {} ({})",
-                            info.path.display(),
-                            info.mime_type
-                        );
-                        None
-                    } else {
-                        Some(info)
-                    }
-                }
-                Err(e) => {
-                    warn!("Error processing file {}: {}", path.display(), e);
-                    None
+/// Walk a git tree and keep the blobs that the ignore and include config would
+/// pack, with the `.gitignore` and `.remixignore` files of the tree applied.
+///
+/// Returns each blob's path relative to the tree, in tree order.
+pub(crate) fn select_tree_blobs(
+    repo: &Repository,
+    tree: &Tree,
+    config: &Config,
+) -> Result<Vec<(PathBuf, Oid)>> {
+    // Skip target/ and .git/ directories completely
+    let blobs = tree_blobs(tree, |name| name == "target" || name == ".git")?;
+
+    let read_blob = |path: &Path| -> Option<String> {
+        let entry = tree.get_path(path).ok()?;
+        let blob = repo.find_blob(entry.id()).ok()?;
+        Some(String::from_utf8_lossy(blob.content()).into_owned())
+    };
+    Ok(select_files(blobs, read_blob, config))
+}
+
+/// The regular files of a git tree, in tree order, relative to the tree.
+/// Directories for which `skip_dir` returns true are not walked.
+pub(crate) fn tree_blobs(
+    tree: &Tree,
+    skip_dir: impl Fn(&str) -> bool,
+) -> Result<Vec<(PathBuf, Oid)>> {
+    let mut blobs = Vec::new();
+    tree.walk(TreeWalkMode::PreOrder, |dir, entry| {
+        let name = match entry.name() {
+            Some(name) => name,
+            None => return TreeWalkResult::Skip,
+        };
+        let relative_path = Path::new(dir).join(name);
+
+        if entry.kind() == Some(ObjectType::Tree) {
+            return if skip_dir(name) {
+                debug!("Ignoring directory: {}", relative_path.display());
+                TreeWalkResult::Skip
+            } else {
+                TreeWalkResult::Ok
+            };
+        }
+
+        // Regular files only: no symbolic links or submodules
+        if entry.kind() != Some(ObjectType::Blob) || entry.filemode() == GIT_FILEMODE_LINK {
+            return TreeWalkResult::Ok;
+        }
+
+        blobs.push((relative_path, entry.id()));
+        TreeWalkResult::Ok
+    })
+    .context("Failed to walk the commit tree")?;
+
+    Ok(blobs)
+}
+
+/// Keep the files that the ignore and include config would pack, out of
+/// files that aren't read from disk, such as git blobs or archive entries.
+/// The `.remixignore` file and the `.gitignore` files among them apply to
+/// their directory like on disk; `read` returns the content of one of them.
+///
+/// Paths are relative to the scan root, and `files` keeps its order.
+pub(crate) fn select_files<T>(
+    mut files: Vec<(PathBuf, T)>,
+    read: impl Fn(&Path) -> Option<String>,
+    config: &Config,
+) -> Vec<(PathBuf, T)> {
+    let layers = IgnoreLayers {
+        config,
+        mixignore: if config.ignore.use_mixignore {
+            read(Path::new(".remixignore"))
+                .and_then(|content| parse_ignore_file(Path::new(""), &content))
+        } else {
+            None
+        },
+    };
+
+    // .gitignore files apply to their directory
+    let mut gitignores: Vec<(PathBuf, Gitignore)> = Vec::new();
+    if config.ignore.use_gitignore {
+        for (path, _) in &files {
+            if path.file_name().is_some_and(|name| name == ".gitignore") {
+                let dir = path.parent().unwrap_or(Path::new(""));
+                if let Some(gitignore) =
+                    read(path).and_then(|content| parse_ignore_file(dir, &content))
+                {
+                    gitignores.push((dir.to_path_buf(), gitignore));
                 }
             }
+        }
+    }
+
+    let is_gitignored = |path: &Path| {
+        gitignores.iter().any(|(dir, gitignore)| {
+            path.starts_with(dir)
+                && gitignore
+                    .matched_path_or_any_parents(path, false)
+                    .is_ignore()
         })
-        .collect();
+    };
+    files.retain(|(path, _)| {
+        !should_ignore_common(path) && !layers.is_ignored(path) && !is_gitignored(path)
+    });
 
-    spinner.finish_with_message(format!("Found {} files to process", file_infos.len()));
-    info!("Found {} files to process", file_infos.len());
+    // Apply include patterns if specified
+    let patterns: Vec<Pattern> = config
+        .include
+        .iter()
+        .filter_map(|pattern| match Pattern::new(&normalize_path(pattern)) {
+            Ok(pattern) => Some(pattern),
+            Err(e) => {
+                warn!("Invalid include pattern '{}': {}", pattern, e);
+                None
+            }
+        })
+        .collect();
+    let options = include_match_options();
+    let included: Vec<bool> = files
+        .iter()
+        .map(|(path, _)| {
+            let path = normalize_path(&path.to_string_lossy());
+            patterns
+                .iter()
+                .any(|pattern| pattern.matches_with(&path, options))
+        })
+        .collect();
+    // Like on disk, patterns that match nothing don't filter anything
+    if included.contains(&true) {
+        let mut included = included.into_iter();
+        files.retain(|_| included.next().unwrap_or(false));
+    }
 
-    Ok(file_infos)
+    files
 }
@@ -1,187 +1,833 @@
-use crate::scanner::should_ignore_common;
-use anyhow::Result;
+use crate::config::SecurityConfig;
+use crate::scanner::FileInfo;
+use crate::source::FileSource;
+use anyhow::{anyhow, Context, Result};
 use log::{debug, info};
-use serde::Serialize;
-use std::collections::HashSet;
+use rayon::prelude::*;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
 
 #[derive(Debug, Serialize, Clone)]
 pub enum SecurityCheckStatus {
     Disabled,
     CompletedNoFindings,
     CompletedWithFindings,
-    Failed(String),
-}
-
-/// Performs a security check on a repository to identify potentially sensitive files
-pub fn perform_security_check(path: &Path) -> Result<Vec<String>> {
-    info!("Performing security check on repository");
-
-    let mut suspicious_files = HashSet::new();
-    let sensitive_keywords = get_sensitive_keywords();
-
-    // Function to normalize paths for consistent handling
-    let normalize_path = |path_str: &str| -> String { path_str.replace('\\', "/") };
-
-    // Walk through the repository
-    for entry in walkdir::WalkDir::new(path)
-        .into_iter()
-        .filter_map(Result::ok)
-        .filter(|e| {
-            // Skip directories we know we should ignore
-            let p = e.path();
-            if p.is_dir() {
-                let path_str = normalize_path(&p.to_string_lossy());
-                if path_str.contains("/target")
-                    || path_str.contains("/.git")
-                    || path_str.ends_with("/target")
-                    || path_str.ends_with("/.git")
-                {
-                    return false;
-                }
+}
+
+/// What happens to a file that contains secrets
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SecurityMode {
+    /// Leave the whole file out of the output
+    Skip,
+    /// Replace each secret with a `[REDACTED:<rule>]` placeholder
+    Redact,
+    /// Abort without writing any output
+    Fail,
+}
+
+impl SecurityMode {
+    pub fn from_name(name: &str) -> Result<Self> {
+        match name.to_lowercase().as_str() {
+            "skip" => Ok(Self::Skip),
+            "redact" => Ok(Self::Redact),
+            "fail" => Ok(Self::Fail),
+            _ => Err(anyhow!("Unknown security mode: {}", name)),
+        }
+    }
+}
+
+/// Minimum Shannon entropy, in bits per character, of a value assigned to a
+/// secret-looking name before the generic rule reports it
+const GENERIC_SECRET_MIN_ENTROPY: f64 = 3.5;
+
+/// Number of leading characters of a secret left visible in masked snippets
+const MASK_VISIBLE_CHARS: usize = 4;
+
+/// Longest snippet reported for a finding, in characters
+const MAX_SNIPPET_CHARS: usize = 120;
+
+/// Marker that suppresses findings on the line it appears on
+const ALLOW_PRAGMA: &str = "remix:allow-secret";
+
+/// Number of hex digits in a finding fingerprint
+const FINGERPRINT_LENGTH: usize = 16;
+
+/// Default name of the baseline file, in the repository root
+pub const BASELINE_FILENAME: &str = ".remix-secrets-baseline.json";
+
+/// Version of the baseline file format
+const BASELINE_VERSION: u32 = 1;
+
+/// A built-in rule
+struct BuiltinRule {
+    id: &'static str,
+    description: &'static str,
+    pattern: &'static str,
+    /// Keyword prefilter, see [`SecretRule`]
+    keywords: &'static [&'static str],
+    min_entropy: Option<f64>,
+    severity: Severity,
+}
+
+/// Built-in rules.
+///
+/// The secret is the first capture group that participated in the match, or the
+/// whole match if the pattern has no groups. Specific rules come first so they win
+/// over the generic assignment rule when both match the same text.
+const BUILTIN_RULES: &[BuiltinRule] = &[
+    BuiltinRule {
+        id: "aws-access-key",
+        description: "AWS access key ID",
+        pattern: r"\b((?:AKIA|ASIA|ABIA|ACCA)[0-9A-Z]{16})\b",
+        keywords: &["akia", "asia", "abia", "acca"],
+        min_entropy: None,
+        severity: Severity::High,
+    },
+    BuiltinRule {
+        id: "aws-secret-key",
+        description: "AWS secret access key",
+        pattern: r#"(?i)aws_?secret_?(?:access_?)?key['"]?\s*(?::=|=>|=|:)\s*['"]?([A-Za-z0-9/+]{40})\b"#,
+        keywords: &["aws"],
+        min_entropy: None,
+        severity: Severity::High,
+    },
+    BuiltinRule {
+        id: "github-token",
+        description: "GitHub personal access, OAuth or app token",
+        pattern: r"\b(gh[pousr]_[A-Za-z0-9]{36,255})\b",
+        keywords: &["ghp_", "gho_", "ghu_", "ghs_", "ghr_"],
+        min_entropy: None,
+        severity: Severity::High,
+    },
+    BuiltinRule {
+        id: "github-fine-grained-token",
+        description: "GitHub fine-grained personal access token",
+        pattern: r"\b(github_pat_[A-Za-z0-9_]{82})\b",
+        keywords: &["github_pat_"],
+        min_entropy: None,
+        severity: Severity::High,
+    },
+    BuiltinRule {
+        id: "gitlab-token",
+        description: "GitLab personal access token",
+        pattern: r"\b(glpat-[A-Za-z0-9_-]{20})\b",
+        keywords: &["glpat-"],
+        min_entropy: None,
+        severity: Severity::High,
+    },
+    BuiltinRule {
+        id: "slack-token",
+        description: "Slack bot, user or app token",
+        pattern: r"\b(xox[abposr]-[0-9A-Za-z-]{10,})\b",
+        keywords: &["xox"],
+        min_entropy: None,
+        severity: Severity::High,
+    },
+    BuiltinRule {
+        id: "slack-webhook",
+        description: "Slack incoming webhook URL",
+        pattern: r"(https://hooks\.slack\.com/services/T[A-Z0-9]+/B[A-Z0-9]+/[A-Za-z0-9]{16,})",
+        keywords: &["hooks.slack.com"],
+        min_entropy: None,
+        severity: Severity::Medium,
+    },
+    BuiltinRule {
+        id: "stripe-key",
+        description: "Stripe live secret or restricted key",
+        pattern: r"\b((?:sk|rk)_live_[0-9a-zA-Z]{24,})\b",
+        keywords: &["sk_live_", "rk_live_"],
+        min_entropy: None,
+        severity: Severity::High,
+    },
+    BuiltinRule {
+        id: "google-api-key",
+        description: "Google API key",
+        pattern: r"\b(AIza[0-9A-Za-z_-]{35})\b",
+        keywords: &["aiza"],
+        min_entropy: None,
+        severity: Severity::Medium,
+    },
+    BuiltinRule {
+        id: "openai-api-key",
+        description: "OpenAI API key",
+        pattern: r"\b(sk-(?:proj-|svcacct-)?[A-Za-z0-9_-]{20,}T3BlbkFJ[A-Za-z0-9_-]{20,})\b",
+        keywords: &["t3blbkfj"],
+        min_entropy: None,
+        severity: Severity::High,
+    },
+    BuiltinRule {
+        id: "anthropic-api-key",
+        description: "Anthropic API key",
+        pattern: r"\b(sk-ant-(?:api|admin)\d{2}-[A-Za-z0-9_-]{80,})\b",
+        keywords: &["sk-ant-"],
+        min_entropy: None,
+        severity: Severity::High,
+    },
+    BuiltinRule {
+        id: "jwt",
+        description: "JSON Web Token",
+        pattern: r"\b(eyJ[A-Za-z0-9_-]{10,}\.eyJ[A-Za-z0-9_-]{10,}\.[A-Za-z0-9_-]{10,})",
+        keywords: &["eyj"],
+        min_entropy: None,
+        severity: Severity::Medium,
+    },
+    BuiltinRule {
+        id: "private-key",
+        description: "PEM-encoded private key",
+        pattern: r"(?i)-----BEGIN[ A-Z0-9_-]{0,100}PRIVATE KEY(?: BLOCK)?-----\s*([A-Za-z0-9+/=\s:,-]{32,}?)\s*-----END[ A-Z0-9_-]{0,100}PRIVATE KEY(?: BLOCK)?-----",
+        keywords: &["private key"],
+        min_entropy: None,
+        severity: Severity::High,
+    },
+    BuiltinRule {
+        id: "connection-string-password",
+        description: "Password in a database or message broker connection string",
+        pattern: r#"(?i)\b(?:postgres(?:ql)?|mysql|mariadb|mongodb(?:\+srv)?|redis|rediss|amqps?|mssql|sqlserver)://[^\s:/@'"]+:([^\s@'"/]+)@"#,
+        keywords: &["://"],
+        min_entropy: None,
+        severity: Severity::High,
+    },
+    BuiltinRule {
+        id: "generic-secret",
+        description: "High-entropy value assigned to a secret-looking name",
+        pattern: r#"(?im)[\w.-]*(?:secret|token|passw(?:or)?d|pwd|api[_-]?key|access[_-]?key|auth[_-]?key|private[_-]?key|credentials?)[\w.-]*['"]?\s*(?::=|=>|=|:)\s*(?:['"]([^'"\s]{8,200})['"]|([^\s'"(),;{}\[\]]{8,200})(?:[\s,;]|$))"#,
+        keywords: &["secret", "token", "passw", "pwd", "key", "credential"],
+        min_entropy: Some(GENERIC_SECRET_MIN_ENTROPY),
+        severity: Severity::Medium,
+    },
+];
+
+/// How serious a leaked secret is, from least to most
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Low,
+    Medium,
+    High,
+}
+
+impl Severity {
+    pub fn from_name(name: &str) -> Result<Self> {
+        match name.to_lowercase().as_str() {
+            "low" => Ok(Self::Low),
+            "medium" => Ok(Self::Medium),
+            "high" => Ok(Self::High),
+            _ => Err(anyhow!("Unknown severity: {}", name)),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Low => "low",
+            Self::Medium => "medium",
+            Self::High => "high",
+        }
+    }
+}
+
+/// A rule that finds one kind of secret
+#[derive(Debug, Clone)]
+pub struct SecretRule {
+    pub id: String,
+    /// What the rule finds, e.g. "GitHub personal access token"
+    pub description: String,
+    pub severity: Severity,
+    /// How to fix a finding
+    pub remediation: String,
+    regex: Regex,
+    /// Lowercase strings of which at least one must occur for the rule to run
+    keywords: Vec<String>,
+    /// Minimum Shannon entropy of the secret, in bits per character
+    min_entropy: Option<f64>,
+}
+
+impl SecretRule {
+    pub fn new(
+        id: &str,
+        description: &str,
+        pattern: &str,
+        keywords: &[&str],
+        min_entropy: Option<f64>,
+        severity: Severity,
+    ) -> Result<Self> {
+        let regex =
+            Regex::new(pattern).with_context(|| format!("Invalid pattern for rule {}", id))?;
+
+        Ok(Self {
+            id: id.to_string(),
+            description: description.to_string(),
+            severity,
+            remediation: format!(
+                "Revoke and rotate this secret ({}) and remove it from the code. Load it at \
+                 runtime from the environment or a secret manager instead, or allowlist it if \
+                 it is a test value.",
+                description
+            ),
+            regex,
+            keywords: keywords.iter().map(|k| k.to_lowercase()).collect(),
+            min_entropy,
+        })
+    }
+}
+
+/// A secret located in a piece of text
+#[derive(Debug, Clone, PartialEq)]
+pub struct SecretMatch {
+    pub rule_id: String,
+    /// Byte range of the secret value
+    pub start: usize,
+    pub end: usize,
+    /// 1-based line of the start of the secret
+    pub line: usize,
+    /// 1-based character column of the start of the secret
+    pub column: usize,
+}
+
+impl SecretMatch {
+    /// Fingerprint of this match in `file`, see [`fingerprint`]
+    pub fn fingerprint(&self, file: &str, content: &str) -> String {
+        fingerprint(file, &self.rule_id, &content[self.start..self.end])
+    }
+
+    /// Report this match for a file, with the secret masked in the snippet
+    pub fn to_finding(&self, file: &str, content: &str) -> SecretFinding {
+        let line_start = content[..self.start].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = content[self.start..]
+            .find('\n')
+            .map_or(content.len(), |i| self.start + i);
+        let secret_end = self.end.min(line_end);
+
+        let snippet = format!(
+            "{}{}{}",
+            &content[line_start..self.start],
+            mask_secret(&content[self.start..self.end]),
+            &content[secret_end..line_end]
+        );
+        let snippet = snippet.trim();
+
+        SecretFinding {
+            file: file.to_string(),
+            line: self.line,
+            column: self.column,
+            rule_id: self.rule_id.clone(),
+            fingerprint: self.fingerprint(file, content),
+            snippet: if snippet.chars().count() > MAX_SNIPPET_CHARS {
+                format!(
+                    "{}...",
+                    snippet.chars().take(MAX_SNIPPET_CHARS).collect::<String>()
+                )
+            } else {
+                snippet.to_string()
+            },
+        }
+    }
+}
+
+/// A secret found in a file, as reported in the output
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct SecretFinding {
+    pub file: String,
+    pub line: usize,
+    pub column: usize,
+    pub rule_id: String,
+    /// Stable id of this secret in this file, for use in allowlists
+    pub fingerprint: String,
+    /// The line containing the secret, with the secret masked
+    pub snippet: String,
+}
+
+/// Identify a secret by file, rule and value, so the id survives the secret
+/// moving to another line
+pub fn fingerprint(file: &str, rule_id: &str, secret: &str) -> String {
+    let digest = Sha256::digest(format!("{}:{}:{}", file, rule_id, secret));
+    format!("{:x}", digest)[..FINGERPRINT_LENGTH].to_string()
+}
+
+/// Secrets that should not be reported
+#[derive(Debug, Clone, Default)]
+struct SecretAllowlist {
+    /// Glob patterns of files to ignore
+    paths: Vec<String>,
+    /// Patterns matched against the secret value
+    regexes: Vec<Regex>,
+    fingerprints: HashSet<String>,
+}
+
+impl SecretAllowlist {
+    fn allows_path(&self, file: &str) -> bool {
+        self.paths
+            .iter()
+            .any(|pattern| glob_match::glob_match(pattern, file))
+    }
+
+    fn allows_secret(&self, secret: &str) -> bool {
+        self.regexes.iter().any(|regex| regex.is_match(secret))
+    }
+}
+
+/// Runs a set of secret rules over text
+#[derive(Debug, Clone)]
+pub struct SecretScanner {
+    rules: Vec<SecretRule>,
+    allowlist: SecretAllowlist,
+}
+
+impl SecretScanner {
+    /// Scanner with the built-in ruleset
+    pub fn builtin() -> &'static SecretScanner {
+        static BUILTIN: OnceLock<SecretScanner> = OnceLock::new();
+        BUILTIN.get_or_init(|| Self {
+            rules: BUILTIN_RULES
+                .iter()
+                .map(|rule| {
+                    SecretRule::new(
+                        rule.id,
+                        rule.description,
+                        rule.pattern,
+                        rule.keywords,
+                        rule.min_entropy,
+                        rule.severity,
+                    )
+                    .expect("built-in secret rules are valid")
+                })
+                .collect(),
+            allowlist: SecretAllowlist::default(),
+        })
+    }
+
+    /// Scanner with the built-in ruleset plus the custom rules and allowlist
+    /// from the config
+    pub fn from_config(config: &SecurityConfig) -> Result<Self> {
+        let mut rules = Self::builtin().rules.clone();
+        for rule in &config.custom_rules {
+            if rules.iter().any(|existing| existing.id == rule.id) {
+                return Err(anyhow!("Duplicate secret rule id: {}", rule.id));
+            }
+
+            let keywords: Vec<&str> = rule.keywords.iter().map(String::as_str).collect();
+            let severity = match &rule.severity {
+                Some(severity) => Severity::from_name(severity)
+                    .with_context(|| format!("Invalid severity for rule {}", rule.id))?,
+                None => Severity::High,
+            };
+            let mut custom = SecretRule::new(
+                &rule.id,
+                rule.description.as_deref().unwrap_or(&rule.id),
+                &rule.regex,
+                &keywords,
+                rule.entropy,
+                severity,
+            )?;
+            if let Some(remediation) = &rule.remediation {
+                custom.remediation = remediation.clone();
             }
-            e.file_type().is_file()
+
+            debug!("Loaded custom secret rule {}", rule.id);
+            rules.push(custom);
+        }
+
+        let regexes = config
+            .allowlist
+            .regexes
+            .iter()
+            .map(|pattern| {
+                Regex::new(pattern)
+                    .with_context(|| format!("Invalid secret allowlist pattern: {}", pattern))
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Self {
+            rules,
+            allowlist: SecretAllowlist {
+                paths: config
+                    .allowlist
+                    .paths
+                    .iter()
+                    .map(|path| path.replace('\\', "/"))
+                    .collect(),
+                regexes,
+                fingerprints: config.allowlist.fingerprints.iter().cloned().collect(),
+            },
         })
-    {
-        // Skip files in target/ and .git/ directories
-        let file_path = entry.path();
-        let path_str = normalize_path(&file_path.to_string_lossy());
-
-        if path_str.contains("/target/")
-            || path_str.starts_with("target/")
-            || path_str.contains("/.git/")
-            || path_str.starts_with(".git/")
-        {
-            continue;
+    }
+
+    /// The rule with the given id
+    pub fn rule(&self, id: &str) -> Option<&SecretRule> {
+        self.rules.iter().find(|rule| rule.id == id)
+    }
+
+    /// Find the secrets in a file that aren't allowlisted by path or fingerprint
+    pub fn scan_file(&self, file: &str, content: &str) -> Vec<SecretMatch> {
+        if self.allowlist.allows_path(file) {
+            debug!("Not scanning allowlisted file: {}", file);
+            return Vec::new();
         }
 
-        // Skip common files we should ignore
-        if should_ignore_common(file_path) {
-            continue;
+        let mut matches = self.scan(content);
+        if !self.allowlist.fingerprints.is_empty() {
+            matches.retain(|secret| {
+                !self
+                    .allowlist
+                    .fingerprints
+                    .contains(&secret.fingerprint(file, content))
+            });
         }
+        matches
+    }
 
-        let relative_path = file_path.strip_prefix(path).unwrap_or(file_path);
+    /// Findings for the secrets in a file, see [`SecretScanner::scan_file`]
+    pub fn findings(&self, file: &str, content: &str) -> Vec<SecretFinding> {
+        self.scan_file(file, content)
+            .iter()
+            .map(|secret| secret.to_finding(file, content))
+            .collect()
+    }
 
-        // Skip binary files
-        if is_likely_binary(file_path) {
-            continue;
+    /// Fingerprints of the secrets in a file that are left out because of a
+    /// `remix:allow-secret` comment or the allowlist, and not found anywhere
+    /// else in the file.
+    ///
+    /// Comment removal and compression can drop the pragma from the packed
+    /// content, so whether a packed secret is allowed is decided here, from
+    /// the file as it is on disk.
+    pub fn suppressed_fingerprints(&self, file: &str, content: &str) -> HashSet<String> {
+        let mut suppressed = HashSet::new();
+        let mut reported = HashSet::new();
+        for (secret, allowed) in self.candidates(content) {
+            let fingerprint = secret.fingerprint(file, content);
+            if allowed || self.allowlist.fingerprints.contains(&fingerprint) {
+                suppressed.insert(fingerprint);
+            } else {
+                reported.insert(fingerprint);
+            }
         }
+        suppressed.retain(|fingerprint| !reported.contains(fingerprint));
+        suppressed
+    }
+
+    /// Find all secrets in `content`, in order of appearance.
+    ///
+    /// Where several rules match overlapping text, the rule listed first wins.
+    /// Secrets matching an allowlist pattern, or on a line containing
+    /// `remix:allow-secret`, are left out.
+    pub fn scan(&self, content: &str) -> Vec<SecretMatch> {
+        self.candidates(content)
+            .into_iter()
+            .filter(|(_, allowed)| !allowed)
+            .map(|(secret, _)| secret)
+            .collect()
+    }
+
+    /// Every secret in `content`, and whether it is allowed by a
+    /// `remix:allow-secret` comment or an allowlist pattern
+    fn candidates(&self, content: &str) -> Vec<(SecretMatch, bool)> {
+        let lowercase = content.to_lowercase();
+        let mut candidates: Vec<(usize, usize, usize)> = Vec::new();
+
+        for (index, rule) in self.rules.iter().enumerate() {
+            if !rule.keywords.is_empty() && !rule.keywords.iter().any(|k| lowercase.contains(k)) {
+                continue;
+            }
+
+            for captures in rule.regex.captures_iter(content) {
+                let secret = match captures.iter().skip(1).flatten().next() {
+                    Some(group) => group,
+                    None => captures.get(0).expect("group 0 always participates"),
+                };
+
+                if let Some(min_entropy) = rule.min_entropy {
+                    if looks_like_placeholder(secret.as_str())
+                        || shannon_entropy(secret.as_str()) < min_entropy
+                    {
+                        continue;
+                    }
+                }
 
-        // Check filename for sensitive patterns
-        let filename = relative_path.to_string_lossy().to_lowercase();
-        if filename.contains("secret")
-            || filename.contains("password")
-            || filename.contains("credential")
-            || filename.contains("token")
-            || filename.contains("key")
-            || filename.contains("auth")
-            || filename.contains(".env")
-            || filename.contains("config")
-        {
-            suspicious_files.insert(relative_path.to_string_lossy().to_string());
-            continue;
+                candidates.push((secret.start(), index, secret.end()));
+            }
         }
 
-        // For text files, check content for sensitive patterns
-        if let Ok(content) = fs::read_to_string(file_path) {
-            let content_lower = content.to_lowercase();
-            for keyword in &sensitive_keywords {
-                if content_lower.contains(keyword) {
-                    suspicious_files.insert(relative_path.to_string_lossy().to_string());
-                    break;
+        // Earliest secret first; for secrets starting at the same place, the rule listed first
+        candidates.sort();
+
+        let lines = LineIndex::new(content);
+        let mut matches = Vec::new();
+        let mut covered_until = 0;
+
+        for (start, index, end) in candidates {
+            if start < covered_until {
+                continue;
+            }
+            covered_until = end;
+
+            let (line, column) = lines.position(start);
+            let allowed = lines.line_text(line).contains(ALLOW_PRAGMA)
+                || self.allowlist.allows_secret(&content[start..end]);
+
+            let secret = SecretMatch {
+                rule_id: self.rules[index].id.clone(),
+                start,
+                end,
+                line,
+                column,
+            };
+            matches.push((secret, allowed));
+        }
+
+        matches
+    }
+}
+
+/// Maps byte offsets to 1-based line and column numbers
+struct LineIndex<'a> {
+    content: &'a str,
+    line_starts: Vec<usize>,
+}
+
+impl<'a> LineIndex<'a> {
+    fn new(content: &'a str) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(content.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+
+        Self {
+            content,
+            line_starts,
+        }
+    }
+
+    fn position(&self, offset: usize) -> (usize, usize) {
+        let line = self.line_starts.partition_point(|&start| start <= offset);
+        let column = self.content[self.line_starts[line - 1]..offset]
+            .chars()
+            .count()
+            + 1;
+        (line, column)
+    }
+
+    /// Text of a 1-based line, without its line break
+    fn line_text(&self, line: usize) -> &'a str {
+        let start = self.line_starts[line - 1];
+        let end = self
+            .line_starts
+            .get(line)
+            .map_or(self.content.len(), |&next| next - 1);
+        &self.content[start..end]
+    }
+}
+
+/// Shannon entropy of a string, in bits per character
+pub fn shannon_entropy(value: &str) -> f64 {
+    let mut counts: HashMap<char, usize> = HashMap::new();
+    for c in value.chars() {
+        *counts.entry(c).or_default() += 1;
+    }
+
+    let length = value.chars().count() as f64;
+    counts
+        .values()
+        .map(|&count| {
+            let p = count as f64 / length;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+/// Values that are references or placeholders rather than secrets
+fn looks_like_placeholder(value: &str) -> bool {
+    let lower = value.to_lowercase();
+
+    value.starts_with(['$', '%', '<', '{', '@'])
+        || value.contains("${")
+        || value.contains("{{")
+        // Environment variable names and plain identifiers
+        || value.chars().all(|c| c.is_ascii_uppercase() || c == '_')
+        || value.chars().all(|c| c.is_ascii_lowercase() || c == '_' || c == '-')
+        // Member access such as `config.api_key` or `process.env.TOKEN`
+        || value.split('.').count() > 1
+            && value.split('.').all(|part| {
+                part.chars().next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+                    && part.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+            })
+        || ["example", "changeme", "placeholder", "your_", "your-", "xxxx", "redacted", "dummy"]
+            .iter()
+            .any(|word| lower.contains(word))
+}
+
+/// Mask a secret, leaving only its first few characters visible
+pub fn mask_secret(secret: &str) -> String {
+    let visible: String = secret
+        .chars()
+        .take(MASK_VISIBLE_CHARS.min(secret.chars().count() / 4))
+        .collect();
+    format!("{}********", visible)
+}
+
+/// The path that findings in a file are reported at: `relative_path` within
+/// the scanned directory, which is at `prefix` in the working copy
+pub fn finding_path(prefix: &Path, relative_path: &Path) -> String {
+    prefix
+        .join(relative_path)
+        .to_string_lossy()
+        .replace('\\', "/")
+}
+
+/// Runs the secret rules over every text file in `files` as it is in `source`
+pub fn perform_security_check<S: FileSource + ?Sized>(
+    source: &S,
+    files: &[FileInfo],
+    scanner: &SecretScanner,
+) -> Vec<SecretFinding> {
+    info!("Performing security check on {} files", files.len());
+
+    let prefix = source
+        .root()
+        .map(SecretBaseline::prefix_for)
+        .unwrap_or_default();
+    let mut findings: Vec<SecretFinding> = files
+        .par_iter()
+        .filter(|file| !file.is_binary)
+        .flat_map_iter(|file| {
+            let relative_path = finding_path(&prefix, &file.relative_path);
+            match source.read_to_string(&file.relative_path) {
+                Ok(content) => scanner.findings(&relative_path, &content),
+                Err(e) => {
+                    debug!("Not scanning {}: {}", file.path.display(), e);
+                    Vec::new()
                 }
             }
+        })
+        .collect();
+
+    findings.sort_by(|a, b| (&a.file, a.line, a.column).cmp(&(&b.file, b.line, b.column)));
+    debug!("Found {} secrets", findings.len());
+
+    findings
+}
+
+/// Replace every matched secret in `content` with `[REDACTED:<rule>]`
+pub fn redact_secrets(content: &str, matches: &[SecretMatch]) -> String {
+    let mut result = String::with_capacity(content.len());
+    let mut cursor = 0;
+
+    for secret in matches {
+        result.push_str(&content[cursor..secret.start]);
+        result.push_str(&format!("[REDACTED:{}]", secret.rule_id));
+        cursor = secret.end;
+    }
+    result.push_str(&content[cursor..]);
+
+    result
+}
+
+/// A finding recorded in a baseline
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct BaselineEntry {
+    pub fingerprint: String,
+    /// Where the secret was when the baseline was written, for reviewers
+    pub file: String,
+    pub line: usize,
+    pub rule_id: String,
+}
+
+/// Known findings that are no longer reported, so only new secrets stand out
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct SecretBaseline {
+    pub version: u32,
+    pub findings: Vec<BaselineEntry>,
+}
+
+impl SecretBaseline {
+    pub fn from_findings(findings: &[SecretFinding]) -> Self {
+        Self {
+            version: BASELINE_VERSION,
+            findings: findings
+                .iter()
+                .map(|finding| BaselineEntry {
+                    fingerprint: finding.fingerprint.clone(),
+                    file: finding.file.clone(),
+                    line: finding.line,
+                    rule_id: finding.rule_id.clone(),
+                })
+                .collect(),
         }
     }
 
-    let result: Vec<String> = suspicious_files.into_iter().collect();
-    debug!("Found {} suspicious files", result.len());
-
-    Ok(result)
-}
-
-/// Checks if a file is likely to be binary
-fn is_likely_binary(path: &Path) -> bool {
-    if let Some(ext) = path.extension() {
-        let ext_str = ext.to_string_lossy().to_lowercase();
-        // Common binary file extensions
-        return matches!(
-            ext_str.as_str(),
-            "jpg"
-                | "jpeg"
-                | "png"
-                | "gif"
-                | "bmp"
-                | "tiff"
-                | "exe"
-                | "dll"
-                | "so"
-                | "dylib"
-                | "zip"
-                | "tar"
-                | "gz"
-                | "rar"
-                | "mp3"
-                | "mp4"
-                | "avi"
-                | "mov"
-                | "pdf"
-                | "doc"
-                | "docx"
-                | "xls"
-                | "xlsx"
-                | "ppt"
-                | "pptx"
-        );
+    /// The directory whose baseline applies to `path`: the root of the git
+    /// working copy containing it, or `path` itself outside of one
+    pub fn root_for(path: &Path) -> PathBuf {
+        git2::Repository::discover(path)
+            .ok()
+            .and_then(|repo| repo.workdir().map(Path::to_path_buf))
+            .unwrap_or_else(|| path.to_path_buf())
+    }
+
+    /// Where the directory `path` is within [`SecretBaseline::root_for`].
+    /// Findings, and so their fingerprints, are relative to the root, so a
+    /// secret is the same finding whichever directory is scanned.
+    pub fn prefix_for(path: &Path) -> PathBuf {
+        let root = Self::root_for(path);
+        path.canonicalize()
+            .ok()
+            .and_then(|path| {
+                let root = root.canonicalize().ok()?;
+                path.strip_prefix(root).ok().map(Path::to_path_buf)
+            })
+            .unwrap_or_default()
+    }
+
+    /// Location of the baseline for a repository
+    pub fn path_for(repository: &Path, config: &SecurityConfig) -> PathBuf {
+        repository.join(config.baseline.as_deref().unwrap_or(BASELINE_FILENAME))
     }
 
-    false
-}
-
-/// Returns a list of sensitive keywords to look for
-fn get_sensitive_keywords() -> Vec<String> {
-    vec![
-        // API Keys and Tokens
-        "api_key".to_string(),
-        "api_token".to_string(),
-        "app_key".to_string(),
-        "app_token".to_string(),
-        "secret_key".to_string(),
-        // AWS Keys
-        "aws_access_key".to_string(),
-        "aws_secret_key".to_string(),
-        // Private Keys
-        "private key".to_string(),
-        "begin private key".to_string(),
-        // Passwords
-        "password=".to_string(),
-        "passwd=".to_string(),
-        "pwd=".to_string(),
-        // Firebase keys
-        "firebase_key".to_string(),
-        // Generic Auth Tokens
-        "auth_token".to_string(),
-        "bearer token".to_string(),
-        // Connection Strings
-        "connection_string".to_string(),
-        "mongodb://".to_string(),
-        "postgres://".to_string(),
-        "mysql://".to_string(),
-        "redis://".to_string(),
-    ]
-}
-
-/// Check if content contains sensitive information
-pub fn check_sensitive_content(content: &str) -> bool {
-    let sensitive_keywords = get_sensitive_keywords();
-    let content_lower = content.to_lowercase();
-
-    for keyword in sensitive_keywords {
-        if content_lower.contains(&keyword) {
-            return true;
+    /// Load the repository's baseline, or an empty one if it has none
+    pub fn load_for(repository: &Path, config: &SecurityConfig) -> Result<Self> {
+        let path = Self::path_for(repository, config);
+        if !path.exists() {
+            if config.baseline.is_some() {
+                return Err(anyhow!("Secrets baseline not found: {}", path.display()));
+            }
+            return Ok(Self::default());
         }
+
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read secrets baseline: {}", path.display()))?;
+        let baseline: Self = serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse secrets baseline: {}", path.display()))?;
+
+        if baseline.version != BASELINE_VERSION {
+            return Err(anyhow!(
+                "Unsupported secrets baseline version {} in {}",
+                baseline.version,
+                path.display()
+            ));
+        }
+
+        info!(
+            "Loaded secrets baseline with {} known findings from {}",
+            baseline.findings.len(),
+            path.display()
+        );
+        Ok(baseline)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let json =
+            serde_json::to_string_pretty(self).context("Failed to serialize secrets baseline")?;
+        fs::write(path, json + "\n")
+            .with_context(|| format!("Failed to write secrets baseline: {}", path.display()))
     }
 
-    false
+    /// Whether a finding was already known when the baseline was written
+    pub fn contains(&self, finding: &SecretFinding) -> bool {
+        self.findings
+            .iter()
+            .any(|entry| entry.fingerprint == finding.fingerprint)
+    }
 }
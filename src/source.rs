@@ -0,0 +1,432 @@
+use crate::archive::{ArchiveFormat, ArchiveSource};
+use crate::config::Config;
+use crate::scanner::{select_dir_files, select_files, select_tree_blobs, tree_blobs};
+use anyhow::{anyhow, bail, Context, Result};
+use git2::{Oid, Repository, Tree};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+use tree_magic_mini as tree_magic;
+use walkdir::WalkDir;
+
+/// How much of a file is sniffed for its MIME type, as much as
+/// `tree_magic::from_filepath` reads
+const MIME_PREFIX_LEN: usize = 2048;
+
+/// Size and modification time of a file in a source
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileStat {
+    pub size: u64,
+    pub modified: Option<SystemTime>,
+}
+
+/// Where the files to pack come from: a directory on disk, a git tree, an
+/// archive or files in memory.
+///
+/// Paths are relative to the root of the source. Scanning and packing only go
+/// through this trait, so other inputs can be packed by implementing it.
+pub trait FileSource: Sync {
+    /// Every regular file in the source
+    fn list(&self) -> Result<Vec<PathBuf>>;
+
+    /// The content of a file
+    fn read(&self, path: &Path) -> Result<Vec<u8>>;
+
+    /// The size and modification time of a file
+    fn stat(&self, path: &Path) -> Result<FileStat>;
+
+    /// What is packed, for messages, e.g. `archive release.tar.gz`
+    fn describe(&self) -> String;
+
+    /// The files that the ignore and include config would pack, with the
+    /// `.gitignore` and `.remixignore` files of the source applied
+    fn select(&self, config: &Config) -> Result<Vec<PathBuf>> {
+        let files = self.list()?.into_iter().map(|path| (path, ())).collect();
+        let read = |path: &Path| {
+            let data = self.read(path).ok()?;
+            Some(String::from_utf8_lossy(&data).into_owned())
+        };
+        Ok(select_files(files, read, config)
+            .into_iter()
+            .map(|(path, ())| path)
+            .collect())
+    }
+
+    /// The MIME type of a file, from its content
+    fn mime_type(&self, path: &Path) -> Result<&'static str> {
+        Ok(tree_magic::from_u8(&self.read(path)?))
+    }
+
+    /// The content of a text file
+    fn read_to_string(&self, path: &Path) -> Result<String> {
+        String::from_utf8(self.read(path)?)
+            .with_context(|| format!("File is not valid UTF-8: {}", self.path(path).display()))
+    }
+
+    /// The directory of a git working copy that the source is in, if any.
+    /// Changed files and git history are looked up there, and the secrets
+    /// baseline in the root of the working copy.
+    fn root(&self) -> Option<&Path> {
+        None
+    }
+
+    /// The path of a file as shown to the user
+    fn path(&self, relative_path: &Path) -> PathBuf {
+        relative_path.to_path_buf()
+    }
+}
+
+/// Open the source for a path: an archive file, a git ref of the repository
+/// at the path if `git.reference` is set, or the directory or file on disk
+pub fn open_path(path: &Path, config: &Config) -> Result<Box<dyn FileSource>> {
+    if let Some(format) = ArchiveFormat::from_path(path).filter(|_| path.is_file()) {
+        if config.git.reference.is_some() {
+            bail!(
+                "Git options can't be used with an archive: {}",
+                path.display()
+            );
+        }
+        return Ok(Box::new(ArchiveSource::open(path, format, config)?));
+    }
+    if let Some(reference) = &config.git.reference {
+        return Ok(Box::new(GitTreeSource::new(path, reference)?));
+    }
+    Ok(Box::new(FsSource::new(path)?))
+}
+
+/// The files under a directory on disk, or a single file
+pub struct FsSource {
+    /// The path as given
+    base: PathBuf,
+    /// The directory the files are relative to
+    dir: PathBuf,
+    /// `dir`, canonicalized
+    root: PathBuf,
+}
+
+impl FsSource {
+    pub fn new(path: &Path) -> Result<Self> {
+        let canonical = path
+            .canonicalize()
+            .with_context(|| format!("Path not found: {}", path.display()))?;
+        // A single file is relative to its directory
+        let (dir, root) = if canonical.is_file() {
+            let dir = path.parent().unwrap_or(Path::new(""));
+            let root = canonical.parent().unwrap_or(&canonical);
+            (dir.to_path_buf(), root.to_path_buf())
+        } else {
+            (path.to_path_buf(), canonical)
+        };
+        Ok(Self {
+            base: path.to_path_buf(),
+            dir,
+            root,
+        })
+    }
+
+    fn relative(&self, path: &Path) -> PathBuf {
+        path.strip_prefix(&self.dir).unwrap_or(path).to_path_buf()
+    }
+}
+
+impl FileSource for FsSource {
+    fn list(&self) -> Result<Vec<PathBuf>> {
+        Ok(WalkDir::new(&self.base)
+            .into_iter()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_type().is_file())
+            .map(|entry| self.relative(entry.path()))
+            .collect())
+    }
+
+    fn read(&self, path: &Path) -> Result<Vec<u8>> {
+        let path = self.path(path);
+        fs::read(&path).with_context(|| format!("Failed to read file: {}", path.display()))
+    }
+
+    fn stat(&self, path: &Path) -> Result<FileStat> {
+        let path = self.path(path);
+        let metadata = fs::metadata(&path)
+            .with_context(|| format!("Failed to get metadata for {}", path.display()))?;
+        Ok(FileStat {
+            size: metadata.len(),
+            modified: metadata.modified().ok(),
+        })
+    }
+
+    fn describe(&self) -> String {
+        format!("repository at {}", self.base.display())
+    }
+
+    /// Walks the directory, respecting `.gitignore` files like git does
+    fn select(&self, config: &Config) -> Result<Vec<PathBuf>> {
+        Ok(select_dir_files(&self.base, config)?
+            .iter()
+            .map(|path| self.relative(path))
+            .collect())
+    }
+
+    fn mime_type(&self, path: &Path) -> Result<&'static str> {
+        Ok(tree_magic::from_filepath(&self.path(path)).unwrap_or("application/octet-stream"))
+    }
+
+    fn root(&self) -> Option<&Path> {
+        Some(&self.root)
+    }
+
+    fn path(&self, relative_path: &Path) -> PathBuf {
+        self.dir.join(relative_path)
+    }
+}
+
+/// A commit, tag or branch of a git repository.
+///
+/// Blobs are read from the object database, so the working tree is neither
+/// read nor touched. Every file is dated with the commit time.
+pub struct GitTreeSource {
+    /// Open handles to the repository. A handle can only be used by one
+    /// thread at a time, so each parallel read takes one, opening another if
+    /// none is free, and puts it back afterwards.
+    repos: Mutex<Vec<Repository>>,
+    git_dir: PathBuf,
+    tree: Oid,
+    reference: String,
+    base: PathBuf,
+    /// The directory in the working copy, unless the repository is bare
+    root: Option<PathBuf>,
+    modified: SystemTime,
+}
+
+impl GitTreeSource {
+    /// The tree of `reference` in the git repository containing `base_path`,
+    /// limited to the directory at `base_path`
+    pub fn new(base_path: &Path, reference: &str) -> Result<Self> {
+        let repo = Repository::discover(base_path)
+            .with_context(|| format!("Not a git repository: {}", base_path.display()))?;
+
+        // Only the part of the tree under the requested directory is packed
+        let workdir = repo
+            .workdir()
+            .map(|workdir| workdir.canonicalize())
+            .transpose()
+            .context("Failed to resolve the git working directory")?;
+        let prefix = match &workdir {
+            Some(workdir) => base_path
+                .canonicalize()
+                .with_context(|| format!("Path not found: {}", base_path.display()))?
+                .strip_prefix(workdir)
+                .map(Path::to_path_buf)
+                .unwrap_or_default(),
+            None => PathBuf::new(),
+        };
+        let (tree, modified) = {
+            let commit = repo
+                .revparse_single(reference)
+                .and_then(|object| object.peel_to_commit())
+                .with_context(|| format!("Unknown git ref: {}", reference))?;
+            let tree = commit.tree().context("Failed to read the commit tree")?;
+            let tree = if prefix.as_os_str().is_empty() {
+                tree.id()
+            } else {
+                tree.get_path(&prefix)
+                    .and_then(|entry| entry.to_object(&repo))
+                    .and_then(|object| object.peel_to_tree())
+                    .map_err(|_| {
+                        anyhow!("{} is not a directory at {}", prefix.display(), reference)
+                    })?
+                    .id()
+            };
+            let seconds = commit.time().seconds().max(0) as u64;
+            (tree, SystemTime::UNIX_EPOCH + Duration::from_secs(seconds))
+        };
+
+        Ok(Self {
+            git_dir: repo.path().to_path_buf(),
+            tree,
+            reference: reference.to_string(),
+            base: base_path.to_path_buf(),
+            root: workdir.map(|workdir| workdir.join(&prefix)),
+            modified,
+            repos: Mutex::new(vec![repo]),
+        })
+    }
+
+    fn with_tree<T>(&self, f: impl FnOnce(&Repository, &Tree) -> Result<T>) -> Result<T> {
+        let free = self.repos.lock().ok().and_then(|mut repos| repos.pop());
+        let repo = match free {
+            Some(repo) => repo,
+            None => Repository::open(&self.git_dir).with_context(|| {
+                format!(
+                    "Failed to open the git repository at {}",
+                    self.git_dir.display()
+                )
+            })?,
+        };
+        let result = repo
+            .find_tree(self.tree)
+            .map_err(Into::into)
+            .and_then(|tree| f(&repo, &tree));
+        if let Ok(mut repos) = self.repos.lock() {
+            repos.push(repo);
+        }
+        result
+    }
+
+    fn blob_id(&self, tree: &Tree, path: &Path) -> Result<Oid> {
+        tree.get_path(path)
+            .map(|entry| entry.id())
+            .with_context(|| format!("{} does not exist at {}", path.display(), self.reference))
+    }
+}
+
+impl FileSource for GitTreeSource {
+    fn list(&self) -> Result<Vec<PathBuf>> {
+        self.with_tree(|_, tree| {
+            let blobs = tree_blobs(tree, |_| false)?;
+            Ok(blobs.into_iter().map(|(path, _)| path).collect())
+        })
+    }
+
+    fn read(&self, path: &Path) -> Result<Vec<u8>> {
+        self.with_tree(|repo, tree| {
+            let blob = repo.find_blob(self.blob_id(tree, path)?).with_context(|| {
+                format!("Failed to read {} at {}", path.display(), self.reference)
+            })?;
+            Ok(blob.content().to_vec())
+        })
+    }
+
+    /// Reads only the blob's header, so large blobs can be skipped cheaply
+    fn stat(&self, path: &Path) -> Result<FileStat> {
+        self.with_tree(|repo, tree| {
+            let (size, _) = repo.odb()?.read_header(self.blob_id(tree, path)?)?;
+            Ok(FileStat {
+                size: size as u64,
+                modified: Some(self.modified),
+            })
+        })
+    }
+
+    /// Sniffs the first bytes of the blob, like `FsSource` does for files.
+    /// Loose objects are streamed; packed ones can't be, so they are inflated
+    /// but not copied.
+    fn mime_type(&self, path: &Path) -> Result<&'static str> {
+        self.with_tree(|repo, tree| {
+            let id = self.blob_id(tree, path)?;
+            let mut prefix = Vec::with_capacity(MIME_PREFIX_LEN);
+            let streamed = repo.odb()?.reader(id).ok().and_then(|(reader, _, _)| {
+                reader
+                    .take(MIME_PREFIX_LEN as u64)
+                    .read_to_end(&mut prefix)
+                    .ok()
+            });
+            if streamed.is_some() {
+                return Ok(tree_magic::from_u8(&prefix));
+            }
+            let blob = repo.find_blob(id).with_context(|| {
+                format!("Failed to read {} at {}", path.display(), self.reference)
+            })?;
+            let content = blob.content();
+            Ok(tree_magic::from_u8(
+                &content[..content.len().min(MIME_PREFIX_LEN)],
+            ))
+        })
+    }
+
+    fn describe(&self) -> String {
+        format!(
+            "{} of the repository at {}",
+            self.reference,
+            self.base.display()
+        )
+    }
+
+    fn select(&self, config: &Config) -> Result<Vec<PathBuf>> {
+        self.with_tree(|repo, tree| {
+            let blobs = select_tree_blobs(repo, tree, config)?;
+            Ok(blobs.into_iter().map(|(path, _)| path).collect())
+        })
+    }
+
+    fn root(&self) -> Option<&Path> {
+        self.root.as_deref()
+    }
+
+    fn path(&self, relative_path: &Path) -> PathBuf {
+        self.base.join(relative_path)
+    }
+}
+
+/// Files held in memory, such as the entries of an archive or files that a
+/// service received over the network
+#[derive(Debug, Clone, Default)]
+pub struct MemorySource {
+    files: BTreeMap<PathBuf, MemoryFile>,
+}
+
+#[derive(Debug, Clone)]
+struct MemoryFile {
+    data: Vec<u8>,
+    modified: Option<SystemTime>,
+}
+
+impl MemorySource {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a file, replacing any file at the same path
+    pub fn insert(
+        &mut self,
+        path: impl Into<PathBuf>,
+        data: impl Into<Vec<u8>>,
+        modified: Option<SystemTime>,
+    ) {
+        let file = MemoryFile {
+            data: data.into(),
+            modified,
+        };
+        self.files.insert(path.into(), file);
+    }
+
+    fn get(&self, path: &Path) -> Result<&MemoryFile> {
+        self.files
+            .get(path)
+            .ok_or_else(|| anyhow!("File not found: {}", path.display()))
+    }
+}
+
+impl<P: Into<PathBuf>, D: Into<Vec<u8>>> FromIterator<(P, D)> for MemorySource {
+    fn from_iter<I: IntoIterator<Item = (P, D)>>(files: I) -> Self {
+        let mut source = Self::new();
+        for (path, data) in files {
+            source.insert(path, data, None);
+        }
+        source
+    }
+}
+
+impl FileSource for MemorySource {
+    fn list(&self) -> Result<Vec<PathBuf>> {
+        Ok(self.files.keys().cloned().collect())
+    }
+
+    fn read(&self, path: &Path) -> Result<Vec<u8>> {
+        Ok(self.get(path)?.data.clone())
+    }
+
+    fn stat(&self, path: &Path) -> Result<FileStat> {
+        let file = self.get(path)?;
+        Ok(FileStat {
+            size: file.data.len() as u64,
+            modified: file.modified,
+        })
+    }
+
+    fn describe(&self) -> String {
+        format!("{} files in memory", self.files.len())
+    }
+}
@@ -0,0 +1,289 @@
+use crate::formatter::{
+    markdown_files, markdown_preamble, text_file, text_preamble, xml_directory_structure,
+    xml_files, xml_instruction, xml_preamble,
+};
+use crate::packer::{FileContent, PackedRepository};
+use crate::tokenizer::Tokenizer;
+use anyhow::{anyhow, Context, Result};
+use log::{info, warn};
+use serde_json::json;
+use std::path::Path;
+
+/// Maximum size of a single output part
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SplitLimit {
+    Bytes(u64),
+    Tokens(usize),
+}
+
+impl SplitLimit {
+    /// Parse a limit such as `500kb`, `2mb`, `1000000` (bytes), `100k-tokens` or `50000tokens`
+    pub fn parse(spec: &str) -> Result<Self> {
+        let spec = spec.trim().to_lowercase().replace([' ', '_'], "");
+
+        let (number, is_tokens) = if let Some(number) = spec
+            .strip_suffix("-tokens")
+            .or_else(|| spec.strip_suffix("tokens"))
+            .or_else(|| spec.strip_suffix("tok"))
+        {
+            (number.to_string(), true)
+        } else {
+            (spec.clone(), false)
+        };
+
+        let (digits, multiplier) = if is_tokens {
+            match number.strip_suffix('k') {
+                Some(digits) => (digits, 1_000),
+                None => match number.strip_suffix('m') {
+                    Some(digits) => (digits, 1_000_000),
+                    None => (number.as_str(), 1),
+                },
+            }
+        } else if let Some(digits) = number.strip_suffix("kb") {
+            (digits, 1024)
+        } else if let Some(digits) = number.strip_suffix("mb") {
+            (digits, 1024 * 1024)
+        } else if let Some(digits) = number.strip_suffix('b') {
+            (digits, 1)
+        } else {
+            (number.as_str(), 1)
+        };
+
+        let value: u64 = digits
+            .parse()
+            .map_err(|_| anyhow!("Invalid split limit: {}", spec))?;
+        let value = value
+            .checked_mul(multiplier)
+            .ok_or_else(|| anyhow!("Split limit is too large: {}", spec))?;
+
+        if value == 0 {
+            return Err(anyhow!("Split limit must be greater than zero"));
+        }
+
+        Ok(if is_tokens {
+            Self::Tokens(value as usize)
+        } else {
+            Self::Bytes(value)
+        })
+    }
+}
+
+/// Path of the k-th part: `remix-output.md` becomes `remix-output.part1.md`
+pub fn part_path(output_path: &str, part: usize) -> String {
+    let path = Path::new(output_path);
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| "remix-output".to_string());
+
+    let file_name = match path.extension() {
+        Some(ext) => format!("{}.part{}.{}", stem, part, ext.to_string_lossy()),
+        None => format!("{}.part{}", stem, part),
+    };
+
+    path.with_file_name(file_name).to_string_lossy().to_string()
+}
+
+/// Splits a packed repository into numbered parts at file boundaries
+struct Splitter<'a> {
+    repo: &'a PackedRepository,
+    format: &'a str,
+    tree: Option<String>,
+    indented_tree: Option<String>,
+    measure: Box<dyn Fn(&str) -> usize + 'a>,
+}
+
+impl<'a> Splitter<'a> {
+    fn new(repo: &'a PackedRepository, format: &'a str, limit: SplitLimit) -> Result<Self> {
+        let measure: Box<dyn Fn(&str) -> usize> = match limit {
+            SplitLimit::Bytes(_) => Box::new(|text: &str| text.len()),
+            SplitLimit::Tokens(_) => {
+                let tokenizer = Tokenizer::from_name(&repo.summary.token_encoding)?;
+                Box::new(move |text: &str| tokenizer.count(text))
+            }
+        };
+
+        Ok(Self {
+            repo,
+            format,
+            tree: repo.directory_tree.as_ref().map(|tree| tree.render()),
+            indented_tree: repo
+                .directory_tree
+                .as_ref()
+                .map(|tree| tree.render_indented()),
+            measure,
+        })
+    }
+
+    /// Render a single part
+    fn render(&self, part: usize, total: usize, files: &[&FileContent]) -> Result<String> {
+        let first = part == 1;
+
+        match self.format {
+            "json" | "toon" => {
+                let mut value = if first {
+                    serde_json::to_value(self.repo)
+                        .context("Failed to convert repository to JSON value")?
+                } else {
+                    json!({})
+                };
+
+                value["part"] = json!(part);
+                value["total_parts"] = json!(total);
+                value["directory_tree"] = json!(self.tree);
+                value["files"] =
+                    serde_json::to_value(files).context("Failed to convert files to JSON value")?;
+
+                if self.format == "toon" {
+                    rtoon::encode_default(&value).context("Failed to encode part to TOON")
+                } else {
+                    serde_json::to_string_pretty(&value).context("Failed to serialize part to JSON")
+                }
+            }
+            "xml" => {
+                let mut output = format!(
+                    "This is part {} of {} of the packed repository.",
+                    part, total
+                );
+                if !first {
+                    output.push_str(" It continues the repository packed in part 1.");
+                }
+                output.push_str("\n\n");
+
+                if first {
+                    output.push_str(&xml_preamble(self.repo));
+                }
+
+                if let Some(tree) = &self.indented_tree {
+                    output.push_str(&xml_directory_structure(tree));
+                }
+                output.push_str(&xml_files(files));
+
+                if part == total {
+                    output.push_str(&xml_instruction(self.repo));
+                }
+
+                Ok(output)
+            }
+            "txt" | "text" => {
+                let mut output = format!("PART {} OF {}\n\n", part, total);
+                if first {
+                    // The preamble already carries the directory tree
+                    output.push_str(&text_preamble(self.repo));
+                } else {
+                    output.push_str("This part continues the repository packed in part 1.\n");
+                    if let Some(tree) = &self.tree {
+                        output.push_str("\nDIRECTORY STRUCTURE:\n\n");
+                        output.push_str(tree);
+                    }
+                }
+
+                output.push_str("\nFILES:\n\n");
+
+                for file in files {
+                    output.push_str(&text_file(file));
+                }
+
+                Ok(output)
+            }
+            _ => {
+                let mut output = format!("> **Part {} of {}**", part, total);
+                if !first {
+                    output.push_str(" - continues the repository packed in part 1");
+                }
+                output.push_str("\n\n");
+
+                if first {
+                    // The preamble already carries the directory tree
+                    output.push_str(&markdown_preamble(self.repo));
+                } else if let Some(tree) = &self.tree {
+                    output.push_str("# Directory Structure\n\n```\n");
+                    output.push_str(tree);
+                    output.push_str("```\n");
+                }
+
+                output.push_str("\n# Files\n\n");
+                output.push_str(&markdown_files(files));
+
+                Ok(output)
+            }
+        }
+    }
+
+    /// Size of a single file's section in the output
+    fn file_cost(&self, file: &FileContent) -> Result<usize> {
+        let rendered = match self.format {
+            "json" | "toon" => {
+                serde_json::to_string_pretty(file).context("Failed to serialize file to JSON")?
+            }
+            "txt" | "text" => text_file(file),
+            "xml" => xml_files(&[file]),
+            _ => markdown_files(&[file]),
+        };
+        Ok((self.measure)(&rendered))
+    }
+
+    /// Group files into parts so that every part stays under the limit where possible
+    fn group(&self, limit: usize) -> Result<Vec<Vec<&'a FileContent>>> {
+        // Measure the fixed overhead with a generous part count placeholder
+        let first_overhead = (self.measure)(&self.render(1, 999, &[])?);
+        let other_overhead = (self.measure)(&self.render(2, 999, &[])?);
+
+        let mut parts: Vec<Vec<&FileContent>> = vec![Vec::new()];
+        let mut used = first_overhead;
+
+        for file in &self.repo.files {
+            let cost = self.file_cost(file)?;
+            let current = parts.last().expect("there is always a current part");
+
+            if !current.is_empty() && used + cost > limit {
+                parts.push(Vec::new());
+                used = other_overhead;
+            }
+
+            if used + cost > limit {
+                warn!(
+                    "{} does not fit into a single part on its own",
+                    file.relative_path
+                );
+            }
+
+            parts
+                .last_mut()
+                .expect("there is always a current part")
+                .push(file);
+            used += cost;
+        }
+
+        Ok(parts)
+    }
+}
+
+/// Render the packed repository as numbered parts that each stay under `limit`.
+///
+/// Parts are split at file boundaries. Every part starts with a "part k of n" header
+/// and the directory tree, if enabled; the first part also holds the summary and
+/// security report.
+pub fn split_output(
+    repo: &PackedRepository,
+    format: &str,
+    limit: SplitLimit,
+) -> Result<Vec<String>> {
+    let splitter = Splitter::new(repo, format, limit)?;
+
+    let max = match limit {
+        SplitLimit::Bytes(bytes) => bytes as usize,
+        SplitLimit::Tokens(tokens) => tokens,
+    };
+
+    let groups = splitter.group(max)?;
+    let total = groups.len();
+
+    info!("Splitting output into {} parts", total);
+
+    groups
+        .iter()
+        .enumerate()
+        .map(|(i, files)| splitter.render(i + 1, total, files))
+        .collect()
+}
@@ -0,0 +1,92 @@
+use tree_sitter::{Language, Parser, Tree};
+
+/// Languages with a bundled tree-sitter grammar
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyntaxLanguage {
+    Rust,
+    Python,
+    JavaScript,
+    TypeScript,
+    Tsx,
+    Go,
+    Java,
+    C,
+    Cpp,
+    CSharp,
+    Ruby,
+    Php,
+    Bash,
+    Css,
+    Html,
+    Xml,
+    Yaml,
+    Swift,
+    Kotlin,
+}
+
+impl SyntaxLanguage {
+    /// Grammar for a file extension, if one is bundled
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension.to_lowercase().as_str() {
+            "rs" => Some(Self::Rust),
+            "py" | "pyi" => Some(Self::Python),
+            "js" | "jsx" | "mjs" | "cjs" => Some(Self::JavaScript),
+            "ts" | "mts" | "cts" => Some(Self::TypeScript),
+            "tsx" => Some(Self::Tsx),
+            "go" => Some(Self::Go),
+            "java" => Some(Self::Java),
+            "c" => Some(Self::C),
+            // C++ is close enough to a superset of C to parse either kind of header
+            "cpp" | "cc" | "cxx" | "h" | "hpp" | "hh" | "hxx" => Some(Self::Cpp),
+            "cs" => Some(Self::CSharp),
+            "rb" => Some(Self::Ruby),
+            "php" => Some(Self::Php),
+            "sh" | "bash" => Some(Self::Bash),
+            "css" => Some(Self::Css),
+            "html" | "htm" => Some(Self::Html),
+            "xml" | "svg" => Some(Self::Xml),
+            "yaml" | "yml" => Some(Self::Yaml),
+            "swift" => Some(Self::Swift),
+            "kt" | "kts" => Some(Self::Kotlin),
+            _ => None,
+        }
+    }
+
+    fn language(&self) -> Language {
+        match self {
+            Self::Rust => tree_sitter_rust::LANGUAGE.into(),
+            Self::Python => tree_sitter_python::LANGUAGE.into(),
+            Self::JavaScript => tree_sitter_javascript::LANGUAGE.into(),
+            Self::TypeScript => tree_sitter_typescript::LANGUAGE_TYPESCRIPT.into(),
+            Self::Tsx => tree_sitter_typescript::LANGUAGE_TSX.into(),
+            Self::Go => tree_sitter_go::LANGUAGE.into(),
+            Self::Java => tree_sitter_java::LANGUAGE.into(),
+            Self::C => tree_sitter_c::LANGUAGE.into(),
+            Self::Cpp => tree_sitter_cpp::LANGUAGE.into(),
+            Self::CSharp => tree_sitter_c_sharp::LANGUAGE.into(),
+            Self::Ruby => tree_sitter_ruby::LANGUAGE.into(),
+            Self::Php => tree_sitter_php::LANGUAGE_PHP.into(),
+            Self::Bash => tree_sitter_bash::LANGUAGE.into(),
+            Self::Css => tree_sitter_css::LANGUAGE.into(),
+            Self::Html => tree_sitter_html::LANGUAGE.into(),
+            Self::Xml => tree_sitter_xml::LANGUAGE_XML.into(),
+            Self::Yaml => tree_sitter_yaml::LANGUAGE.into(),
+            Self::Swift => tree_sitter_swift::LANGUAGE.into(),
+            Self::Kotlin => tree_sitter_kotlin_ng::LANGUAGE.into(),
+        }
+    }
+
+    /// Parse source code, returning `None` if the grammar can't be loaded or the
+    /// source contains syntax errors
+    pub fn parse(&self, content: &str) -> Option<Tree> {
+        let mut parser = Parser::new();
+        parser.set_language(&self.language()).ok()?;
+
+        let tree = parser.parse(content, None)?;
+        if tree.root_node().has_error() {
+            return None;
+        }
+
+        Some(tree)
+    }
+}
@@ -0,0 +1,180 @@
+use serde::{Serialize, Serializer};
+use std::collections::BTreeMap;
+
+/// A file shown in the directory tree, with an optional marker such as "binary"
+#[derive(Debug, Clone)]
+pub struct TreeEntry {
+    pub path: String,
+    pub marker: Option<String>,
+}
+
+impl TreeEntry {
+    pub fn new(path: &str) -> Self {
+        Self {
+            path: path.to_string(),
+            marker: None,
+        }
+    }
+
+    pub fn marked(path: &str, marker: &str) -> Self {
+        Self {
+            path: path.to_string(),
+            marker: Some(marker.to_string()),
+        }
+    }
+
+    fn label(&self, name: &str) -> String {
+        match &self.marker {
+            Some(marker) => format!("{} [{}]", name, marker),
+            None => name.to_string(),
+        }
+    }
+}
+
+/// A directory in the file tree
+#[derive(Default)]
+struct TreeNode {
+    directories: BTreeMap<String, TreeNode>,
+    files: Vec<(String, String)>,
+}
+
+impl TreeNode {
+    fn build(entries: &[TreeEntry]) -> Self {
+        let mut root = Self::default();
+        for entry in entries {
+            root.insert(entry);
+        }
+        root
+    }
+
+    fn insert(&mut self, entry: &TreeEntry) {
+        let normalized = entry.path.replace('\\', "/");
+        let mut parts: Vec<&str> = normalized.split('/').filter(|p| !p.is_empty()).collect();
+
+        let file_name = match parts.pop() {
+            Some(name) => name,
+            None => return,
+        };
+
+        let mut node = self;
+        for dir in parts {
+            node = node.directories.entry(dir.to_string()).or_default();
+        }
+        node.files
+            .push((file_name.to_string(), entry.label(file_name)));
+    }
+
+    /// Number of files below this directory
+    fn file_count(&self) -> usize {
+        self.files.len()
+            + self
+                .directories
+                .values()
+                .map(TreeNode::file_count)
+                .sum::<usize>()
+    }
+
+    /// Sorted file labels of this directory
+    fn sorted_files(&self) -> Vec<&str> {
+        let mut files: Vec<&(String, String)> = self.files.iter().collect();
+        files.sort();
+        files.into_iter().map(|(_, label)| label.as_str()).collect()
+    }
+
+    fn render(&self, prefix: &str, depth: usize, max_depth: Option<usize>, output: &mut String) {
+        let files = self.sorted_files();
+        let total = self.directories.len() + files.len();
+        let mut index = 0;
+
+        // Directories first, then files, both alphabetically
+        for (name, child) in &self.directories {
+            index += 1;
+            let last = index == total;
+            output.push_str(prefix);
+            output.push_str(if last { "└── " } else { "├── " });
+            output.push_str(name);
+            output.push('/');
+
+            if max_depth.is_some_and(|max| depth + 1 >= max) {
+                output.push_str(&format!(" ({} files)\n", child.file_count()));
+                continue;
+            }
+            output.push('\n');
+
+            let child_prefix = format!("{}{}", prefix, if last { "    " } else { "│   " });
+            child.render(&child_prefix, depth + 1, max_depth, output);
+        }
+
+        for label in files {
+            index += 1;
+            output.push_str(prefix);
+            output.push_str(if index == total {
+                "└── "
+            } else {
+                "├── "
+            });
+            output.push_str(label);
+            output.push('\n');
+        }
+    }
+
+    fn render_indented(&self, depth: usize, max_depth: Option<usize>, output: &mut String) {
+        let indent = "  ".repeat(depth);
+
+        for (name, child) in &self.directories {
+            if max_depth.is_some_and(|max| depth + 1 >= max) {
+                output.push_str(&format!(
+                    "{}{}/ ({} files)\n",
+                    indent,
+                    name,
+                    child.file_count()
+                ));
+                continue;
+            }
+
+            output.push_str(&format!("{}{}/\n", indent, name));
+            child.render_indented(depth + 1, max_depth, output);
+        }
+
+        for label in self.sorted_files() {
+            output.push_str(&format!("{}{}\n", indent, label));
+        }
+    }
+}
+
+/// Directory tree of the packed repository.
+///
+/// Serializes as its ASCII rendering so JSON and TOON output carry the same view
+/// as the markdown and text formats.
+#[derive(Debug, Clone)]
+pub struct DirectoryTree {
+    entries: Vec<TreeEntry>,
+    max_depth: Option<usize>,
+}
+
+impl DirectoryTree {
+    /// Create a tree; directories at `max_depth` are collapsed into a file count
+    pub fn new(entries: Vec<TreeEntry>, max_depth: Option<usize>) -> Self {
+        Self { entries, max_depth }
+    }
+
+    /// ASCII tree using box-drawing characters
+    pub fn render(&self) -> String {
+        let mut output = String::from(".\n");
+        TreeNode::build(&self.entries).render("", 0, self.max_depth, &mut output);
+        output
+    }
+
+    /// Indented listing, two spaces per level, as used by repomix
+    pub fn render_indented(&self) -> String {
+        let mut output = String::new();
+        TreeNode::build(&self.entries).render_indented(0, self.max_depth, &mut output);
+        output
+    }
+}
+
+impl Serialize for DirectoryTree {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.render())
+    }
+}
@@ -0,0 +1,163 @@
+use remix::budget::{apply_token_budget, OmissionKind};
+use remix::config::BudgetConfig;
+use remix::packer::FileContent;
+use remix::tokenizer::{TokenEncoding, Tokenizer};
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime};
+
+fn file(path: &str, content: &str, tokenizer: &Tokenizer) -> FileContent {
+    FileContent {
+        relative_path: path.to_string(),
+        extension: path.rsplit('.').next().unwrap_or("").to_string(),
+        content: content.to_string(),
+        size: content.len() as u64,
+        is_binary: false,
+        token_count: tokenizer.count(content),
+        diff: None,
+        history: None,
+    }
+}
+
+fn budget(max_tokens: Option<usize>, strategy: &str) -> BudgetConfig {
+    BudgetConfig {
+        max_tokens,
+        strategy: strategy.to_string(),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_no_budget_keeps_everything() {
+    let tokenizer = Tokenizer::new(TokenEncoding::Heuristic).unwrap();
+    let files = vec![
+        file("src/a.rs", &"let a = 1;\n".repeat(100), &tokenizer),
+        file("src/b.rs", "let b = 2;\n", &tokenizer),
+    ];
+
+    let (kept, omitted) = apply_token_budget(
+        files,
+        &HashMap::new(),
+        &budget(None, "size"),
+        false,
+        &tokenizer,
+    )
+    .unwrap();
+
+    assert_eq!(kept.len(), 2);
+    assert!(omitted.is_empty());
+}
+
+#[test]
+fn test_readme_and_manifest_packed_first() {
+    let tokenizer = Tokenizer::new(TokenEncoding::Heuristic).unwrap();
+    let body = "x\n".repeat(40);
+    let files = vec![
+        file("src/util.rs", &body, &tokenizer),
+        file("README.md", &body, &tokenizer),
+        file("Cargo.toml", &body, &tokenizer),
+    ];
+    let per_file = files[0].token_count + 30;
+
+    let (kept, omitted) = apply_token_budget(
+        files,
+        &HashMap::new(),
+        &budget(Some(per_file * 2), "size"),
+        true,
+        &tokenizer,
+    )
+    .unwrap();
+
+    let kept_paths: Vec<&str> = kept.iter().map(|f| f.relative_path.as_str()).collect();
+    assert_eq!(kept_paths, vec!["README.md", "Cargo.toml"]);
+    assert_eq!(omitted.len(), 1);
+    assert_eq!(omitted[0].relative_path, "src/util.rs");
+    assert_eq!(omitted[0].kind, OmissionKind::Dropped);
+}
+
+#[test]
+fn test_large_file_is_truncated() {
+    let tokenizer = Tokenizer::new(TokenEncoding::Heuristic).unwrap();
+    let content: String = (0..500).map(|i| format!("value_{} = {}\n", i, i)).collect();
+    let files = vec![file("data.txt", &content, &tokenizer)];
+    let original_tokens = files[0].token_count;
+
+    let (kept, omitted) = apply_token_budget(
+        files,
+        &HashMap::new(),
+        &budget(Some(500), "size"),
+        true,
+        &tokenizer,
+    )
+    .unwrap();
+
+    assert_eq!(kept.len(), 1);
+    assert!(kept[0].content.contains("[truncated by remix:"));
+    assert!(kept[0].token_count < original_tokens);
+    assert_eq!(omitted[0].kind, OmissionKind::Truncated);
+    assert_eq!(omitted[0].kept_tokens, kept[0].token_count);
+    assert!(kept.iter().map(|f| f.token_count).sum::<usize>() <= 500);
+}
+
+#[test]
+fn test_recency_strategy_prefers_recent_files() {
+    let tokenizer = Tokenizer::new(TokenEncoding::Heuristic).unwrap();
+    let body = "y\n".repeat(40);
+    let files = vec![
+        file("src/old.rs", &body, &tokenizer),
+        file("src/new.rs", &body, &tokenizer),
+    ];
+    let per_file = files[0].token_count + 30;
+
+    let now = SystemTime::now();
+    let modified_times = HashMap::from([
+        ("src/old.rs".to_string(), now - Duration::from_secs(3600)),
+        ("src/new.rs".to_string(), now),
+    ]);
+
+    let (kept, omitted) = apply_token_budget(
+        files,
+        &modified_times,
+        &budget(Some(per_file), "recency"),
+        true,
+        &tokenizer,
+    )
+    .unwrap();
+
+    assert_eq!(kept.len(), 1);
+    assert_eq!(kept[0].relative_path, "src/new.rs");
+    assert_eq!(omitted[0].relative_path, "src/old.rs");
+}
+
+#[test]
+fn test_priority_patterns_win() {
+    let tokenizer = Tokenizer::new(TokenEncoding::Heuristic).unwrap();
+    let body = "z\n".repeat(40);
+    let files = vec![
+        file("README.md", &body, &tokenizer),
+        file("src/core/engine.rs", &body, &tokenizer),
+    ];
+    let per_file = files[0].token_count + 30;
+
+    let config = BudgetConfig {
+        max_tokens: Some(per_file),
+        priority_patterns: vec!["src/core/**".to_string()],
+        ..Default::default()
+    };
+
+    let (kept, _) = apply_token_budget(files, &HashMap::new(), &config, true, &tokenizer).unwrap();
+
+    assert_eq!(kept[0].relative_path, "src/core/engine.rs");
+}
+
+#[test]
+fn test_unknown_strategy_is_rejected() {
+    let tokenizer = Tokenizer::new(TokenEncoding::Heuristic).unwrap();
+    let result = apply_token_budget(
+        Vec::new(),
+        &HashMap::new(),
+        &budget(Some(10), "random"),
+        false,
+        &tokenizer,
+    );
+    assert!(result.is_err());
+}
@@ -1,5 +1,6 @@
-use remix::cli::Cli;
+use remix::cli::{CacheCommand, Cli, Command, SecurityCommand};
 use clap::Parser;
+use std::path::PathBuf;
 
 #[test]
 fn test_cli_parsing() {
@@ -51,12 +52,16 @@ fn test_cli_remote_repo() {
 
     assert_eq!(cli.remote, Some("username/repo".to_string()));
     assert_eq!(cli.remote_branch, Some("main".to_string()));
+    assert_eq!(cli.clone_depth, None);
+
+    let cli = Cli::parse_from(["remix", "--remote", "username/repo", "--clone-depth", "0"]);
+    assert_eq!(cli.clone_depth, Some(0));
 }
 
 #[test]
 fn test_cli_format_validation() {
     // Test valid formats
-    for format in ["md", "markdown", "json", "txt", "text", "toon"] {
+    for format in ["md", "markdown", "json", "txt", "text", "toon", "xml"] {
         let cli = Cli::parse_from(["remix", "--format", format]);
         assert_eq!(cli.format, Some(format.to_string()));
     }
@@ -64,6 +69,94 @@ fn test_cli_format_validation() {
 
 
 
+#[test]
+fn test_cli_token_budget() {
+    let cli = Cli::parse_from([
+        "remix",
+        "--max-tokens",
+        "120000",
+        "--budget-strategy",
+        "recency",
+    ]);
+    assert_eq!(cli.max_tokens, Some(120000));
+    assert_eq!(cli.budget_strategy, Some("recency".to_string()));
+
+    assert!(Cli::try_parse_from(["remix", "--budget-strategy", "random"]).is_err());
+}
+
+#[test]
+fn test_cli_tree_options() {
+    let cli = Cli::parse_from(["remix"]);
+    assert!(!cli.no_tree);
+    assert_eq!(cli.tree_depth, None);
+    assert!(!cli.no_tree_excluded);
+
+    let cli = Cli::parse_from(["remix", "--tree-depth", "2", "--no-tree-excluded"]);
+    assert_eq!(cli.tree_depth, Some(2));
+    assert!(cli.no_tree_excluded);
+
+    let cli = Cli::parse_from(["remix", "--no-tree"]);
+    assert!(cli.no_tree);
+}
+
+#[test]
+fn test_cli_cache() {
+    let cli = Cli::parse_from(["remix", "cache", "prune", "--cache-dir", "/tmp/remix-cache"]);
+    assert!(matches!(
+        cli.command,
+        Some(Command::Cache { command: CacheCommand::Prune })
+    ));
+    assert_eq!(cli.cache_dir, Some(PathBuf::from("/tmp/remix-cache")));
+
+    let cli = Cli::parse_from(["remix", "cache", "clear"]);
+    assert!(matches!(
+        cli.command,
+        Some(Command::Cache { command: CacheCommand::Clear })
+    ));
+
+    let cli = Cli::parse_from(["remix", "--remote", "user/repo", "--no-cache"]);
+    assert!(cli.no_cache);
+    assert!(cli.command.is_none());
+}
+
+#[test]
+fn test_cli_subcommands() {
+    let cli = Cli::parse_from(["remix", "security", "baseline"]);
+    match cli.command {
+        Some(Command::Security {
+            command: SecurityCommand::Baseline { path, output },
+        }) => {
+            assert_eq!(path, None);
+            assert_eq!(output, None);
+        }
+        other => panic!("unexpected command: {:?}", other),
+    }
+
+    let cli = Cli::parse_from(["remix", "security", "baseline", "src", "-o", "base.json"]);
+    assert!(matches!(
+        cli.command,
+        Some(Command::Security {
+            command: SecurityCommand::Baseline { path: Some(_), output: Some(_) },
+        })
+    ));
+
+    let cli = Cli::parse_from(["remix", "check", "--staged", "--fail-on", "medium"]);
+    assert!(matches!(
+        cli.command,
+        Some(Command::Check { path: None, staged: true, fail_on: Some(_) })
+    ));
+    assert!(Cli::try_parse_from(["remix", "check", "--fail-on", "critical"]).is_err());
+
+    // Global options can follow the command
+    let cli = Cli::parse_from(["remix", "check", "--security-report", "secrets.sarif"]);
+    assert_eq!(cli.security_report.as_deref(), Some("secrets.sarif"));
+
+    // A plain path is still packed, not taken for a command
+    let cli = Cli::parse_from(["remix", "src/"]);
+    assert!(cli.command.is_none());
+    assert_eq!(cli.path.as_deref(), Some("src/"));
+}
+
 #[test]
 fn test_cli_edge_cases() {
     // Test empty path
@@ -145,3 +238,32 @@ fn test_cli_pattern_parsing_edge_cases() {
     assert_eq!(patterns.len(), 1);
     assert_eq!(patterns[0], "*.rs");
 }
+
+#[test]
+fn test_cli_git_changes() {
+    let cli = Cli::parse_from(["remix", "--since", "main", "--working-tree", "--with-diff"]);
+    assert_eq!(cli.since.as_deref(), Some("main"));
+    assert!(cli.working_tree);
+    assert!(cli.with_diff);
+    assert!(!cli.staged);
+
+    // The index and the working tree are alternative targets
+    assert!(Cli::try_parse_from(["remix", "--staged", "--working-tree"]).is_err());
+
+    let cli = Cli::parse_from(["remix", "--ref", "v1.2.0", "--since", "v1.1.0"]);
+    assert_eq!(cli.git_ref.as_deref(), Some("v1.2.0"));
+    assert!(Cli::try_parse_from(["remix", "--ref", "v1.2.0", "--staged"]).is_err());
+    assert!(Cli::try_parse_from(["remix", "--ref", "v1.2.0", "--remote", "user/repo"]).is_err());
+
+    let cli = Cli::parse_from([
+        "remix",
+        "--git-history",
+        "--history-commits",
+        "5",
+        "--top-contributors",
+        "3",
+    ]);
+    assert!(cli.git_history);
+    assert_eq!(cli.history_commits, Some(5));
+    assert_eq!(cli.top_contributors, Some(3));
+}
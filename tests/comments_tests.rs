@@ -1,4 +1,6 @@
-use remix::comments::remove_comments;
+use remix::comments::{
+    is_comment_removal_supported, is_unsupported_dialect, remove_comments, CommentMode,
+};
 
 #[test]
 fn test_remove_comments_rust() {
@@ -12,7 +14,7 @@ fn main() {
 
     let expected = "\n\nfn main() {\n    println!(\"Hello\"); \n    \n}\n";
 
-    assert_eq!(remove_comments(code, "rs"), expected);
+    assert_eq!(remove_comments(code, "rs", CommentMode::All), expected);
 }
 
 #[test]
@@ -29,7 +31,7 @@ def main():
 
     let expected = "\n\ndef main():\n    print(\"Hello\")  \n    \"\"\"\n    Multi-line string (not comment)\n    \"\"\"\n    pass\n";
 
-    assert_eq!(remove_comments(code, "py"), expected);
+    assert_eq!(remove_comments(code, "py", CommentMode::All), expected);
 }
 
 #[test]
@@ -45,7 +47,7 @@ function test() {
 
     let expected = "\n\nfunction test() {\n    console.log(\"Hello\"); \n    \n}\n\n";
 
-    assert_eq!(remove_comments(code, "js"), expected);
+    assert_eq!(remove_comments(code, "js", CommentMode::All), expected);
 }
 
 #[test]
@@ -61,7 +63,7 @@ int main() {
 
     let expected = "\n\nint main() {\n    printf(\"Hello\"); \n    \n}\n\n";
 
-    assert_eq!(remove_comments(code, "c"), expected);
+    assert_eq!(remove_comments(code, "c", CommentMode::All), expected);
 }
 
 #[test]
@@ -74,7 +76,7 @@ fn test_remove_comments_html() {
 
     let expected = "\n\n<div>Hello</div>\n\n";
 
-    assert_eq!(remove_comments(html, "html"), expected);
+    assert_eq!(remove_comments(html, "html", CommentMode::All), expected);
 }
 
 #[test]
@@ -89,24 +91,628 @@ fn test_remove_comments_css() {
 
     let expected = "\n\n.class {\n    color: red; \n}\n\n";
 
-    assert_eq!(remove_comments(css, "css"), expected);
+    assert_eq!(remove_comments(css, "css", CommentMode::All), expected);
 }
 
 #[test]
 fn test_remove_comments_unsupported() {
     let code = "// comment\ncode";
-    assert_eq!(remove_comments(code, "txt"), "// comment\ncode");
+    assert_eq!(remove_comments(code, "txt", CommentMode::All), "// comment\ncode");
 }
 
 #[test]
 fn test_remove_comments_edge_cases() {
     // Empty input
-    assert_eq!(remove_comments("", "rs"), "");
+    assert_eq!(remove_comments("", "rs", CommentMode::All), "");
 
     // No comments
     let code = "fn main() {}\n";
-    assert_eq!(remove_comments(code, "rs"), code);
+    assert_eq!(remove_comments(code, "rs", CommentMode::All), code);
 
     // Only comments
-    assert_eq!(remove_comments("// comment\n/* block */", "rs"), "\n");
-}
\ No newline at end of file
+    assert_eq!(remove_comments("// comment\n/* block */", "rs", CommentMode::All), "\n");
+}
+#[test]
+fn test_remove_comments_corpus_rust() {
+    let code = r##"#![allow(dead_code)]
+//! Crate docs
+/// Doc comment
+fn parse<'a>(s: &'a str) -> &'a str {
+    let raw = r#"// not a comment "quoted" /* nor this */"#;
+    let c = '"'; // quote char
+    let slash = '/';
+    /* outer /* nested */ still comment */
+    let url = "http://example.com"; /* trailing */
+    s
+}
+"##;
+
+    let expected = concat!(
+        "#![allow(dead_code)]\n",
+        "\n",
+        "\n",
+        "fn parse<'a>(s: &'a str) -> &'a str {\n",
+        "    let raw = r#\"// not a comment \"quoted\" /* nor this */\"#;\n",
+        "    let c = '\"'; \n",
+        "    let slash = '/';\n",
+        "    \n",
+        "    let url = \"http://example.com\"; \n",
+        "    s\n",
+        "}\n",
+    );
+
+    assert_eq!(remove_comments(code, "rs", CommentMode::All), expected);
+}
+
+#[test]
+fn test_remove_comments_corpus_python() {
+    let code = r##"#!/usr/bin/env python3
+# leading comment
+def f(x):
+    """Docstring with # hash"""
+    s = "# not a comment"
+    t = f"{x} # still a string"  # real comment
+    return s + t
+"##;
+
+    let expected = concat!(
+        "#!/usr/bin/env python3\n",
+        "\n",
+        "def f(x):\n",
+        "    \"\"\"Docstring with # hash\"\"\"\n",
+        "    s = \"# not a comment\"\n",
+        "    t = f\"{x} # still a string\"  \n",
+        "    return s + t\n",
+    );
+
+    assert_eq!(remove_comments(code, "py", CommentMode::All), expected);
+}
+
+#[test]
+fn test_remove_comments_corpus_javascript() {
+    let code = r#"// header
+const re = /\/\/ not a comment/g;
+const div = a / b; // divide
+const tpl = `// not a comment ${x /* inside */}`;
+const s = '/* not a comment */';
+/**
+ * JSDoc
+ */
+function f() { return re.test(s); }
+"#;
+
+    let expected = concat!(
+        "\n",
+        "const re = /\\/\\/ not a comment/g;\n",
+        "const div = a / b; \n",
+        "const tpl = `// not a comment ${x }`;\n",
+        "const s = '/* not a comment */';\n",
+        "\n",
+        "function f() { return re.test(s); }\n",
+    );
+
+    assert_eq!(remove_comments(code, "js", CommentMode::All), expected);
+}
+
+#[test]
+fn test_remove_comments_corpus_typescript() {
+    let code = r#"// header
+interface Props<T> {
+  value: T; // the value
+}
+const url: string = "https://example.com//path";
+/* block */ export const re = /a\/b/;
+"#;
+
+    let expected = concat!(
+        "\n",
+        "interface Props<T> {\n",
+        "  value: T; \n",
+        "}\n",
+        "const url: string = \"https://example.com//path\";\n",
+        " export const re = /a\\/b/;\n",
+    );
+
+    assert_eq!(remove_comments(code, "ts", CommentMode::All), expected);
+}
+
+#[test]
+fn test_remove_comments_corpus_go() {
+    let code = r#"package main
+
+// Doc comment
+func main() {
+	s := `// raw string`
+	r := '/' // rune
+	/* block */
+	fmt.Println(s, r)
+}
+"#;
+
+    let expected = concat!(
+        "package main\n",
+        "\n",
+        "\n",
+        "func main() {\n",
+        "\ts := `// raw string`\n",
+        "\tr := '/' \n",
+        "\t\n",
+        "\tfmt.Println(s, r)\n",
+        "}\n",
+    );
+
+    assert_eq!(remove_comments(code, "go", CommentMode::All), expected);
+}
+
+#[test]
+fn test_remove_comments_corpus_java() {
+    let code = r#"// header
+public class A {
+    /** Javadoc */
+    String block = """
+        // text block
+        """;
+    char c = '/'; // char
+    String s = "/* not */"; /* real */
+}
+"#;
+
+    let expected = concat!(
+        "\n",
+        "public class A {\n",
+        "    \n",
+        "    String block = \"\"\"\n",
+        "        // text block\n",
+        "        \"\"\";\n",
+        "    char c = '/'; \n",
+        "    String s = \"/* not */\"; \n",
+        "}\n",
+    );
+
+    assert_eq!(remove_comments(code, "java", CommentMode::All), expected);
+}
+
+#[test]
+fn test_remove_comments_corpus_c() {
+    let code = r#"#include <stdio.h>
+/* header */
+int main(void) {
+    char c = '\''; // quote
+    printf("/* %d */\n", 1); // print
+    return 0;
+}
+"#;
+
+    let expected = concat!(
+        "#include <stdio.h>\n",
+        "\n",
+        "int main(void) {\n",
+        "    char c = '\\''; \n",
+        "    printf(\"/* %d */\\n\", 1); \n",
+        "    return 0;\n",
+        "}\n",
+    );
+
+    assert_eq!(remove_comments(code, "c", CommentMode::All), expected);
+}
+
+#[test]
+fn test_remove_comments_corpus_cpp() {
+    let code = r#"// header
+auto raw = R"(// not /* a comment */)";
+int x = 1; /* block */
+"#;
+
+    let expected = concat!(
+        "\n",
+        "auto raw = R\"(// not /* a comment */)\";\n",
+        "int x = 1; \n",
+    );
+
+    assert_eq!(remove_comments(code, "cpp", CommentMode::All), expected);
+}
+
+#[test]
+fn test_remove_comments_corpus_csharp() {
+    let code = r#"// header
+class A {
+    string v = @"C:\path // not";
+    string i = $"{x} // not"; // real
+}
+"#;
+
+    let expected = concat!(
+        "\n",
+        "class A {\n",
+        "    string v = @\"C:\\path // not\";\n",
+        "    string i = $\"{x} // not\"; \n",
+        "}\n",
+    );
+
+    assert_eq!(remove_comments(code, "cs", CommentMode::All), expected);
+}
+
+#[test]
+fn test_remove_comments_corpus_ruby() {
+    let code = r##"# header
+=begin
+block comment
+=end
+name = "#{user} # not a comment" # real
+"##;
+
+    let expected = concat!(
+        "\n",
+        "\n",
+        "name = \"#{user} # not a comment\" \n",
+    );
+
+    assert_eq!(remove_comments(code, "rb", CommentMode::All), expected);
+}
+
+#[test]
+fn test_remove_comments_corpus_php() {
+    let code = r##"<?php
+# hash comment
+// slash comment
+$s = "# not // a comment"; /* block */
+echo $s;
+"##;
+
+    let expected = concat!(
+        "<?php\n",
+        "\n",
+        "\n",
+        "$s = \"# not // a comment\"; \n",
+        "echo $s;\n",
+    );
+
+    assert_eq!(remove_comments(code, "php", CommentMode::All), expected);
+}
+
+#[test]
+fn test_remove_comments_corpus_bash() {
+    let code = r##"#!/bin/bash
+# comment
+echo "# not a comment" '# nor this' # real
+len=${#name}
+"##;
+
+    let expected = concat!(
+        "#!/bin/bash\n",
+        "\n",
+        "echo \"# not a comment\" '# nor this' \n",
+        "len=${#name}\n",
+    );
+
+    assert_eq!(remove_comments(code, "sh", CommentMode::All), expected);
+}
+
+#[test]
+fn test_remove_comments_corpus_css() {
+    let code = r#"/* header */
+a { background: url(http://example.com/a.png); content: "/* not */"; }
+"#;
+
+    let expected = concat!(
+        "\n",
+        "a { background: url(http://example.com/a.png); content: \"/* not */\"; }\n",
+    );
+
+    assert_eq!(remove_comments(code, "css", CommentMode::All), expected);
+}
+
+#[test]
+fn test_remove_comments_corpus_html() {
+    let code = r#"<!-- header -->
+<p>a -- b</p>
+<!-- trailing -->
+"#;
+
+    let expected = concat!(
+        "\n",
+        "<p>a -- b</p>\n",
+        "\n",
+    );
+
+    assert_eq!(remove_comments(code, "html", CommentMode::All), expected);
+}
+
+#[test]
+fn test_remove_comments_corpus_xml() {
+    let code = r#"<?xml version="1.0"?>
+<!-- header -->
+<root><![CDATA[<!-- not a comment -->]]></root>
+"#;
+
+    let expected = concat!(
+        "<?xml version=\"1.0\"?>\n",
+        "\n",
+        "<root><![CDATA[<!-- not a comment -->]]></root>\n",
+    );
+
+    assert_eq!(remove_comments(code, "xml", CommentMode::All), expected);
+}
+
+#[test]
+fn test_remove_comments_corpus_yaml() {
+    let code = r#"# header
+url: "http://example.com/#anchor" # real
+tag: 'a # b'
+"#;
+
+    let expected = concat!(
+        "\n",
+        "url: \"http://example.com/#anchor\" \n",
+        "tag: 'a # b'\n",
+    );
+
+    assert_eq!(remove_comments(code, "yaml", CommentMode::All), expected);
+}
+
+#[test]
+fn test_remove_comments_corpus_swift() {
+    let code = r#"// header
+/* outer /* nested */ still comment */
+let s = "// not a comment"
+"#;
+
+    let expected = concat!(
+        "\n",
+        "\n",
+        "let s = \"// not a comment\"\n",
+    );
+
+    assert_eq!(remove_comments(code, "swift", CommentMode::All), expected);
+}
+
+#[test]
+fn test_remove_comments_corpus_kotlin() {
+    let code = r#"// header
+/* outer /* nested */ still comment */
+val s = "// not ${x} a comment" // real
+"#;
+
+    let expected = concat!(
+        "\n",
+        "\n",
+        "val s = \"// not ${x} a comment\" \n",
+    );
+
+    assert_eq!(remove_comments(code, "kt", CommentMode::All), expected);
+}
+
+#[test]
+fn test_remove_comments_unparseable_left_unchanged() {
+    // An unterminated raw string must not be "repaired" by dropping half the file
+    let code = "fn main() {\n    let s = r#\"// still inside\n}\n";
+    assert_eq!(remove_comments(code, "rs", CommentMode::All), code);
+}
+
+#[test]
+fn test_is_comment_removal_supported() {
+    for ext in ["rs", "py", "ts", "tsx", "go", "java", "kt", "swift", "yml", "xml"] {
+        assert!(is_comment_removal_supported(ext), "{} should be supported", ext);
+    }
+    assert!(!is_comment_removal_supported("txt"));
+    assert!(!is_comment_removal_supported("md"));
+}
+
+#[test]
+fn test_stylesheet_dialects_unsupported() {
+    let cases = [
+        (
+            "scss",
+            "// Theme\n$accent: red;\n.nav {\n  a { color: $accent; } /* links */\n}\n",
+        ),
+        (
+            "sass",
+            "// Theme\n$accent: red\n.nav\n  a\n    color: $accent\n",
+        ),
+        (
+            "less",
+            "// Theme\n@accent: red;\n.nav {\n  a { color: @accent; } /* links */\n}\n",
+        ),
+    ];
+    for (extension, content) in cases {
+        assert!(!is_comment_removal_supported(extension), "{}", extension);
+        assert!(is_unsupported_dialect(extension), "{}", extension);
+        // The CSS grammar would only half understand them, so nothing is removed
+        assert_eq!(
+            remove_comments(content, extension, CommentMode::All),
+            content,
+            "{}",
+            extension
+        );
+    }
+    assert!(!is_unsupported_dialect("css"));
+}
+
+#[test]
+fn test_comment_mode_from_name() {
+    assert_eq!(CommentMode::from_name("all").unwrap(), CommentMode::All);
+    assert_eq!(
+        CommentMode::from_name("keep-docs").unwrap(),
+        CommentMode::KeepDocs
+    );
+    assert_eq!(
+        CommentMode::from_name("keep_license").unwrap(),
+        CommentMode::KeepLicense
+    );
+    assert!(CommentMode::from_name("some").is_err());
+}
+
+#[test]
+fn test_keep_docs_rust() {
+    let code = r#"// Copyright 2024 Example Corp.
+//! Crate docs
+/// Adds one
+// plain
+fn add(x: i32) -> i32 {
+    /** block doc */
+    //// not a doc comment
+    x + 1 /* plain */
+}
+"#;
+
+    let expected = concat!(
+        "\n",
+        "//! Crate docs\n",
+        "/// Adds one\n",
+        "\n",
+        "fn add(x: i32) -> i32 {\n",
+        "    /** block doc */\n",
+        "    \n",
+        "    x + 1 \n",
+        "}\n",
+    );
+
+    assert_eq!(remove_comments(code, "rs", CommentMode::KeepDocs), expected);
+}
+
+#[test]
+fn test_keep_docs_javadoc_and_jsdoc() {
+    let java = "/** Javadoc */\npublic class A {\n    // plain\n    /**/ int x;\n}\n";
+    assert_eq!(
+        remove_comments(java, "java", CommentMode::KeepDocs),
+        "/** Javadoc */\npublic class A {\n    \n     int x;\n}\n"
+    );
+
+    let ts = "/** Adds */\nexport function add(a: number): number { return a; } // plain\n";
+    assert_eq!(
+        remove_comments(ts, "ts", CommentMode::KeepDocs),
+        "/** Adds */\nexport function add(a: number): number { return a; } \n"
+    );
+}
+
+#[test]
+fn test_keep_docs_go() {
+    let code = r#"// Package main does things
+package main
+
+// Server serves
+// over HTTP
+type Server struct{}
+
+func f() {
+	// inside
+}
+
+// detached
+
+func g() {}
+"#;
+
+    let expected = concat!(
+        "// Package main does things\n",
+        "package main\n",
+        "\n",
+        "// Server serves\n",
+        "// over HTTP\n",
+        "type Server struct{}\n",
+        "\n",
+        "func f() {\n",
+        "\t\n",
+        "}\n",
+        "\n",
+        "\n",
+        "\n",
+        "func g() {}\n",
+    );
+
+    assert_eq!(remove_comments(code, "go", CommentMode::KeepDocs), expected);
+}
+
+#[test]
+fn test_keep_docs_ruby() {
+    let code = r#"# frozen_string_literal: true
+
+# Greeter says hi
+class Greeter
+  # Says hello
+  def hello
+    # inside
+    puts 'hi'
+  end
+end
+"#;
+
+    let expected = concat!(
+        "\n",
+        "\n",
+        "# Greeter says hi\n",
+        "class Greeter\n",
+        "  # Says hello\n",
+        "  def hello\n",
+        "    \n",
+        "    puts 'hi'\n",
+        "  end\n",
+        "end\n",
+    );
+
+    assert_eq!(remove_comments(code, "rb", CommentMode::KeepDocs), expected);
+}
+
+#[test]
+fn test_keep_docs_python_docstrings() {
+    // Docstrings are strings, not comments, and survive every mode
+    let code = "def f():\n    \"\"\"Docstring\"\"\"\n    # comment\n    return 1\n";
+    let expected = "def f():\n    \"\"\"Docstring\"\"\"\n    \n    return 1\n";
+
+    assert_eq!(remove_comments(code, "py", CommentMode::All), expected);
+    assert_eq!(remove_comments(code, "py", CommentMode::KeepDocs), expected);
+}
+
+#[test]
+fn test_keep_license_header() {
+    let code = r#"// Copyright 2024 Example Corp.
+// SPDX-License-Identifier: MIT
+
+//! Crate docs
+/// Adds one
+fn add(x: i32) -> i32 {
+    x + 1 // plain
+}
+"#;
+
+    let expected = concat!(
+        "// Copyright 2024 Example Corp.\n",
+        "// SPDX-License-Identifier: MIT\n",
+        "\n",
+        "\n",
+        "\n",
+        "fn add(x: i32) -> i32 {\n",
+        "    x + 1 \n",
+        "}\n",
+    );
+
+    assert_eq!(
+        remove_comments(code, "rs", CommentMode::KeepLicense),
+        expected
+    );
+}
+
+#[test]
+fn test_keep_license_header_after_prelude() {
+    let bash = "#!/bin/bash\n# Copyright (c) 2024 Example\n# Licensed under Apache-2.0\necho hi # trailing\n";
+    assert_eq!(
+        remove_comments(bash, "sh", CommentMode::KeepLicense),
+        "#!/bin/bash\n# Copyright (c) 2024 Example\n# Licensed under Apache-2.0\necho hi \n"
+    );
+
+    let php = "<?php\n/*\n * @license MIT\n */\n\n// plain\necho 1;\n";
+    assert_eq!(
+        remove_comments(php, "php", CommentMode::KeepLicense),
+        "<?php\n/*\n * @license MIT\n */\n\n\necho 1;\n"
+    );
+}
+
+#[test]
+fn test_keep_license_requires_license_text() {
+    // A leading comment that isn't a license is removed like any other
+    let code = "// Helpers for parsing\nfn parse() {}\n// Copyright 2024 Example\n";
+    assert_eq!(
+        remove_comments(code, "rs", CommentMode::KeepLicense),
+        "\nfn parse() {}\n\n"
+    );
+}
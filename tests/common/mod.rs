@@ -1,8 +1,10 @@
+use git2::{Repository, Signature};
 use std::fs;
 use std::path::Path;
 use tempfile::TempDir;
 
 /// Create a temporary directory with test files for testing
+#[allow(dead_code)]
 pub fn create_test_repo() -> TempDir {
     let dir = tempfile::tempdir().expect("Failed to create temporary directory");
 
@@ -86,3 +88,30 @@ pub fn get_relative_path(base: &Path, path: &Path) -> String {
         .to_string_lossy()
         .replace('\\', "/")
 }
+
+/// Add a file to the git index
+#[allow(dead_code)]
+pub fn git_stage(repo: &Repository, path: &str) {
+    let mut index = repo.index().unwrap();
+    index.add_path(Path::new(path)).unwrap();
+    index.write().unwrap();
+}
+
+/// Commit the git index on top of HEAD
+#[allow(dead_code)]
+pub fn git_commit(repo: &Repository, message: &str) {
+    let mut index = repo.index().unwrap();
+    let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+    let signature = Signature::now("Test", "test@example.com").unwrap();
+    let parent = repo.head().ok().and_then(|head| head.peel_to_commit().ok());
+    let parents: Vec<_> = parent.iter().collect();
+    repo.commit(
+        Some("HEAD"),
+        &signature,
+        &signature,
+        message,
+        &tree,
+        &parents,
+    )
+    .unwrap();
+}
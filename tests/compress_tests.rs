@@ -0,0 +1,261 @@
+use remix::compress::compress_content;
+
+#[test]
+fn test_compress_rust() {
+    let code = r#"use std::fmt;
+
+/// A point
+pub struct Point {
+    x: i32,
+}
+
+impl Point {
+    /// Create a point
+    pub(crate) async fn new(x: i32) -> Self {
+        let y = x * 2;
+        Self { x: y }
+    }
+}
+
+#[inline]
+fn helper() -> i32 {
+    fn nested() {}
+    42
+}
+
+trait Shape {
+    fn area(&self) -> f64;
+    fn name(&self) -> String {
+        String::from("shape")
+    }
+}
+"#;
+
+    let expected = r#"use std::fmt;
+
+/// A point
+pub struct Point {
+    x: i32,
+}
+
+impl Point {
+    /// Create a point
+    pub(crate) async fn new(x: i32) -> Self { ... }
+}
+
+#[inline]
+fn helper() -> i32 { ... }
+
+trait Shape {
+    fn area(&self) -> f64;
+    fn name(&self) -> String { ... }
+}
+"#;
+
+    assert_eq!(compress_content(code, "rs"), expected);
+}
+
+#[test]
+fn test_compress_python() {
+    let code = r#"import os
+from typing import List
+
+
+@dataclass
+class Foo:
+    """A foo."""
+    x: int = 0
+
+    def bar(self, y: int) -> int:
+        """Return bar."""
+        z = y + 1
+        return z
+
+    @property
+    def baz(self):
+        return 1
+
+
+def main(): return 0
+"#;
+
+    let expected = r#"import os
+from typing import List
+
+@dataclass
+class Foo:
+    """A foo."""
+    x: int = 0
+
+    def bar(self, y: int) -> int:
+        """Return bar."""
+        ...
+
+    @property
+    def baz(self):
+        ...
+
+def main(): ...
+"#;
+
+    assert_eq!(compress_content(code, "py"), expected);
+}
+
+#[test]
+fn test_compress_typescript() {
+    let code = r#"import { x } from "./x";
+
+export interface Props {
+  name: string;
+}
+
+/** Greets someone */
+export function greet(p: Props): string {
+  return `hi ${p.name}`;
+}
+
+export const handler = async (req: Request) => {
+  const body = await req.json();
+  return body;
+};
+
+export const double = (n: number) => n * 2;
+
+class Service {
+  private count = 0;
+  run(): void {
+    this.count++;
+  }
+}
+"#;
+
+    let result = compress_content(code, "ts");
+
+    assert!(result.contains("import { x } from \"./x\";"));
+    assert!(result.contains("export interface Props {\n  name: string;\n}"));
+    assert!(
+        result.contains("/** Greets someone */\nexport function greet(p: Props): string { ... }")
+    );
+    assert!(result.contains("export const handler = async (req: Request) => { ... };"));
+    assert!(result.contains("export const double = (n: number) => n * 2;"));
+    assert!(result.contains("  run(): void { ... }"));
+    assert!(!result.contains("req.json()"));
+}
+
+#[test]
+fn test_compress_javascript() {
+    let code = r#"const fs = require("fs");
+
+function* ids() {
+  let i = 0;
+  while (true) yield i++;
+}
+
+module.exports = {
+  read(path) {
+    return fs.readFileSync(path, "utf8");
+  },
+};
+"#;
+
+    let result = compress_content(code, "js");
+
+    assert!(result.contains("const fs = require(\"fs\");"));
+    assert!(result.contains("function* ids() { ... }"));
+    assert!(result.contains("  read(path) { ... },"));
+    assert!(!result.contains("readFileSync"));
+}
+
+#[test]
+fn test_compress_go() {
+    let code = r#"package main
+
+import "fmt"
+
+type Server struct {
+	Addr string
+}
+
+// Start starts the server
+func (s *Server) Start() error {
+	fmt.Println(s.Addr)
+	return nil
+}
+
+func main() {
+	s := &Server{Addr: ":8080"}
+	s.Start()
+}
+"#;
+
+    let expected = r#"package main
+
+import "fmt"
+
+type Server struct {
+	Addr string
+}
+
+// Start starts the server
+func (s *Server) Start() error { ... }
+
+func main() { ... }
+"#;
+
+    assert_eq!(compress_content(code, "go"), expected);
+}
+
+#[test]
+fn test_compress_java() {
+    let code = r#"package com.example;
+
+import java.util.List;
+
+public class Foo {
+    private int x;
+
+    public Foo(int x) {
+        this.x = x;
+    }
+
+    /** Returns x */
+    public int getX() {
+        return x;
+    }
+}
+
+interface Shape {
+    double area();
+}
+"#;
+
+    let expected = r#"package com.example;
+
+import java.util.List;
+
+public class Foo {
+    private int x;
+
+    public Foo(int x) { ... }
+
+    /** Returns x */
+    public int getX() { ... }
+}
+
+interface Shape {
+    double area();
+}
+"#;
+
+    assert_eq!(compress_content(code, "java"), expected);
+}
+
+#[test]
+fn test_compress_unsupported_or_invalid() {
+    let markdown = "# Title\n\nSome text\n";
+    assert_eq!(compress_content(markdown, "md"), markdown);
+
+    // Syntax errors leave the file untouched rather than risk a mangled view
+    let broken = "fn main( {\n    let x = ;\n";
+    assert_eq!(compress_content(broken, "rs"), broken);
+}
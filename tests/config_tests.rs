@@ -13,9 +13,15 @@ fn test_default_config() {
     assert_eq!(config.max_file_size, 100_000); // 100KB
     assert!(!config.compress);
     assert!(config.security.enable_security_check);
+    assert_eq!(config.security.mode, "skip");
+    assert_eq!(config.security.fail_on, "low");
     assert_eq!(config.output.format, "txt");
     assert!(!config.output.open_file);
     assert_eq!(config.output.path, "./remix-output.txt");
+    assert_eq!(config.token_count.encoding, "o200k_base");
+    assert!(config.output.tree.enabled);
+    assert_eq!(config.output.tree.max_depth, None);
+    assert!(config.output.tree.include_excluded);
 }
 
 #[test]
@@ -27,6 +33,7 @@ fn test_output_config_default() {
     assert_eq!(output_config.path, "./remix-output.txt");
     assert!(output_config.instruction_file_path.is_none());
     assert!(!output_config.remove_comments);
+    assert_eq!(output_config.comment_mode, "all");
 }
 
 #[test]
@@ -110,6 +117,70 @@ fn test_config_merge_with_cli() {
     assert!(merged.output.remove_comments);
 }
 
+#[test]
+fn test_config_merge_comment_mode() {
+    let cli = Cli::parse_from(["remix", "--comment-mode", "keep-docs"]);
+    let merged = Config::default().merge_with_cli(&cli);
+
+    // Picking the comments to keep implies removing the rest
+    assert!(merged.output.remove_comments);
+    assert_eq!(merged.output.comment_mode, "keep-docs");
+
+    assert!(Cli::try_parse_from(["remix", "--comment-mode", "some"]).is_err());
+}
+
+#[test]
+fn test_config_merge_security_mode() {
+    let cli = Cli::parse_from(["remix", "--security-mode", "fail"]);
+    let merged = Config::default().merge_with_cli(&cli);
+    assert_eq!(merged.security.mode, "fail");
+    assert_eq!(merged.security.report, None);
+
+    let cli = Cli::parse_from([
+        "remix",
+        "--security-report",
+        "secrets.sarif",
+        "--security-report-format",
+        "json",
+    ]);
+    let merged = Config::default().merge_with_cli(&cli);
+    assert_eq!(merged.security.report.as_deref(), Some("secrets.sarif"));
+    assert_eq!(merged.security.report_format.as_deref(), Some("json"));
+
+    assert!(Cli::try_parse_from(["remix", "--security-mode", "ignore"]).is_err());
+}
+
+#[test]
+fn test_load_config_secret_rules_and_allowlist() {
+    let temp_dir = TempDir::new().unwrap();
+    let config_path = temp_dir.path().join("config.json");
+    fs::write(
+        &config_path,
+        r#"{
+        "security": {
+            "custom_rules": [
+                { "id": "acme-token", "regex": "acme_[a-z0-9]{12}", "entropy": 3.0 }
+            ],
+            "allowlist": {
+                "paths": ["tests/fixtures/**"],
+                "fingerprints": ["0123456789abcdef"]
+            }
+        }
+    }"#,
+    )
+    .unwrap();
+
+    let config = load_config(&config_path).unwrap();
+    assert_eq!(config.security.mode, "skip");
+    assert_eq!(config.security.custom_rules.len(), 1);
+    assert_eq!(config.security.custom_rules[0].id, "acme-token");
+    assert_eq!(config.security.custom_rules[0].entropy, Some(3.0));
+    assert!(config.security.custom_rules[0].description.is_none());
+    assert_eq!(config.security.allowlist.paths, vec!["tests/fixtures/**"]);
+    assert!(config.security.allowlist.regexes.is_empty());
+    assert_eq!(config.security.allowlist.fingerprints.len(), 1);
+}
+
 #[test]
 fn test_config_merge_overrides() {
     let temp_dir = TempDir::new().unwrap();
@@ -134,3 +205,63 @@ fn test_config_merge_overrides() {
     assert_eq!(merged.output.format, "json");
     assert_eq!(merged.output.path, "cli.json");
 }
+
+#[test]
+fn test_config_merge_git_changes() {
+    let config = Config::default();
+    assert!(config.git.since.is_none());
+    assert!(!config.git.with_diff);
+
+    let cli = Cli::parse_from(["remix", "--since", "v1.0.0", "--staged", "--with-diff"]);
+    let merged = config.merge_with_cli(&cli);
+    assert_eq!(merged.git.since.as_deref(), Some("v1.0.0"));
+    assert!(merged.git.staged);
+    assert!(!merged.git.working_tree);
+    assert!(merged.git.with_diff);
+
+    let cli = Cli::parse_from(["remix", "--ref", "v1.2.0"]);
+    let merged = config.merge_with_cli(&cli);
+    assert_eq!(merged.git.reference.as_deref(), Some("v1.2.0"));
+
+    // The config file key is "ref"
+    let temp_dir = TempDir::new().unwrap();
+    let config_path = temp_dir.path().join("config.json");
+    fs::write(&config_path, r#"{ "git": { "ref": "v1.0.0" } }"#).unwrap();
+    let config = load_config(&config_path).unwrap();
+    assert_eq!(config.git.reference.as_deref(), Some("v1.0.0"));
+    assert!(!config.git.history);
+    assert_eq!(config.git.history_commits, 10);
+    assert_eq!(config.git.top_contributors, 0);
+
+    let cli = Cli::parse_from(["remix", "--git-history", "--top-contributors", "2"]);
+    let merged = config.merge_with_cli(&cli);
+    assert!(merged.git.history);
+    assert_eq!(merged.git.history_commits, 10);
+    assert_eq!(merged.git.top_contributors, 2);
+}
+
+#[test]
+fn test_config_merge_remote_depth() {
+    let config = Config::default();
+    assert_eq!(config.remote.depth, 1);
+
+    let cli = Cli::parse_from(["remix", "--remote", "user/repo", "--clone-depth", "0"]);
+    let merged = config.merge_with_cli(&cli);
+    assert_eq!(merged.remote.depth, 0);
+
+    let temp_dir = TempDir::new().unwrap();
+    let config_path = temp_dir.path().join("config.json");
+    fs::write(&config_path, r#"{ "remote": { "depth": 5 } }"#).unwrap();
+    let config = load_config(&config_path).unwrap();
+    assert_eq!(config.remote.depth, 5);
+    assert!(config.remote.cache);
+    assert_eq!(config.remote.cache_max_age_days, 30);
+
+    let cli = Cli::parse_from(["remix", "--no-cache", "--cache-dir", "/tmp/remix-cache"]);
+    let merged = config.merge_with_cli(&cli);
+    assert!(!merged.remote.cache);
+    assert_eq!(
+        merged.remote.cache_dir,
+        Some(std::path::PathBuf::from("/tmp/remix-cache"))
+    );
+}
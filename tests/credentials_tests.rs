@@ -0,0 +1,199 @@
+use git2::{CredentialType, ErrorClass, ErrorCode};
+use remix::config::{HostCredentials, RemoteConfig};
+use remix::credentials::Credentials;
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+
+/// Credentials for a URL with only the given environment variables set
+fn credentials(url: &str, config: &RemoteConfig, vars: &[(&str, &str)]) -> Credentials {
+    let env: HashMap<String, String> = vars
+        .iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect();
+    Credentials::with_env(url, config, move |name| env.get(name).cloned())
+}
+
+/// Ask for credentials until every method was tried
+fn exhaust(credentials: &mut Credentials, url: &str, allowed: CredentialType) {
+    for _ in 0..10 {
+        if credentials.credential(url, None, allowed).is_err() {
+            return;
+        }
+    }
+    panic!("credentials were never exhausted");
+}
+
+#[test]
+fn test_https_token_order() {
+    let url = "https://github.com/user/private";
+    let vars = [("REMIX_GIT_TOKEN", "remix"), ("GITHUB_TOKEN", "github")];
+    let mut creds = credentials(url, &RemoteConfig::default(), &vars);
+    exhaust(&mut creds, url, CredentialType::USER_PASS_PLAINTEXT);
+    assert_eq!(
+        creds.tried(),
+        [
+            "token from $REMIX_GIT_TOKEN",
+            "token from $GITHUB_TOKEN",
+            "git credential helper"
+        ]
+    );
+
+    // GITHUB_TOKEN is only sent to GitHub, and unset variables are skipped
+    let url = "https://gitlab.com/group/private";
+    let mut creds = credentials(url, &RemoteConfig::default(), &[("GITHUB_TOKEN", "github")]);
+    exhaust(&mut creds, url, CredentialType::USER_PASS_PLAINTEXT);
+    assert_eq!(creds.tried(), ["git credential helper"]);
+}
+
+#[test]
+fn test_host_credentials() {
+    let mut hosts = BTreeMap::new();
+    hosts.insert(
+        "gitlab.example.com".to_string(),
+        HostCredentials {
+            token_env: Some("GITLAB_TOKEN".to_string()),
+            ssh_key: Some(PathBuf::from("/keys/gitlab")),
+            ..Default::default()
+        },
+    );
+    hosts.insert(
+        "*".to_string(),
+        HostCredentials {
+            token_env: Some("OTHER_TOKEN".to_string()),
+            ..Default::default()
+        },
+    );
+    let config = RemoteConfig {
+        credentials: hosts,
+        ..Default::default()
+    };
+    let vars = [
+        ("GITLAB_TOKEN", "gitlab"),
+        ("OTHER_TOKEN", "other"),
+        ("REMIX_GIT_TOKEN", "remix"),
+    ];
+
+    let url = "https://GitLab.example.com/group/repo.git";
+    let mut creds = credentials(url, &config, &vars);
+    creds
+        .credential(url, None, CredentialType::USER_PASS_PLAINTEXT)
+        .unwrap();
+    assert_eq!(creds.tried(), ["token from $GITLAB_TOKEN"]);
+
+    // Other hosts get the "*" entry
+    let url = "https://git.example.org/repo";
+    let mut creds = credentials(url, &config, &vars);
+    creds
+        .credential(url, None, CredentialType::USER_PASS_PLAINTEXT)
+        .unwrap();
+    creds
+        .credential(url, None, CredentialType::USER_PASS_PLAINTEXT)
+        .unwrap();
+    assert_eq!(
+        creds.tried(),
+        ["token from $OTHER_TOKEN", "token from $REMIX_GIT_TOKEN"]
+    );
+
+    // SSH tries the agent before the configured key
+    let url = "git@gitlab.example.com:group/repo.git";
+    let mut creds = credentials(url, &config, &vars);
+    for _ in 0..2 {
+        creds
+            .credential(url, Some("git"), CredentialType::SSH_KEY)
+            .unwrap();
+    }
+    assert_eq!(creds.tried(), ["ssh-agent", "SSH key /keys/gitlab"]);
+}
+
+#[test]
+fn test_explain_auth_failure() {
+    let url = "https://git.example.org/private";
+    let mut creds = credentials(url, &RemoteConfig::default(), &[("REMIX_GIT_TOKEN", "t")]);
+
+    // Nothing tried yet: the error is passed through
+    let error = git2::Error::new(ErrorCode::Auth, ErrorClass::Http, "authentication required");
+    let message = error.to_string();
+    assert_eq!(creds.explain(error).to_string(), message);
+
+    exhaust(&mut creds, url, CredentialType::USER_PASS_PLAINTEXT);
+    let error = git2::Error::new(ErrorCode::Auth, ErrorClass::Http, "authentication required");
+    let message = creds.explain(error).to_string();
+    assert!(message.contains("Authentication with git.example.org failed"));
+    assert!(message.contains("tried token from $REMIX_GIT_TOKEN, git credential helper"));
+
+    // Other errors are not about credentials
+    let error = git2::Error::new(ErrorCode::NotFound, ErrorClass::Net, "repository not found");
+    let message = error.to_string();
+    assert_eq!(creds.explain(error).to_string(), message);
+}
+
+#[test]
+fn test_credentials_across_connections() {
+    let config = RemoteConfig {
+        credentials: BTreeMap::from([(
+            "*".to_string(),
+            HostCredentials {
+                token_env: Some("HOST_TOKEN".to_string()),
+                ..Default::default()
+            },
+        )]),
+        ..Default::default()
+    };
+    let vars = [("HOST_TOKEN", "wrong"), ("REMIX_GIT_TOKEN", "right")];
+    let url = "https://git.example.org/private";
+    let mut creds = credentials(url, &config, &vars);
+
+    // The first connection is rejected with the host token, then accepted
+    creds.new_connection();
+    for _ in 0..2 {
+        creds
+            .credential(url, None, CredentialType::USER_PASS_PLAINTEXT)
+            .unwrap();
+    }
+    // The next connection, e.g. the fetch after listing refs, starts with
+    // the token that worked and can still fall back to the others
+    creds.new_connection();
+    creds
+        .credential(url, None, CredentialType::USER_PASS_PLAINTEXT)
+        .unwrap();
+    assert_eq!(
+        creds.tried(),
+        [
+            "token from $HOST_TOKEN",
+            "token from $REMIX_GIT_TOKEN",
+            "token from $REMIX_GIT_TOKEN"
+        ]
+    );
+    exhaust(&mut creds, url, CredentialType::USER_PASS_PLAINTEXT);
+    assert_eq!(
+        &creds.tried()[3..],
+        ["token from $HOST_TOKEN", "git credential helper"]
+    );
+
+    // A failure lists every method once
+    let error = git2::Error::new(ErrorCode::Auth, ErrorClass::Http, "authentication required");
+    let message = creds.explain(error).to_string();
+    assert!(message.contains(
+        "tried token from $HOST_TOKEN, token from $REMIX_GIT_TOKEN, git credential helper"
+    ));
+}
+
+#[test]
+fn test_tokens_only_over_https() {
+    let vars = [("REMIX_GIT_TOKEN", "remix"), ("GITHUB_TOKEN", "github")];
+    for url in [
+        "http://github.com/user/private",
+        "git://github.com/user/private",
+    ] {
+        let mut creds = credentials(url, &RemoteConfig::default(), &vars);
+        exhaust(&mut creds, url, CredentialType::USER_PASS_PLAINTEXT);
+        assert_eq!(creds.tried(), ["git credential helper"], "{}", url);
+    }
+
+    let url = "HTTPS://github.com/user/private";
+    let mut creds = credentials(url, &RemoteConfig::default(), &vars);
+    creds
+        .credential(url, None, CredentialType::USER_PASS_PLAINTEXT)
+        .unwrap();
+    assert_eq!(creds.tried(), ["token from $REMIX_GIT_TOKEN"]);
+}
@@ -1,7 +1,10 @@
 use anyhow::Result;
-use remix::formatter::{format_toon, format_markdown, format_json, format_text};
+use remix::formatter::{format_toon, format_markdown, format_json, format_text, format_xml};
+use remix::history::{CommitSummary, Contributor, FileHistory, GitHistory};
 use remix::packer::{PackedRepository, RepositorySummary, FileContent};
-use remix::security::SecurityCheckStatus;
+use remix::remote::RemoteSource;
+use remix::security::{SecretFinding, SecurityCheckStatus};
+use remix::tree::{DirectoryTree, TreeEntry};
 
 #[test]
 fn test_format_toon_basic() -> Result<()> {
@@ -12,6 +15,9 @@ fn test_format_toon_basic() -> Result<()> {
             total_size: 100,
             extensions: vec!["txt".to_string(), "rs".to_string()],
             binary_file_count: 0,
+            total_tokens: 0,
+            token_encoding: "o200k_base".to_string(),
+            token_budget: None,
         },
         files: vec![
             FileContent {
@@ -20,6 +26,9 @@ fn test_format_toon_basic() -> Result<()> {
                 size: 5,
                 extension: "txt".to_string(),
                 is_binary: false,
+                token_count: 0,
+                diff: None,
+                history: None,
             },
             FileContent {
                 relative_path: "file2.rs".to_string(),
@@ -27,12 +36,22 @@ fn test_format_toon_basic() -> Result<()> {
                 size: 11,
                 extension: "rs".to_string(),
                 is_binary: false,
+                token_count: 0,
+                diff: None,
+                history: None,
             },
         ],
+        directory_tree: None,
         instruction: Some("Test instruction".to_string()),
-        suspicious_files: None,
+        git_history: None,
+        remote: None,
+        security_findings: None,
         security_check_status: remix::security::SecurityCheckStatus::CompletedNoFindings,
+        on_disk_only_findings: None,
+        redactions: None,
+        skipped_sensitive_files: None,
         binary_files: None,
+        omitted_files: None,
     };
 
     let result = format_toon(&repo)?;
@@ -51,6 +70,9 @@ fn test_format_markdown() -> Result<()> {
             total_size: 10,
             extensions: vec!["md".to_string()],
             binary_file_count: 0,
+            total_tokens: 0,
+            token_encoding: "o200k_base".to_string(),
+            token_budget: None,
         },
         files: vec![
             FileContent {
@@ -59,18 +81,29 @@ fn test_format_markdown() -> Result<()> {
                 size: 10,
                 extension: "md".to_string(),
                 is_binary: false,
+                token_count: 0,
+                diff: None,
+                history: None,
             },
         ],
+        directory_tree: None,
         instruction: Some("Test instruction".to_string()),
-        suspicious_files: None,
+        git_history: None,
+        remote: None,
+        security_findings: None,
         security_check_status: SecurityCheckStatus::CompletedNoFindings,
+        on_disk_only_findings: None,
+        redactions: None,
+        skipped_sensitive_files: None,
         binary_files: None,
+        omitted_files: None,
     };
 
     let result = format_markdown(&repo);
     assert!(result.contains("# User Instruction"));
     assert!(result.contains("Test instruction"));
     assert!(result.contains("- **Files:** 1"));
+    assert!(result.contains("- **Tokens:** 0 (o200k_base)"));
     assert!(result.contains("### README.md"));
     assert!(result.contains("# Hello"));
     Ok(())
@@ -85,6 +118,9 @@ fn test_format_json() -> Result<()> {
             total_size: 5,
             extensions: vec!["txt".to_string()],
             binary_file_count: 0,
+            total_tokens: 0,
+            token_encoding: "o200k_base".to_string(),
+            token_budget: None,
         },
         files: vec![
             FileContent {
@@ -93,12 +129,22 @@ fn test_format_json() -> Result<()> {
                 size: 5,
                 extension: "txt".to_string(),
                 is_binary: false,
+                token_count: 0,
+                diff: None,
+                history: None,
             },
         ],
+        directory_tree: None,
         instruction: None,
-        suspicious_files: None,
+        git_history: None,
+        remote: None,
+        security_findings: None,
         security_check_status: SecurityCheckStatus::CompletedNoFindings,
+        on_disk_only_findings: None,
+        redactions: None,
+        skipped_sensitive_files: None,
         binary_files: None,
+        omitted_files: None,
     };
 
     let result = format_json(&repo)?;
@@ -120,6 +166,9 @@ fn test_format_text() -> Result<()> {
             total_size: 5,
             extensions: vec!["txt".to_string()],
             binary_file_count: 0,
+            total_tokens: 0,
+            token_encoding: "o200k_base".to_string(),
+            token_budget: None,
         },
         files: vec![
             FileContent {
@@ -128,12 +177,22 @@ fn test_format_text() -> Result<()> {
                 size: 5,
                 extension: "txt".to_string(),
                 is_binary: false,
+                token_count: 0,
+                diff: None,
+                history: None,
             },
         ],
+        directory_tree: None,
         instruction: Some("Instruction".to_string()),
-        suspicious_files: None,
+        git_history: None,
+        remote: None,
+        security_findings: None,
         security_check_status: SecurityCheckStatus::CompletedNoFindings,
+        on_disk_only_findings: None,
+        redactions: None,
+        skipped_sensitive_files: None,
         binary_files: None,
+        omitted_files: None,
     };
 
     let result = format_text(&repo);
@@ -154,16 +213,341 @@ fn test_format_toon_empty_repo() -> Result<()> {
             total_size: 0,
             extensions: vec![],
             binary_file_count: 0,
+            total_tokens: 0,
+            token_encoding: "o200k_base".to_string(),
+            token_budget: None,
         },
         files: vec![],
+        directory_tree: None,
         instruction: None,
-        suspicious_files: None,
+        git_history: None,
+        remote: None,
+        security_findings: None,
         security_check_status: remix::security::SecurityCheckStatus::CompletedNoFindings,
+        on_disk_only_findings: None,
+        redactions: None,
+        skipped_sensitive_files: None,
         binary_files: None,
+        omitted_files: None,
     };
 
     let result = format_toon(&repo)?;
     assert!(!result.is_empty());
     assert!(result.contains("file_count: 0"));
     Ok(())
-}
\ No newline at end of file
+}
+#[test]
+fn test_format_xml() -> Result<()> {
+    let repo = PackedRepository {
+        summary: RepositorySummary {
+            file_count: 2,
+            directory_count: 1,
+            total_size: 60,
+            extensions: vec!["rs".to_string(), "xml".to_string()],
+            binary_file_count: 0,
+            total_tokens: 0,
+            token_encoding: "o200k_base".to_string(),
+            token_budget: None,
+        },
+        files: vec![
+            FileContent {
+                relative_path: "src/main.rs".to_string(),
+                content: "fn main() { let x = a[b[0]]>1; }".to_string(),
+                size: 33,
+                extension: "rs".to_string(),
+                is_binary: false,
+                token_count: 0,
+                diff: None,
+                history: None,
+            },
+            FileContent {
+                relative_path: "R&D \"notes\".xml".to_string(),
+                content: "<a>&amp;</a>".to_string(),
+                size: 12,
+                extension: "xml".to_string(),
+                is_binary: false,
+                token_count: 0,
+                diff: None,
+                history: None,
+            },
+        ],
+        directory_tree: Some(DirectoryTree::new(
+            vec![
+                TreeEntry::new("src/main.rs"),
+                TreeEntry::new("R&D \"notes\".xml"),
+            ],
+            None,
+        )),
+        instruction: Some("Review this".to_string()),
+        git_history: None,
+        remote: None,
+        security_findings: None,
+        security_check_status: SecurityCheckStatus::CompletedNoFindings,
+        on_disk_only_findings: None,
+        redactions: None,
+        skipped_sensitive_files: None,
+        binary_files: None,
+        omitted_files: None,
+    };
+
+    let result = format_xml(&repo);
+
+    assert!(result.contains("<file_summary>"));
+    assert!(result.contains("</file_summary>"));
+    assert!(result.contains("<directory_structure>\nsrc/\n  main.rs\n"));
+    assert!(result.contains("<files>"));
+    assert!(result.contains("<file path=\"src/main.rs\">"));
+    assert!(result.contains("<file path=\"R&amp;D &quot;notes&quot;.xml\">"));
+    assert!(result.contains("<![CDATA[<a>&amp;</a>]]>"));
+    assert!(result.contains("<instruction>\n<![CDATA[Review this]]>\n</instruction>"));
+
+    // "]]>" inside content must not terminate the CDATA section
+    assert!(result.contains("<![CDATA[fn main() { let x = a[b[0]]]]><![CDATA[>1; }]]>"));
+    assert_eq!(result.matches("<file path=").count(), 2);
+    Ok(())
+}
+
+fn create_repo_with_tree(tree: Option<DirectoryTree>) -> PackedRepository {
+    PackedRepository {
+        summary: RepositorySummary {
+            file_count: 1,
+            directory_count: 1,
+            total_size: 12,
+            extensions: vec!["rs".to_string()],
+            binary_file_count: 1,
+            total_tokens: 4,
+            token_encoding: "o200k_base".to_string(),
+            token_budget: None,
+        },
+        files: vec![FileContent {
+            relative_path: "src/main.rs".to_string(),
+            content: "fn main() {}".to_string(),
+            size: 12,
+            extension: "rs".to_string(),
+            is_binary: false,
+            token_count: 4,
+            diff: None,
+            history: None,
+        }],
+        directory_tree: tree,
+        instruction: None,
+        git_history: None,
+        remote: None,
+        security_findings: None,
+        security_check_status: SecurityCheckStatus::CompletedNoFindings,
+        on_disk_only_findings: None,
+        redactions: None,
+        skipped_sensitive_files: None,
+        binary_files: None,
+        omitted_files: None,
+    }
+}
+
+#[test]
+fn test_directory_tree_in_outputs() -> Result<()> {
+    let tree = DirectoryTree::new(
+        vec![
+            TreeEntry::new("src/main.rs"),
+            TreeEntry::marked("logo.png", "binary"),
+        ],
+        None,
+    );
+    let repo = create_repo_with_tree(Some(tree));
+
+    let markdown = format_markdown(&repo);
+    assert!(markdown.contains("# Directory Structure"));
+    assert!(markdown.contains("└── logo.png [binary]"));
+
+    let text = format_text(&repo);
+    assert!(text.contains("DIRECTORY STRUCTURE:"));
+    assert!(text.contains("│   └── main.rs"));
+
+    let json = format_json(&repo)?;
+    let value: serde_json::Value = serde_json::from_str(&json)?;
+    assert!(value["directory_tree"]
+        .as_str()
+        .unwrap()
+        .contains("logo.png [binary]"));
+
+    let xml = format_xml(&repo);
+    assert!(xml.contains("<directory_structure>\nsrc/\n  main.rs\nlogo.png [binary]\n"));
+    Ok(())
+}
+
+#[test]
+fn test_directory_tree_disabled() -> Result<()> {
+    let repo = create_repo_with_tree(None);
+
+    assert!(!format_markdown(&repo).contains("# Directory Structure"));
+    assert!(!format_text(&repo).contains("DIRECTORY STRUCTURE:"));
+    assert!(!format_xml(&repo).contains("<directory_structure>"));
+    Ok(())
+}
+
+#[test]
+fn test_security_findings_in_outputs() {
+    let mut repo = create_repo_with_tree(None);
+    repo.security_check_status = SecurityCheckStatus::CompletedWithFindings;
+    repo.security_findings = Some(vec![SecretFinding {
+        file: "src/main.rs".to_string(),
+        line: 2,
+        column: 16,
+        rule_id: "aws-access-key".to_string(),
+        fingerprint: "0123456789abcdef".to_string(),
+        snippet: "let key = \"AKIA********\";".to_string(),
+    }]);
+
+    let markdown = format_markdown(&repo);
+    assert!(markdown.contains("1 secret(s) detected"));
+    assert!(markdown
+        .contains("1. `src/main.rs:2:16` **aws-access-key**: `let key = \"AKIA********\";`"));
+
+    let text = format_text(&repo);
+    assert!(text.contains("1. src/main.rs:2:16 [aws-access-key] let key = \"AKIA********\";"));
+
+    assert!(text.contains("(fingerprint 0123456789abcdef)"));
+
+    let xml = format_xml(&repo);
+    assert!(xml.contains("- src/main.rs:2:16 [aws-access-key] let key = &quot;AKIA********&quot;;"));
+}
+
+#[test]
+fn test_redactions_in_outputs() {
+    let mut repo = create_repo_with_tree(None);
+    repo.redactions = Some(vec![SecretFinding {
+        file: "src/main.rs".to_string(),
+        line: 2,
+        column: 16,
+        rule_id: "aws-access-key".to_string(),
+        fingerprint: "0123456789abcdef".to_string(),
+        snippet: "let key = \"AKIA********\";".to_string(),
+    }]);
+    repo.skipped_sensitive_files = Some(vec![".env".to_string()]);
+
+    let markdown = format_markdown(&repo);
+    assert!(markdown.contains("## Redacted Secrets"));
+    assert!(markdown.contains("1. `src/main.rs:2:16` **aws-access-key**"));
+    assert!(markdown.contains("## Files Skipped for Secrets"));
+    assert!(markdown.contains("1. `.env`"));
+
+    let text = format_text(&repo);
+    assert!(text.contains("REDACTED SECRETS:"));
+    assert!(text.contains("1. src/main.rs:2:16 [aws-access-key]"));
+    assert!(text.contains("FILES SKIPPED FOR SECRETS:"));
+
+    let xml = format_xml(&repo);
+    assert!(xml.contains("- Secret redacted: src/main.rs:2:16 [aws-access-key]"));
+    assert!(xml.contains("- File omitted because it contains secrets: .env"));
+}
+
+#[test]
+fn test_on_disk_only_findings_in_outputs() {
+    let mut repo = create_repo_with_tree(None);
+    repo.on_disk_only_findings = Some(vec![SecretFinding {
+        file: "src/main.rs".to_string(),
+        line: 1,
+        column: 11,
+        rule_id: "github-token".to_string(),
+        fingerprint: "0123456789abcdef".to_string(),
+        snippet: "// token: ghp_********".to_string(),
+    }]);
+
+    let markdown = format_markdown(&repo);
+    assert!(markdown.contains("## Secrets Not in This Output"));
+    assert!(markdown.contains("1. `src/main.rs:1:11` **github-token** (on disk only)"));
+
+    let text = format_text(&repo);
+    assert!(text.contains("SECRETS NOT IN THIS OUTPUT:"));
+    assert!(text.contains("1. (on disk only) src/main.rs:1:11 [github-token]"));
+
+    let xml = format_xml(&repo);
+    assert!(xml.contains("- Secret on disk only, not in this output: src/main.rs:1:11"));
+}
+
+#[test]
+fn test_diffs_in_outputs() {
+    let mut repo = create_repo_with_tree(None);
+    repo.files[0].diff = Some("@@ -1 +1 @@\n-old\n+new\n".to_string());
+
+    let markdown = format_markdown(&repo);
+    assert!(markdown.contains("**Changes:**\n\n```diff\n@@ -1 +1 @@\n-old\n+new\n```"));
+
+    let text = format_text(&repo);
+    assert!(text.contains("DIFF:\n@@ -1 +1 @@\n-old\n+new\n"));
+
+    let xml = format_xml(&repo);
+    assert!(xml.contains("<diff path=\"src/main.rs\">\n<![CDATA[@@ -1 +1 @@\n-old\n+new]]>\n</diff>"));
+
+    let json = format_json(&repo).unwrap();
+    assert!(json.contains("\"diff\": \"@@ -1 +1 @@\\n-old\\n+new\\n\""));
+}
+
+#[test]
+fn test_git_history_in_outputs() {
+    let mut repo = create_repo_with_tree(None);
+    repo.git_history = Some(GitHistory {
+        recent_commits: vec![CommitSummary {
+            id: "abc1234".to_string(),
+            author: "Alice".to_string(),
+            date: "2024-05-01T12:00:00Z".to_string(),
+            message: "Fix the parser".to_string(),
+        }],
+    });
+    repo.files[0].history = Some(FileHistory {
+        last_commit: "2024-05-01T12:00:00Z".to_string(),
+        commit_count: 7,
+        top_contributors: vec![
+            Contributor {
+                name: "Alice".to_string(),
+                commits: 5,
+            },
+            Contributor {
+                name: "Bob".to_string(),
+                commits: 2,
+            },
+        ],
+    });
+
+    let markdown = format_markdown(&repo);
+    assert!(markdown.contains("## Git History\n\n- `abc1234` 2024-05-01 Alice: Fix the parser\n"));
+    assert!(markdown.contains("| `src/main.rs` | 2024-05-01 | 7 | Alice (5), Bob (2) |"));
+
+    let text = format_text(&repo);
+    assert!(text.contains("GIT HISTORY:\n\nabc1234 2024-05-01 Alice: Fix the parser\n"));
+    assert!(text.contains("src/main.rs - 2024-05-01, 7 commits, Alice (5), Bob (2)\n"));
+
+    let json = format_json(&repo).unwrap();
+    assert!(json.contains("\"commit_count\": 7"));
+    assert!(json.contains("\"recent_commits\""));
+}
+
+#[test]
+fn test_remote_source_in_outputs() {
+    let mut repo = create_repo_with_tree(None);
+    assert!(!format_markdown(&repo).contains("**Commit:**"));
+
+    let commit = "0123456789abcdef0123456789abcdef01234567";
+    repo.remote = Some(RemoteSource {
+        url: "https://github.com/user/repo".to_string(),
+        reference: "develop".to_string(),
+        commit: commit.to_string(),
+        subpath: Some("src".to_string()),
+    });
+
+    let markdown = format_markdown(&repo);
+    assert!(markdown
+        .contains("- **Repository:** https://github.com/user/repo\n- **Ref:** develop\n"));
+    assert!(markdown.contains(&format!("- **Commit:** {}\n- **Path:** src\n", commit)));
+
+    let text = format_text(&repo);
+    assert!(text.contains(&format!("Ref: develop\nCommit: {}\n", commit)));
+
+    let xml = format_xml(&repo);
+    assert!(xml.contains(&format!(
+        "<additional_info>\nRepository: https://github.com/user/repo\nRef: develop\nCommit: {}\n",
+        commit
+    )));
+
+    let json = format_json(&repo).unwrap();
+    assert!(json.contains(&format!("\"commit\": \"{}\"", commit)));
+}
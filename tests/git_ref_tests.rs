@@ -0,0 +1,138 @@
+mod common;
+
+use common::{git_commit, git_stage};
+use git2::Repository;
+use remix::config::Config;
+use remix::packer::pack_repository;
+use std::fs;
+use tempfile::{tempdir, TempDir};
+
+/// A repository tagged `v1`, with a second commit and uncommitted changes on top
+fn repo_with_release() -> TempDir {
+    let dir = tempdir().unwrap();
+    let repo = Repository::init(dir.path()).unwrap();
+    fs::create_dir_all(dir.path().join("src/gen")).unwrap();
+    fs::create_dir(dir.path().join("docs")).unwrap();
+    fs::write(
+        dir.path().join("src/lib.rs"),
+        "pub const VERSION: u32 = 1;\n",
+    )
+    .unwrap();
+    fs::write(dir.path().join("src/gen/out.rs"), "// generated\n").unwrap();
+    fs::write(dir.path().join("src/.gitignore"), "gen/\n").unwrap();
+    fs::write(dir.path().join(".remixignore"), "docs/\n").unwrap();
+    fs::write(dir.path().join("docs/guide.md"), "# Guide\n").unwrap();
+    fs::write(dir.path().join("README.md"), "# Release 1\n").unwrap();
+    for path in [
+        "src/lib.rs",
+        "src/gen/out.rs",
+        "src/.gitignore",
+        ".remixignore",
+        "docs/guide.md",
+        "README.md",
+    ] {
+        git_stage(&repo, path);
+    }
+    git_commit(&repo, "release 1");
+    let head = repo.head().unwrap().peel_to_commit().unwrap();
+    repo.tag_lightweight("v1", head.as_object(), false).unwrap();
+    drop(head);
+
+    fs::write(
+        dir.path().join("src/lib.rs"),
+        "pub const VERSION: u32 = 2;\n",
+    )
+    .unwrap();
+    fs::write(dir.path().join("src/new.rs"), "pub fn new() {}\n").unwrap();
+    git_stage(&repo, "src/lib.rs");
+    git_stage(&repo, "src/new.rs");
+    git_commit(&repo, "release 2");
+
+    // Uncommitted work that packing a ref must not see or touch
+    fs::write(dir.path().join("README.md"), "# Work in progress\n").unwrap();
+    fs::write(dir.path().join("notes.txt"), "todo\n").unwrap();
+
+    dir
+}
+
+fn packed_paths(files: &[remix::packer::FileContent]) -> Vec<String> {
+    let mut paths: Vec<String> = files
+        .iter()
+        .map(|file| file.relative_path.replace('\\', "/"))
+        .collect();
+    paths.sort();
+    paths
+}
+
+#[tokio::test]
+async fn test_pack_git_ref() {
+    let dir = repo_with_release();
+
+    let mut config = Config::default();
+    config.git.reference = Some("v1".to_string());
+    let result = pack_repository(dir.path(), &config).await.unwrap();
+
+    // .gitignore and .remixignore apply as they were in that commit
+    assert_eq!(
+        packed_paths(&result.files),
+        [".remixignore", "README.md", "src/lib.rs"]
+    );
+    let lib = result
+        .files
+        .iter()
+        .find(|file| file.relative_path.ends_with("lib.rs"))
+        .unwrap();
+    assert_eq!(lib.content, "pub const VERSION: u32 = 1;\n");
+    let readme = result
+        .files
+        .iter()
+        .find(|file| file.relative_path == "README.md")
+        .unwrap();
+    assert_eq!(readme.content, "# Release 1\n");
+
+    // The working tree is untouched
+    assert_eq!(
+        fs::read_to_string(dir.path().join("README.md")).unwrap(),
+        "# Work in progress\n"
+    );
+    assert_eq!(
+        fs::read_to_string(dir.path().join("src/lib.rs")).unwrap(),
+        "pub const VERSION: u32 = 2;\n"
+    );
+
+    // Include patterns and subdirectories work as on disk
+    config.include = vec!["*.rs".to_string()];
+    config.git.reference = Some("HEAD".to_string());
+    let result = pack_repository(&dir.path().join("src"), &config)
+        .await
+        .unwrap();
+    assert_eq!(packed_paths(&result.files), ["lib.rs", "new.rs"]);
+
+    config.git.reference = Some("no-such-ref".to_string());
+    assert!(pack_repository(dir.path(), &config).await.is_err());
+}
+
+#[tokio::test]
+async fn test_pack_git_ref_changes() {
+    let dir = repo_with_release();
+
+    let mut config = Config::default();
+    config.git.reference = Some("HEAD".to_string());
+    config.git.since = Some("v1".to_string());
+    config.git.with_diff = true;
+    let result = pack_repository(dir.path(), &config).await.unwrap();
+
+    assert_eq!(packed_paths(&result.files), ["src/lib.rs", "src/new.rs"]);
+    let lib = result
+        .files
+        .iter()
+        .find(|file| file.relative_path.ends_with("lib.rs"))
+        .unwrap();
+    let diff = lib.diff.as_deref().unwrap();
+    assert!(diff.contains("-pub const VERSION: u32 = 1;"), "{}", diff);
+    assert!(diff.contains("+pub const VERSION: u32 = 2;"), "{}", diff);
+
+    // Only commits can be compared with a ref, not the index or working tree
+    config.git.working_tree = true;
+    assert!(pack_repository(dir.path(), &config).await.is_err());
+}
@@ -0,0 +1,142 @@
+mod common;
+
+use common::{git_commit, git_stage};
+use git2::{Repository, Signature, Time};
+use remix::config::{Config, GitConfig};
+use remix::history::collect_history;
+use remix::packer::pack_repository;
+use std::fs;
+use tempfile::{tempdir, TempDir};
+
+/// Commit the git index on top of HEAD as `author`, at `seconds` since the epoch
+fn commit_as(repo: &Repository, author: &str, seconds: i64, message: &str) {
+    let mut index = repo.index().unwrap();
+    let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+    let signature = Signature::new(author, "dev@example.com", &Time::new(seconds, 0)).unwrap();
+    let parent = repo.head().ok().and_then(|head| head.peel_to_commit().ok());
+    let parents: Vec<_> = parent.iter().collect();
+    repo.commit(
+        Some("HEAD"),
+        &signature,
+        &signature,
+        message,
+        &tree,
+        &parents,
+    )
+    .unwrap();
+}
+
+/// `src/hot.rs` changed in every commit, mostly by Alice; `src/cold.rs` and
+/// `README.md` only in the first one
+fn repo_with_authors() -> (TempDir, Repository) {
+    let dir = tempdir().unwrap();
+    let repo = Repository::init(dir.path()).unwrap();
+    fs::create_dir(dir.path().join("src")).unwrap();
+    fs::write(dir.path().join("README.md"), "# Test\n").unwrap();
+    fs::write(dir.path().join("src/cold.rs"), "fn cold() {}\n").unwrap();
+
+    let authors = ["Bob", "Alice", "Alice", "Carol"];
+    for (i, author) in authors.iter().enumerate() {
+        fs::write(
+            dir.path().join("src/hot.rs"),
+            format!("fn hot() -> u32 {{ {} }}\n", i),
+        )
+        .unwrap();
+        git_stage(&repo, "src/hot.rs");
+        if i == 0 {
+            git_stage(&repo, "src/cold.rs");
+            git_stage(&repo, "README.md");
+        }
+        // 2024-01-01 plus one day per commit
+        commit_as(
+            &repo,
+            author,
+            1_704_067_200 + i as i64 * 86_400,
+            &format!("Change {}\n\nDetails", i),
+        );
+    }
+
+    (dir, repo)
+}
+
+#[test]
+fn test_collect_history() {
+    let (dir, _repo) = repo_with_authors();
+    let config = GitConfig {
+        history_commits: 2,
+        top_contributors: 2,
+        ..Default::default()
+    };
+
+    let (history, files) = collect_history(dir.path(), &config).unwrap();
+    assert_eq!(history.recent_commits.len(), 2);
+    assert_eq!(history.recent_commits[0].message, "Change 3");
+    assert_eq!(history.recent_commits[0].author, "Carol");
+    assert_eq!(history.recent_commits[0].date, "2024-01-04T00:00:00Z");
+    assert_eq!(history.recent_commits[0].id.len(), 7);
+
+    let hot = &files["src/hot.rs"];
+    assert_eq!(hot.commit_count, 4);
+    assert_eq!(hot.last_commit, "2024-01-04T00:00:00Z");
+    let contributors: Vec<(&str, usize)> = hot
+        .top_contributors
+        .iter()
+        .map(|contributor| (contributor.name.as_str(), contributor.commits))
+        .collect();
+    assert_eq!(contributors, [("Alice", 2), ("Bob", 1)]);
+
+    let cold = &files["src/cold.rs"];
+    assert_eq!(cold.commit_count, 1);
+    assert_eq!(cold.last_commit, "2024-01-01T00:00:00Z");
+
+    // Paths are relative to the directory packed
+    let (_, files) = collect_history(&dir.path().join("src"), &GitConfig::default()).unwrap();
+    assert!(files.contains_key("hot.rs"));
+    assert!(!files.contains_key("README.md"));
+    assert!(files["hot.rs"].top_contributors.is_empty());
+
+    // From an older ref, later commits don't count
+    let config = GitConfig {
+        reference: Some("HEAD~2".to_string()),
+        ..Default::default()
+    };
+    let (history, files) = collect_history(dir.path(), &config).unwrap();
+    assert_eq!(history.recent_commits.len(), 2);
+    assert_eq!(files["src/hot.rs"].commit_count, 2);
+}
+
+#[tokio::test]
+async fn test_pack_with_git_history() {
+    let (dir, repo) = repo_with_authors();
+
+    let mut config = Config::default();
+    let result = pack_repository(dir.path(), &config).await.unwrap();
+    assert!(result.git_history.is_none());
+    assert!(result.files.iter().all(|file| file.history.is_none()));
+
+    // Untracked files have no history
+    fs::write(dir.path().join("src/new.rs"), "fn new() {}\n").unwrap();
+    config.git.history = true;
+    let result = pack_repository(dir.path(), &config).await.unwrap();
+    assert_eq!(result.git_history.unwrap().recent_commits.len(), 4);
+    let history = |name: &str| {
+        result
+            .files
+            .iter()
+            .find(|file| file.relative_path.replace('\\', "/") == name)
+            .unwrap()
+            .history
+            .clone()
+    };
+    assert_eq!(history("src/hot.rs").unwrap().commit_count, 4);
+    assert_eq!(history("README.md").unwrap().commit_count, 1);
+    assert!(history("src/new.rs").is_none());
+
+    git_stage(&repo, "src/new.rs");
+    git_commit(&repo, "Add new");
+    let result = pack_repository(dir.path(), &config).await.unwrap();
+    assert_eq!(
+        result.git_history.unwrap().recent_commits[0].message,
+        "Add new"
+    );
+}
@@ -0,0 +1,187 @@
+use remix::config::{SecretRuleConfig, SecurityConfig};
+use remix::packer::{PackedRepository, RepositorySummary};
+use remix::report::{json_report, output_security_report, sarif_report, ReportFormat};
+use remix::security::{SecretFinding, SecretScanner, SecurityCheckStatus};
+use std::fs;
+use std::path::Path;
+use tempfile::tempdir;
+
+fn finding(file: &str, line: usize, rule_id: &str) -> SecretFinding {
+    SecretFinding {
+        file: file.to_string(),
+        line,
+        column: 9,
+        rule_id: rule_id.to_string(),
+        fingerprint: format!("{}-{}", file, line),
+        snippet: "TOKEN = \"ghp_********\"".to_string(),
+    }
+}
+
+fn repo_with_findings(findings: Vec<SecretFinding>) -> PackedRepository {
+    PackedRepository {
+        files: Vec::new(),
+        summary: RepositorySummary {
+            file_count: 0,
+            directory_count: 0,
+            total_size: 0,
+            extensions: Vec::new(),
+            binary_file_count: 0,
+            total_tokens: 0,
+            token_encoding: "o200k_base".to_string(),
+            token_budget: None,
+        },
+        directory_tree: None,
+        instruction: None,
+        git_history: None,
+        remote: None,
+        security_findings: Some(findings),
+        security_check_status: SecurityCheckStatus::CompletedWithFindings,
+        on_disk_only_findings: None,
+        redactions: None,
+        skipped_sensitive_files: None,
+        binary_files: None,
+        omitted_files: None,
+    }
+}
+
+#[test]
+fn test_report_format() {
+    assert_eq!(
+        ReportFormat::from_path(Path::new("findings.sarif")),
+        ReportFormat::Sarif
+    );
+    assert_eq!(
+        ReportFormat::from_path(Path::new("out/findings.sarif.json")),
+        ReportFormat::Sarif
+    );
+    assert_eq!(
+        ReportFormat::from_path(Path::new("findings.json")),
+        ReportFormat::Json
+    );
+    assert_eq!(
+        ReportFormat::from_name("SARIF").unwrap(),
+        ReportFormat::Sarif
+    );
+    assert!(ReportFormat::from_name("csv").is_err());
+}
+
+#[test]
+fn test_sarif_report() {
+    let findings = vec![
+        finding("src/app.py", 2, "github-token"),
+        finding("src/auth.js", 7, "jwt"),
+        finding("src/app.py", 9, "github-token"),
+    ];
+    let report = sarif_report(&findings, &[], SecretScanner::builtin());
+
+    assert_eq!(report["version"], "2.1.0");
+    let run = &report["runs"][0];
+    assert_eq!(run["tool"]["driver"]["name"], "remix");
+
+    // Each rule is listed once, sorted by id
+    let rules = run["tool"]["driver"]["rules"].as_array().unwrap();
+    assert_eq!(rules.len(), 2);
+    assert_eq!(rules[0]["id"], "github-token");
+    assert_eq!(rules[1]["id"], "jwt");
+    assert!(rules[0]["help"]["text"]
+        .as_str()
+        .unwrap()
+        .contains("rotate"));
+
+    let results = run["results"].as_array().unwrap();
+    assert_eq!(results.len(), 3);
+    assert_eq!(results[0]["ruleId"], "github-token");
+    assert_eq!(results[0]["ruleIndex"], 0);
+    assert_eq!(results[0]["level"], "error");
+    assert_eq!(results[1]["ruleIndex"], 1);
+    assert_eq!(results[1]["level"], "warning");
+
+    let location = &results[2]["locations"][0]["physicalLocation"];
+    assert_eq!(location["artifactLocation"]["uri"], "src/app.py");
+    assert_eq!(location["region"]["startLine"], 9);
+    assert_eq!(location["region"]["startColumn"], 9);
+    assert_eq!(
+        results[2]["partialFingerprints"]["remixSecret/v1"],
+        "src/app.py-9"
+    );
+}
+
+#[test]
+fn test_json_report_uses_custom_rule_metadata() {
+    let mut config = SecurityConfig::default();
+    config.custom_rules.push(SecretRuleConfig {
+        id: "acme-token".to_string(),
+        regex: r"acme_[a-z0-9]{12}".to_string(),
+        description: Some("ACME API token".to_string()),
+        entropy: None,
+        keywords: Vec::new(),
+        severity: Some("low".to_string()),
+        remediation: Some("Rotate it in the ACME console.".to_string()),
+    });
+    let scanner = SecretScanner::from_config(&config).unwrap();
+
+    let report = json_report(&[finding("config.toml", 3, "acme-token")], &[], &scanner);
+    let entry = &report["findings"][0];
+    assert_eq!(entry["rule_id"], "acme-token");
+    assert_eq!(entry["description"], "ACME API token");
+    assert_eq!(entry["severity"], "low");
+    assert_eq!(entry["remediation"], "Rotate it in the ACME console.");
+    assert_eq!(entry["file"], "config.toml");
+    assert_eq!(entry["line"], 3);
+    assert_eq!(entry["fingerprint"], "config.toml-3");
+    assert_eq!(entry["on_disk_only"], false);
+}
+
+#[test]
+fn test_reports_label_on_disk_only_findings() {
+    let scanner = SecretScanner::builtin();
+    let packed = [finding("src/app.py", 2, "github-token")];
+    let on_disk_only = [finding("src/lib.rs", 1, "github-token")];
+
+    let report = json_report(&packed, &on_disk_only, scanner);
+    let findings = report["findings"].as_array().unwrap();
+    assert_eq!(findings.len(), 2);
+    assert_eq!(findings[0]["on_disk_only"], false);
+    assert_eq!(findings[1]["file"], "src/lib.rs");
+    assert_eq!(findings[1]["on_disk_only"], true);
+
+    let report = sarif_report(&packed, &on_disk_only, scanner);
+    let results = report["runs"][0]["results"].as_array().unwrap();
+    assert_eq!(results[0]["properties"]["onDiskOnly"], false);
+    assert_eq!(results[1]["properties"]["onDiskOnly"], true);
+    assert!(results[1]["message"]["text"]
+        .as_str()
+        .unwrap()
+        .contains("on disk only"));
+}
+
+#[test]
+fn test_output_security_report() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("secrets.sarif");
+    let repo = repo_with_findings(vec![finding("src/app.py", 2, "github-token")]);
+
+    // Nothing is written unless a report is requested
+    let mut config = SecurityConfig::default();
+    output_security_report(&repo, &config).unwrap();
+    assert!(!path.exists());
+
+    config.report = Some(path.to_string_lossy().to_string());
+    output_security_report(&repo, &config).unwrap();
+    let report: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+    assert_eq!(report["runs"][0]["results"].as_array().unwrap().len(), 1);
+
+    config.report_format = Some("json".to_string());
+    output_security_report(&repo, &config).unwrap();
+    let report: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+    assert_eq!(report["findings"].as_array().unwrap().len(), 1);
+
+    // Without a security check there is nothing to report
+    fs::remove_file(&path).unwrap();
+    let mut disabled = repo_with_findings(Vec::new());
+    disabled.security_check_status = SecurityCheckStatus::Disabled;
+    output_security_report(&disabled, &config).unwrap();
+    assert!(!path.exists());
+}
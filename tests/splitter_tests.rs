@@ -0,0 +1,169 @@
+use anyhow::Result;
+use remix::packer::{FileContent, PackedRepository, RepositorySummary};
+use remix::security::SecurityCheckStatus;
+use remix::splitter::{part_path, split_output, SplitLimit};
+use remix::tree::{DirectoryTree, TreeEntry};
+
+fn create_repo(file_count: usize, file_size: usize) -> PackedRepository {
+    let files: Vec<FileContent> = (0..file_count)
+        .map(|i| FileContent {
+            relative_path: format!("src/file{}.rs", i),
+            content: "x".repeat(file_size),
+            size: file_size as u64,
+            extension: "rs".to_string(),
+            is_binary: false,
+            token_count: file_size / 4,
+            diff: None,
+            history: None,
+        })
+        .collect();
+    let tree_entries = files
+        .iter()
+        .map(|f| TreeEntry::new(&f.relative_path))
+        .collect();
+
+    PackedRepository {
+        summary: RepositorySummary {
+            file_count,
+            directory_count: 1,
+            total_size: (file_count * file_size) as u64,
+            extensions: vec!["rs".to_string()],
+            binary_file_count: 0,
+            total_tokens: file_count * file_size / 4,
+            token_encoding: "heuristic".to_string(),
+            token_budget: None,
+        },
+        files,
+        directory_tree: Some(DirectoryTree::new(tree_entries, None)),
+        instruction: None,
+        git_history: None,
+        remote: None,
+        security_findings: None,
+        security_check_status: SecurityCheckStatus::CompletedNoFindings,
+        on_disk_only_findings: None,
+        redactions: None,
+        skipped_sensitive_files: None,
+        binary_files: None,
+        omitted_files: None,
+    }
+}
+
+#[test]
+fn test_parse_split_limit() {
+    assert_eq!(
+        SplitLimit::parse("500kb").unwrap(),
+        SplitLimit::Bytes(500 * 1024)
+    );
+    assert_eq!(
+        SplitLimit::parse("2MB").unwrap(),
+        SplitLimit::Bytes(2 * 1024 * 1024)
+    );
+    assert_eq!(SplitLimit::parse("1000").unwrap(), SplitLimit::Bytes(1000));
+    assert_eq!(
+        SplitLimit::parse("100k-tokens").unwrap(),
+        SplitLimit::Tokens(100_000)
+    );
+    assert_eq!(
+        SplitLimit::parse("50000tokens").unwrap(),
+        SplitLimit::Tokens(50_000)
+    );
+    assert!(SplitLimit::parse("lots").is_err());
+    assert!(SplitLimit::parse("0kb").is_err());
+    assert!(SplitLimit::parse("99999999999999999mb").is_err());
+    assert!(SplitLimit::parse("99999999999999999m-tokens").is_err());
+}
+
+#[test]
+fn test_part_path() {
+    assert_eq!(part_path("remix-output.md", 1), "remix-output.part1.md");
+    assert_eq!(part_path("./out/pack.json", 3), "./out/pack.part3.json");
+    assert_eq!(part_path("pack", 2), "pack.part2");
+}
+
+#[test]
+fn test_split_markdown_into_parts() -> Result<()> {
+    let repo = create_repo(10, 1000);
+    let parts = split_output(&repo, "md", SplitLimit::Bytes(4000))?;
+
+    assert!(parts.len() > 1, "expected multiple parts");
+
+    for (i, part) in parts.iter().enumerate() {
+        assert!(part.contains(&format!("Part {} of {}", i + 1, parts.len())));
+        assert!(part.contains("# Directory Structure"));
+        assert!(
+            part.contains("file9.rs"),
+            "every part carries the full tree"
+        );
+        assert!(part.len() <= 4000);
+    }
+
+    assert!(parts[0].contains("# Repository Summary"));
+    assert!(parts[0].contains("## Security Check"));
+    assert!(!parts[1].contains("# Repository Summary"));
+
+    // Every file appears in exactly one part
+    for i in 0..10 {
+        let header = format!("- **Path:** src/file{}.rs", i);
+        assert_eq!(parts.iter().filter(|p| p.contains(&header)).count(), 1);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_split_small_repo_is_single_part() -> Result<()> {
+    let repo = create_repo(2, 10);
+    let parts = split_output(&repo, "txt", SplitLimit::Bytes(1024 * 1024))?;
+    assert_eq!(parts.len(), 1);
+    assert!(parts[0].contains("PART 1 OF 1"));
+    Ok(())
+}
+
+#[test]
+fn test_split_json_by_tokens() -> Result<()> {
+    let repo = create_repo(6, 2000);
+    let parts = split_output(&repo, "json", SplitLimit::Tokens(1500))?;
+
+    assert!(parts.len() > 1);
+
+    let first: serde_json::Value = serde_json::from_str(&parts[0])?;
+    assert_eq!(first["part"], 1);
+    assert_eq!(first["total_parts"], parts.len());
+    assert!(first["summary"].is_object());
+
+    let second: serde_json::Value = serde_json::from_str(&parts[1])?;
+    assert_eq!(second["part"], 2);
+    assert!(second["summary"].is_null());
+    assert!(second["directory_tree"]
+        .as_str()
+        .unwrap()
+        .contains("file0.rs"));
+
+    let total_files: usize = parts
+        .iter()
+        .map(|p| {
+            let value: serde_json::Value = serde_json::from_str(p).unwrap();
+            value["files"].as_array().unwrap().len()
+        })
+        .sum();
+    assert_eq!(total_files, 6);
+
+    Ok(())
+}
+
+#[test]
+fn test_split_xml_into_parts() -> Result<()> {
+    let repo = create_repo(8, 1000);
+    let parts = split_output(&repo, "xml", SplitLimit::Bytes(4000))?;
+
+    assert!(parts.len() > 1);
+    assert!(parts[0].contains("<file_summary>"));
+    assert!(!parts[1].contains("<file_summary>"));
+
+    for part in &parts {
+        assert!(part.contains("<directory_structure>"));
+        assert!(part.contains("<files>"));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,67 @@
+use remix::tokenizer::{estimate_tokens, TokenEncoding, Tokenizer};
+
+#[test]
+fn test_token_encoding_from_name() {
+    assert_eq!(
+        TokenEncoding::from_name("o200k_base").unwrap(),
+        TokenEncoding::O200kBase
+    );
+    assert_eq!(
+        TokenEncoding::from_name("CL100K_BASE").unwrap(),
+        TokenEncoding::Cl100kBase
+    );
+    assert_eq!(
+        TokenEncoding::from_name("heuristic").unwrap(),
+        TokenEncoding::Heuristic
+    );
+    assert!(TokenEncoding::from_name("gpt2").is_err());
+}
+
+#[test]
+fn test_bpe_token_counts() {
+    let cl100k = Tokenizer::new(TokenEncoding::Cl100kBase).unwrap();
+    let o200k = Tokenizer::new(TokenEncoding::O200kBase).unwrap();
+
+    // Reference counts from the tiktoken Python package
+    assert_eq!(cl100k.count("hello world"), 2);
+    assert_eq!(o200k.count("hello world"), 2);
+    assert_eq!(cl100k.count(""), 0);
+    assert_eq!(o200k.count(""), 0);
+}
+
+#[test]
+fn test_estimate_tokens() {
+    assert_eq!(estimate_tokens(""), 0);
+    assert_eq!(estimate_tokens("hello"), 1);
+    assert_eq!(estimate_tokens("fn main() {}"), 4);
+    assert_eq!(estimate_tokens("a\nb\n"), 2);
+    assert_eq!(estimate_tokens("日本"), 2);
+}
+
+#[test]
+fn test_estimate_close_to_bpe_for_code() {
+    let code = r#"use std::collections::HashMap;
+
+/// Count word frequencies in a text
+pub fn word_counts(text: &str) -> HashMap<String, usize> {
+    let mut counts = HashMap::new();
+    for word in text.split_whitespace() {
+        *counts.entry(word.to_lowercase()).or_insert(0) += 1;
+    }
+    counts
+}
+"#;
+
+    let bpe = Tokenizer::new(TokenEncoding::Cl100kBase)
+        .unwrap()
+        .count(code);
+    let estimate = estimate_tokens(code);
+    let ratio = estimate as f64 / bpe as f64;
+
+    assert!(
+        (0.75..=1.25).contains(&ratio),
+        "Estimate {} too far from BPE count {}",
+        estimate,
+        bpe
+    );
+}
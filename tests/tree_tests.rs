@@ -0,0 +1,90 @@
+use remix::tree::{DirectoryTree, TreeEntry};
+
+fn tree_from_paths(paths: &[&str], max_depth: Option<usize>) -> DirectoryTree {
+    DirectoryTree::new(paths.iter().map(|p| TreeEntry::new(p)).collect(), max_depth)
+}
+
+#[test]
+fn test_render_tree() {
+    let tree = tree_from_paths(
+        &["src/main.rs", "README.md", "src/utils/mod.rs", "Cargo.toml"],
+        None,
+    );
+
+    let expected = "\
+.
+├── src/
+│   ├── utils/
+│   │   └── mod.rs
+│   └── main.rs
+├── Cargo.toml
+└── README.md
+";
+
+    assert_eq!(tree.render(), expected);
+}
+
+#[test]
+fn test_render_tree_empty() {
+    assert_eq!(tree_from_paths(&[], None).render(), ".\n");
+}
+
+#[test]
+fn test_render_indented_tree() {
+    let tree = tree_from_paths(&["src/main.rs", "README.md", "src/utils/mod.rs"], None);
+
+    let expected = "\
+src/
+  utils/
+    mod.rs
+  main.rs
+README.md
+";
+
+    assert_eq!(tree.render_indented(), expected);
+}
+
+#[test]
+fn test_render_tree_max_depth() {
+    let tree = tree_from_paths(
+        &["src/main.rs", "src/utils/mod.rs", "src/utils/io.rs", "README.md"],
+        Some(2),
+    );
+
+    let expected = "\
+.
+├── src/
+│   ├── utils/ (2 files)
+│   └── main.rs
+└── README.md
+";
+
+    assert_eq!(tree.render(), expected);
+
+    let shallow = tree_from_paths(&["src/main.rs", "src/utils/mod.rs"], Some(1));
+    assert_eq!(shallow.render(), ".\n└── src/ (2 files)\n");
+}
+
+#[test]
+fn test_render_tree_markers() {
+    let tree = DirectoryTree::new(
+        vec![
+            TreeEntry::new("src/main.rs"),
+            TreeEntry::marked("assets/logo.png", "binary"),
+            TreeEntry::marked("src/secrets.rs", "skipped"),
+        ],
+        None,
+    );
+
+    let rendered = tree.render();
+    assert!(rendered.contains("logo.png [binary]"));
+    assert!(rendered.contains("secrets.rs [skipped]"));
+    assert!(rendered.contains("main.rs\n"));
+}
+
+#[test]
+fn test_tree_serializes_as_string() {
+    let tree = tree_from_paths(&["a.txt"], None);
+    let value = serde_json::to_value(&tree).unwrap();
+    assert_eq!(value, serde_json::json!(".\n└── a.txt\n"));
+}